@@ -0,0 +1,156 @@
+//! Fetches the lexicons that generated lexicons reference but don't include.
+
+use crate::resolve::{LexiconResolver, ResolveError, ResolvedLexicon};
+use atrium_api::types::string::Nsid;
+use atrium_identity::handle::DnsTxtResolver;
+use atrium_lex::LexiconDoc;
+use atrium_xrpc::HttpClient;
+use esquema_codegen::ExternalCrates;
+use esquema_validator::{DanglingRef, LexiconRegistry};
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt::{self, Display};
+
+/// Where missing lexicons are looked for, in order.
+pub struct DependencySources<'a, D, H> {
+    /// Lexicons from the repo being generated from, including ones outside the generated namespace
+    pub same_repo: Option<&'a LexiconRegistry>,
+    /// Lexicons loaded from a local directory
+    pub fallback: Option<&'a LexiconRegistry>,
+    /// Resolves lexicons of other authorities through their `_lexicon` DNS record
+    pub resolver: Option<&'a LexiconResolver<D, H>>,
+    /// Lexicons provided by other crates, which are never fetched
+    pub externals: &'a ExternalCrates,
+}
+
+/// The lexicons to generate, the given ones along with everything they reference.
+#[derive(Debug)]
+pub struct Dependencies {
+    pub docs: Vec<LexiconDoc>,
+    /// The dependencies resolved through DNS, whose schema records can be pinned in a lockfile
+    pub resolved: Vec<ResolvedLexicon>,
+}
+
+/// References that could not be satisfied by any source.
+#[derive(Debug)]
+pub struct UnresolvedRefs {
+    pub refs: Vec<DanglingRef>,
+    /// Why resolving each missing NSID through DNS failed
+    pub errors: Vec<(String, ResolveError)>,
+}
+
+impl Display for UnresolvedRefs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} reference(s) could not be resolved", self.refs.len())?;
+        for dangling in &self.refs {
+            write!(
+                f,
+                "\n  `{}` references `{}` at {}",
+                dangling.from, dangling.r#ref, dangling.path
+            )?;
+        }
+        for (nsid, error) in &self.errors {
+            write!(f, "\n  {nsid}: {error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for UnresolvedRefs {}
+
+/// Adds every lexicon transitively referenced by `docs`, returning them along with `docs`.
+pub async fn fetch_dependencies<D, H>(
+    docs: Vec<LexiconDoc>,
+    sources: &DependencySources<'_, D, H>,
+) -> Result<Dependencies, UnresolvedRefs>
+where
+    D: DnsTxtResolver,
+    H: HttpClient + Send + Sync + 'static,
+{
+    let mut registry = LexiconRegistry::from_docs(docs);
+    let mut attempted = BTreeSet::new();
+    let mut resolved = Vec::new();
+    let mut errors = Vec::new();
+    loop {
+        let missing = unresolved(&registry, sources.externals)
+            .into_iter()
+            .map(|dangling| ref_nsid(&dangling.r#ref).to_string())
+            .filter(|nsid| !registry.contains(nsid) && attempted.insert(nsid.clone()))
+            .collect::<BTreeSet<_>>();
+        if missing.is_empty() {
+            break;
+        }
+        for nsid in missing {
+            match fetch(&nsid, sources, &mut errors).await {
+                Some(Fetched::Found(doc)) => {
+                    registry.insert(doc);
+                }
+                Some(Fetched::Resolved(lexicon)) => {
+                    registry.insert(lexicon.doc.clone());
+                    resolved.push(lexicon);
+                }
+                None => {}
+            }
+        }
+    }
+
+    let refs = unresolved(&registry, sources.externals);
+    if refs.is_empty() {
+        Ok(Dependencies {
+            docs: registry.into_docs(),
+            resolved,
+        })
+    } else {
+        Err(UnresolvedRefs { refs, errors })
+    }
+}
+
+enum Fetched {
+    /// From the same repo or the fallback directory
+    Found(LexiconDoc),
+    Resolved(ResolvedLexicon),
+}
+
+async fn fetch<D, H>(
+    nsid: &str,
+    sources: &DependencySources<'_, D, H>,
+    errors: &mut Vec<(String, ResolveError)>,
+) -> Option<Fetched>
+where
+    D: DnsTxtResolver,
+    H: HttpClient + Send + Sync + 'static,
+{
+    if let Some(doc) = sources.same_repo.and_then(|repo| repo.get(nsid)) {
+        log::info!("Found dependency {nsid} in the same repo");
+        return Some(Fetched::Found(doc.clone()));
+    }
+    if let Some(doc) = sources.fallback.and_then(|fallback| fallback.get(nsid)) {
+        log::info!("Found dependency {nsid} in the fallback directory");
+        return Some(Fetched::Found(doc.clone()));
+    }
+    let resolver = sources.resolver?;
+    let parsed = Nsid::new(nsid.to_string()).ok()?;
+    match resolver.resolve(&parsed).await {
+        Ok(lexicon) => {
+            log::info!("Resolved dependency {nsid} from {}", lexicon.uri);
+            Some(Fetched::Resolved(lexicon))
+        }
+        Err(error) => {
+            errors.push((nsid.to_string(), error));
+            None
+        }
+    }
+}
+
+fn unresolved(registry: &LexiconRegistry, externals: &ExternalCrates) -> Vec<DanglingRef> {
+    registry
+        .dangling_refs()
+        .into_iter()
+        .filter(|dangling| !externals.contains(ref_nsid(&dangling.r#ref)))
+        .collect()
+}
+
+/// The NSID part of a fully qualified `nsid#def` reference.
+fn ref_nsid(r#ref: &str) -> &str {
+    r#ref.split_once('#').map_or(r#ref, |(nsid, _)| nsid)
+}
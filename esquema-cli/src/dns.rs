@@ -0,0 +1,31 @@
+use atrium_identity::handle::DnsTxtResolver;
+use hickory_resolver::TokioAsyncResolver;
+
+/// Looks up TXT records with the system's resolver configuration.
+pub struct HickoryDnsTxtResolver {
+    resolver: TokioAsyncResolver,
+}
+
+impl Default for HickoryDnsTxtResolver {
+    fn default() -> Self {
+        Self {
+            resolver: TokioAsyncResolver::tokio_from_system_conf()
+                .expect("failed to create resolver"),
+        }
+    }
+}
+
+impl DnsTxtResolver for HickoryDnsTxtResolver {
+    async fn resolve(
+        &self,
+        query: &str,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        Ok(self
+            .resolver
+            .txt_lookup(query)
+            .await?
+            .iter()
+            .map(|txt| txt.to_string())
+            .collect())
+    }
+}
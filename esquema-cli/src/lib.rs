@@ -0,0 +1,6 @@
+//! The pieces of esquema-cli that are useful outside of the binary.
+
+pub mod deps;
+pub mod dns;
+pub mod lockfile;
+pub mod resolve;
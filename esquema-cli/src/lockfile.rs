@@ -0,0 +1,241 @@
+//! `esquema.lock`, pinning the content of remotely fetched lexicons between generations.
+
+use atrium_lex::LexiconDoc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+pub const LOCKFILE_NAME: &str = "esquema.lock";
+
+/// How fetched lexicons are compared against the lockfile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    /// New lexicons are added, changed content is an error
+    Default,
+    /// Any difference from the lockfile is an error, including added or removed lexicons
+    Locked,
+    /// The lockfile is rewritten from what was fetched
+    Update,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    #[serde(rename = "lexicon", default)]
+    pub lexicons: Vec<LockedLexicon>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedLexicon {
+    pub nsid: String,
+    /// The `at://` URI of the schema record, if it was fetched from a repo
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cid: Option<String>,
+    /// `sha256:` of the lexicon's JSON with keys sorted
+    pub hash: String,
+}
+
+impl LockedLexicon {
+    pub fn new(doc: &LexiconDoc, uri: Option<String>, cid: Option<String>) -> Self {
+        Self {
+            nsid: doc.id.clone(),
+            uri,
+            cid,
+            hash: content_hash(doc),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LockError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    Serialize(toml::ser::Error),
+    /// Fetched lexicons don't match the lockfile
+    Mismatch {
+        changed: Vec<String>,
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+}
+
+impl Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockError::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            LockError::Parse { path, source } => {
+                write!(f, "{} is not a valid lockfile: {source}", path.display())
+            }
+            LockError::Serialize(source) => write!(f, "failed to write the lockfile: {source}"),
+            LockError::Mismatch {
+                changed,
+                added,
+                removed,
+            } => {
+                write!(f, "fetched lexicons differ from {LOCKFILE_NAME}")?;
+                for nsid in changed {
+                    write!(f, "\n  changed: {nsid}")?;
+                }
+                for nsid in added {
+                    write!(f, "\n  added: {nsid}")?;
+                }
+                for nsid in removed {
+                    write!(f, "\n  removed: {nsid}")?;
+                }
+                write!(
+                    f,
+                    "\nrun `esquema-cli generate update` to accept the new content"
+                )
+            }
+        }
+    }
+}
+
+impl Error for LockError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LockError::Io { source, .. } => Some(source),
+            LockError::Parse { source, .. } => Some(source),
+            LockError::Serialize(source) => Some(source),
+            LockError::Mismatch { .. } => None,
+        }
+    }
+}
+
+impl Lockfile {
+    /// Reads the lockfile at `path`, which is empty if the file doesn't exist yet.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, LockError> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content).map_err(|source| LockError::Parse {
+                path: path.to_path_buf(),
+                source,
+            }),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(source) => Err(LockError::Io {
+                path: path.to_path_buf(),
+                source,
+            }),
+        }
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), LockError> {
+        let path = path.as_ref();
+        let content = toml::to_string(self).map_err(LockError::Serialize)?;
+        fs::write(path, content).map_err(|source| LockError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    pub fn get(&self, nsid: &str) -> Option<&LockedLexicon> {
+        self.lexicons.iter().find(|locked| locked.nsid == nsid)
+    }
+
+    /// Applies `fetched` to the lockfile at `path`, returning the lockfile to write.
+    ///
+    /// Only `None` when nothing was fetched in the default mode, as there is nothing to record then.
+    /// `--locked` still compares, so lexicons no longer fetched are reported as removed.
+    pub fn read_and_apply(
+        path: impl AsRef<Path>,
+        fetched: Vec<LockedLexicon>,
+        mode: LockMode,
+    ) -> Result<Option<Self>, LockError> {
+        if fetched.is_empty() && mode == LockMode::Default {
+            return Ok(None);
+        }
+        Self::read(path)?.apply(fetched, mode).map(Some)
+    }
+
+    /// Compares `fetched` with the lockfile, returning the lockfile to write when `mode` allows the differences.
+    pub fn apply(&self, fetched: Vec<LockedLexicon>, mode: LockMode) -> Result<Self, LockError> {
+        let fetched = fetched
+            .into_iter()
+            .map(|locked| (locked.nsid.clone(), locked))
+            .collect::<BTreeMap<_, _>>();
+        if mode == LockMode::Update {
+            return Ok(Self {
+                lexicons: fetched.into_values().collect(),
+            });
+        }
+
+        let mut changed = Vec::new();
+        let mut added = Vec::new();
+        for (nsid, locked) in &fetched {
+            match self.get(nsid) {
+                Some(existing) if existing.hash != locked.hash => changed.push(nsid.clone()),
+                Some(_) => {}
+                None => added.push(nsid.clone()),
+            }
+        }
+        let removed = self
+            .lexicons
+            .iter()
+            .filter(|locked| !fetched.contains_key(&locked.nsid))
+            .map(|locked| locked.nsid.clone())
+            .collect::<Vec<_>>();
+
+        let allowed = match mode {
+            LockMode::Locked => changed.is_empty() && added.is_empty() && removed.is_empty(),
+            _ => changed.is_empty(),
+        };
+        if !allowed {
+            return Err(LockError::Mismatch {
+                changed,
+                added,
+                removed,
+            });
+        }
+
+        // keep entries for lexicons this run didn't fetch, other generations may rely on them
+        let mut lexicons = self
+            .lexicons
+            .iter()
+            .map(|locked| (locked.nsid.clone(), locked.clone()))
+            .collect::<BTreeMap<_, _>>();
+        for nsid in added {
+            lexicons.insert(nsid.clone(), fetched[&nsid].clone());
+        }
+        Ok(Self {
+            lexicons: lexicons.into_values().collect(),
+        })
+    }
+}
+
+/// `sha256:<hex>` of the lexicon serialized as JSON, with object keys sorted so the hash is stable.
+pub fn content_hash(doc: &LexiconDoc) -> String {
+    let value = serde_json::to_value(doc).expect("lexicon documents serialize to JSON");
+    let bytes = serde_json::to_vec(&sort_keys(value)).expect("JSON values serialize");
+    format!("sha256:{:x}", Sha256::digest(bytes))
+}
+
+// defs is a HashMap, and serde_json may be built with preserve_order, so sort explicitly
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries = map.into_iter().collect::<Vec<_>>();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, sort_keys(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(sort_keys).collect()),
+        other => other,
+    }
+}
@@ -1,5 +1,7 @@
 use anyhow::anyhow;
-use atrium_api::types::string::Nsid;
+use atrium_api::com::atproto::repo::list_records;
+use atrium_api::types::LimitedNonZeroU8;
+use atrium_api::types::string::{Did, Nsid};
 use atrium_api::{
     agent::atp_agent::AtpAgent, agent::atp_agent::store::MemorySessionStore,
     types::string::AtIdentifier,
@@ -7,15 +9,27 @@ use atrium_api::{
 use atrium_common::resolver::Resolver;
 use atrium_identity::{
     did::{CommonDidResolver, CommonDidResolverConfig, DEFAULT_PLC_DIRECTORY_URL},
-    handle::{AtprotoHandleResolver, AtprotoHandleResolverConfig, DnsTxtResolver},
+    handle::{AtprotoHandleResolver, AtprotoHandleResolverConfig},
 };
 use atrium_lex::LexiconDoc;
 use atrium_oauth::DefaultHttpClient;
 use atrium_xrpc_client::reqwest::ReqwestClient;
-use clap::{Parser, Subcommand};
-use esquema_codegen::{gen_from_lexicon_docs, genapi};
-use hickory_resolver::TokioAsyncResolver;
-use std::{fs, path::PathBuf, str::FromStr, sync::Arc};
+use clap::{Args, Parser, Subcommand};
+use esquema_cli::deps::{DependencySources, fetch_dependencies};
+use esquema_cli::dns::HickoryDnsTxtResolver;
+use esquema_cli::lockfile::{LOCKFILE_NAME, LockMode, LockedLexicon, Lockfile};
+use esquema_cli::resolve::{
+    LexiconResolver, LexiconResolverConfig, ResolvedLexicon, parse_lexicon_doc, pds_endpoint,
+};
+use esquema_codegen::config::{CONFIG_FILE_NAME, Config, Source};
+use esquema_codegen::{
+    CodegenError, ExternalCrates, Generator, gen_from_lexicon_docs_with_config, read_lexdir,
+};
+use esquema_server::mock::MockServer;
+use esquema_validator::LexiconRegistry;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::{fs, str::FromStr, sync::Arc};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -29,6 +43,8 @@ struct Cli {
 enum Commands {
     /// Generates rust types from ATProto lexicons
     Generate(Generate),
+    /// Serves the queries and procedures of local lexicons with validated inputs and made up outputs
+    MockServe(MockServe),
 }
 
 #[derive(Parser, Debug)]
@@ -48,6 +64,12 @@ enum GenerateCommands {
     Local(LocalGenerate),
     /// Generates rust types from a remote AT Protocol Lexicon schema record
     Remote(RepoGenerate),
+    /// Generates rust types from lexicons found by their NSID's `_lexicon` DNS record
+    Resolve(ResolveGenerate),
+    /// Re-fetches remote lexicons, regenerates and rewrites the lockfile with their current content
+    Update(UpdateGenerate),
+    /// Generates rust types as described by an esquema.toml
+    Config(ConfigGenerate),
 }
 
 #[derive(Parser, Debug)]
@@ -65,21 +87,24 @@ struct LocalGenerate {
     /// If set, the output is a module instead of a library
     #[arg(short, long)]
     module: Option<String>,
+    /// Map an NSID prefix to an existing crate path instead of generating it, e.g. `com.example=example_api::com::example`.
+    /// com.atproto, app.bsky, chat.bsky and tools.ozone map to atrium_api unless overridden
+    #[arg(long = "external", value_name = "PREFIX=PATH", value_parser = parse_external)]
+    externals: Vec<(String, String)>,
+    /// Also generate `server.rs`, with a `Handler` trait and axum router for the queries and procedures
+    #[arg(long)]
+    server: bool,
 }
 
 fn local_generate_action(args: &LocalGenerate) -> anyhow::Result<()> {
-    let results =
-        genapi(&args.lexdir, &args.outdir, &args.module).map_err(|e| anyhow!(e.to_string()))?;
-
-    for path in &results {
-        log::info!(
-            "{} ({} bytes)",
-            path.as_ref().display(),
-            fs::metadata(path.as_ref())?.len()
-        );
-    }
-
-    Ok(())
+    let lexicon_docs = read_lexdir(&args.lexdir).map_err(|err| anyhow!(err.to_string()))?;
+    generate_docs(
+        lexicon_docs,
+        &args.outdir,
+        &args.module,
+        &external_crates(&args.externals),
+        args.server,
+    )
 }
 
 #[derive(Parser, Debug)]
@@ -88,6 +113,26 @@ fn local_generate_action(args: &LocalGenerate) -> anyhow::Result<()> {
     about = "Generates Rust types from remote Lexicon ATProto Lexicon schema records"
 )]
 struct RepoGenerate {
+    #[command(flatten)]
+    repo: RepoArgs,
+    /// Fail if the fetched lexicons differ in any way from the lockfile
+    #[arg(long)]
+    locked: bool,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "update",
+    about = "Re-fetches remote Lexicon schema records and rewrites the lockfile with their current content"
+)]
+struct UpdateGenerate {
+    #[command(flatten)]
+    repo: RepoArgs,
+}
+
+/// The repo lexicons are fetched from and where they are generated, shared by `remote` and `update`
+#[derive(Args, Debug)]
+struct RepoArgs {
     /// The owner of the PDS repo
     #[arg(long)]
     handle: String,
@@ -105,10 +150,66 @@ struct RepoGenerate {
     /// If set, the output is a module instead of a library
     #[arg(short, long)]
     module: Option<String>,
+    /// A directory of lexicon JSON files to take referenced lexicons from before resolving them remotely
+    #[arg(long)]
+    fallback_dir: Option<PathBuf>,
+    /// Map an NSID prefix to an existing crate path instead of generating it, e.g. `com.example=example_api::com::example`.
+    /// com.atproto, app.bsky, chat.bsky and tools.ozone map to atrium_api unless overridden
+    #[arg(long = "external", value_name = "PREFIX=PATH", value_parser = parse_external)]
+    externals: Vec<(String, String)>,
+    /// Where the hashes of fetched lexicons are recorded
+    #[arg(long, default_value = LOCKFILE_NAME)]
+    lockfile: PathBuf,
+    /// Also generate `server.rs`, with a `Handler` trait and axum router for the queries and procedures
+    #[arg(long)]
+    server: bool,
 }
 
 /// Generates local Rust types from AT Protocol lexicon schema records
-async fn generate_from_record_action(args: &RepoGenerate) -> anyhow::Result<()> {
+async fn generate_from_record_action(args: &RepoArgs, mode: LockMode) -> anyhow::Result<()> {
+    let mut repo = fetch_repo_lexicons(&args.handle, &args.collection, &args.namespace).await?;
+    let externals = external_crates(&args.externals);
+    let lexicon_docs = with_dependencies(
+        repo.docs,
+        Some(&repo.registry),
+        args.fallback_dir.as_deref(),
+        &externals,
+        &mut repo.sources,
+    )
+    .await?;
+
+    let fetched = locked_lexicons(&lexicon_docs, &repo.sources);
+    // checked before generating, so a mismatch leaves the existing types alone
+    let lockfile = Lockfile::read(&args.lockfile)?.apply(fetched, mode)?;
+
+    generate_docs(
+        lexicon_docs,
+        &args.outdir,
+        &args.module,
+        &externals,
+        args.server,
+    )?;
+    lockfile.write(&args.lockfile)?;
+    log::info!("Wrote {}", args.lockfile.display());
+
+    Ok(())
+}
+
+/// The lexicon schema records of a repo
+struct RepoLexicons {
+    /// Lexicons under the requested namespace
+    docs: Vec<LexiconDoc>,
+    /// Every lexicon in the repo, so refs outside the namespace can be found without another lookup
+    registry: LexiconRegistry,
+    /// NSID to the record's URI and CID
+    sources: HashMap<String, (String, Option<String>)>,
+}
+
+async fn fetch_repo_lexicons(
+    handle: &str,
+    collection: &str,
+    namespace: &str,
+) -> anyhow::Result<RepoLexicons> {
     // Currently just constructing in this command but may move to an app state with DI?
     // Seems like over kill unless it ends up being used else where
     let http_client = Arc::new(DefaultHttpClient::default());
@@ -124,121 +225,434 @@ async fn generate_from_record_action(args: &RepoGenerate) -> anyhow::Result<()>
         http_client: Arc::clone(&http_client),
     });
 
-    let handle = atrium_api::types::string::Handle::from_str(args.handle.as_str())
-        .map_err(|e| anyhow!(e.to_string()))?;
+    let handle =
+        atrium_api::types::string::Handle::from_str(handle).map_err(|e| anyhow!(e.to_string()))?;
     let did = handle_resolver.resolve(&handle).await?;
 
     let resolved_did = did_resolver.resolve(&did).await?;
-    let pds_url = resolved_did
-        .service
-        .as_ref()
-        .and_then(|services| {
-            services
-                .iter()
-                .find(|service| service.r#type == "AtprotoPersonalDataServer")
-                .map(|service| service.service_endpoint.clone())
-        })
+    let pds_url = pds_endpoint(&resolved_did)
         .ok_or_else(|| anyhow!("No valid PDS URL found for this DID"))?;
 
     //This endpoint needs your PDS endpoint, for example mine is "https://coral.us-east.host.bsky.network"
     let agent = AtpAgent::new(ReqwestClient::new(pds_url), MemorySessionStore::default());
-    let records = agent
-        .api
-        .com
-        .atproto
-        .repo
-        .list_records(
-            atrium_api::com::atproto::repo::list_records::ParametersData {
-                collection: Nsid::new(args.collection.clone()).map_err(|e| anyhow!(e))?,
-                cursor: None,
-                limit: None,
-                repo: AtIdentifier::Did(did.clone()),
-                reverse: None,
+    let collection_nsid = Nsid::new(collection.to_string()).map_err(|e| anyhow!(e))?;
+    let records = list_all_records(&agent, &did, &collection_nsid).await?;
+
+    let record_uri_prefix = format!("at://{}/{}/{}", did.as_str(), collection, namespace);
+    let mut repo = RepoLexicons {
+        docs: Vec::new(),
+        registry: LexiconRegistry::new(),
+        sources: HashMap::new(),
+    };
+    let mut skipped = 0;
+    for record in &records {
+        let matched = record.uri.starts_with(record_uri_prefix.as_str());
+        let parsed = parse_lexicon_doc(&record.data.value);
+        if let Ok(doc) = &parsed {
+            repo.sources.insert(
+                doc.id.clone(),
+                (record.uri.clone(), Some(record.cid.as_ref().to_string())),
+            );
+        }
+        match parsed {
+            Ok(doc) if matched => {
+                repo.registry.insert(doc.clone());
+                repo.docs.push(doc);
+            }
+            Ok(doc) => {
+                skipped += 1;
+                repo.registry.insert(doc);
+            }
+            Err(_) if !matched => skipped += 1,
+            Err(err) => {
+                skipped += 1;
+                log::debug!("{:?}", err);
+                log::error!(
+                    "There was an error in deserializing the ATProto record found {}. Is it a valid lexicon schema record?",
+                    record.uri
+                );
             }
-            .into(),
-        )
-        .await?;
-
-    let record_uri_prefix = format!(
-        "at://{}/{}/{}",
-        did.as_str(),
-        args.collection,
-        args.namespace,
+        }
+    }
+    log::info!(
+        "Scanned {} records: {} matched, {} skipped",
+        records.len(),
+        repo.docs.len(),
+        skipped
     );
-    let mut lexicon_docs: Vec<LexiconDoc> = Vec::new();
-    for ref record in &records.records {
-        if record.uri.starts_with(record_uri_prefix.as_str()) {
-            //HACK (slightly)
-            //We are using serde_json directly instead of the below because it currently uses .unwrap
-            //and we want a friendlier error
-            // let doc_result = LexiconDoc::try_from_unknown(record.data.value.clone());
-            let json = serde_json::to_vec(&record.data.value)?;
-            match serde_json::from_slice::<LexiconDoc>(&json) {
-                Ok(doc) => {
-                    lexicon_docs.push(doc);
+    Ok(repo)
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "resolve",
+    about = "Generates Rust types from lexicons resolved by NSID through DNS"
+)]
+struct ResolveGenerate {
+    /// The NSIDs of the lexicons to generate, for example xyz.statusphere.status
+    #[arg(required = true)]
+    nsids: Vec<String>,
+    /// The output directory for the rust files, if not there, it will create the folder
+    #[arg(short, long)]
+    outdir: PathBuf,
+    /// If set, the output is a module instead of a library
+    #[arg(short, long)]
+    module: Option<String>,
+    /// A directory of lexicon JSON files to take referenced lexicons from before resolving them remotely
+    #[arg(long)]
+    fallback_dir: Option<PathBuf>,
+    /// Map an NSID prefix to an existing crate path instead of generating it, e.g. `com.example=example_api::com::example`.
+    /// com.atproto, app.bsky, chat.bsky and tools.ozone map to atrium_api unless overridden
+    #[arg(long = "external", value_name = "PREFIX=PATH", value_parser = parse_external)]
+    externals: Vec<(String, String)>,
+    /// Also generate `server.rs`, with a `Handler` trait and axum router for the queries and procedures
+    #[arg(long)]
+    server: bool,
+}
+
+/// Generates local Rust types from lexicons resolved through their `_lexicon` DNS record
+async fn resolve_generate_action(args: &ResolveGenerate) -> anyhow::Result<()> {
+    let nsids = args
+        .nsids
+        .iter()
+        .map(|nsid| Nsid::new(nsid.clone()).map_err(|e| anyhow!("{nsid}: {e}")))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let resolver = default_lexicon_resolver();
+
+    let mut lexicon_docs = Vec::with_capacity(nsids.len());
+    for lexicon in resolver.resolve_all(&nsids).await? {
+        log::info!("Resolved {} from {}", lexicon.nsid.as_str(), lexicon.uri);
+        lexicon_docs.push(lexicon.doc);
+    }
+    let externals = external_crates(&args.externals);
+    // nothing is pinned, so where dependencies came from isn't kept
+    let lexicon_docs = with_dependencies(
+        lexicon_docs,
+        None,
+        args.fallback_dir.as_deref(),
+        &externals,
+        &mut HashMap::new(),
+    )
+    .await?;
+
+    generate_docs(
+        lexicon_docs,
+        &args.outdir,
+        &args.module,
+        &externals,
+        args.server,
+    )
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "config",
+    about = "Generates Rust types from the sources and settings in an esquema.toml"
+)]
+struct ConfigGenerate {
+    /// The config file, its lockfile is written next to it
+    #[arg(short, long, default_value = CONFIG_FILE_NAME)]
+    config: PathBuf,
+    /// Fail if the fetched lexicons differ in any way from the lockfile
+    #[arg(long, conflicts_with = "update")]
+    locked: bool,
+    /// Rewrite the lockfile with the current content of fetched lexicons
+    #[arg(long)]
+    update: bool,
+    /// Also generate `server.rs`, even if the config doesn't set `server`
+    #[arg(long)]
+    server: bool,
+}
+
+/// Generates local Rust types from every source in an esquema.toml
+async fn config_generate_action(args: &ConfigGenerate) -> anyhow::Result<()> {
+    let mut config = Config::load(&args.config)?;
+    config.server |= args.server;
+    let externals = config.external_crates();
+
+    let mut lexicon_docs = Vec::new();
+    // lexicons of every remote repo, searched for dependencies before resolving them through DNS
+    let mut same_repo = LexiconRegistry::new();
+    let mut sources = HashMap::new();
+    let mut local = HashSet::new();
+    for source in &config.sources {
+        match source {
+            Source::Local(lexdir) => {
+                let docs = read_lexdir(lexdir).map_err(|err| anyhow!(err.to_string()))?;
+                local.extend(docs.iter().map(|doc| doc.id.clone()));
+                lexicon_docs.extend(docs);
+            }
+            Source::Remote(remote) => {
+                let repo =
+                    fetch_repo_lexicons(&remote.handle, &remote.collection, &remote.namespace)
+                        .await?;
+                lexicon_docs.extend(repo.docs);
+                for doc in repo.registry.into_docs() {
+                    same_repo.insert(doc);
                 }
-                Err(err) => {
-                    log::debug!("{:?}", err);
-                    log::error!(
-                        "There was an error in deserializing the ATProto record found {}. Is it a valid lexicon schema record?",
-                        record.uri
-                    );
+                sources.extend(repo.sources);
+            }
+            Source::Nsids(nsids) => {
+                let nsids = nsids
+                    .iter()
+                    .map(|nsid| Nsid::new(nsid.clone()).map_err(|e| anyhow!("{nsid}: {e}")))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                for lexicon in default_lexicon_resolver().resolve_all(&nsids).await? {
+                    log::info!("Resolved {} from {}", lexicon.nsid.as_str(), lexicon.uri);
+                    sources.insert(lexicon.doc.id.clone(), resolved_source(&lexicon));
+                    lexicon_docs.push(lexicon.doc);
                 }
             }
         }
     }
+    let lexicon_docs = with_dependencies(
+        lexicon_docs,
+        Some(&same_repo),
+        None,
+        &externals,
+        &mut sources,
+    )
+    .await?;
+
+    // local lexicons are generated from whatever is on disk, only fetched ones are pinned
+    let fetched = locked_lexicons(&lexicon_docs, &sources)
+        .into_iter()
+        .filter(|locked| !local.contains(&locked.nsid))
+        .collect::<Vec<_>>();
+    let lockfile_path = args.config.with_file_name(LOCKFILE_NAME);
+    let mode = if args.update {
+        LockMode::Update
+    } else if args.locked {
+        LockMode::Locked
+    } else {
+        LockMode::Default
+    };
+    let lockfile = Lockfile::read_and_apply(&lockfile_path, fetched, mode)?;
+
+    let results =
+        gen_from_lexicon_docs_with_config(lexicon_docs, &config).map_err(generation_error)?;
+    log_results(&results)?;
+    if let Some(lockfile) = lockfile {
+        lockfile.write(&lockfile_path)?;
+        log::info!("Wrote {}", lockfile_path.display());
+    }
+
+    Ok(())
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "mock-serve",
+    about = "Serves the queries and procedures of local lexicons, for testing clients without a PDS"
+)]
+struct MockServe {
+    /// The directory location of your lexicon JSON files. Works recursively
+    #[arg(short, long)]
+    lexdir: PathBuf,
+    /// The address to listen on
+    #[arg(short, long, default_value = "127.0.0.1:2583")]
+    addr: String,
+    /// Answer a method with the JSON in a file instead of a value made up from its output schema
+    #[arg(long = "output", value_name = "NSID=FILE", value_parser = parse_output)]
+    outputs: Vec<(String, PathBuf)>,
+}
 
-    let out_dir = PathBuf::from(args.outdir.as_path());
-    let results = gen_from_lexicon_docs(lexicon_docs, out_dir, &args.module)
-        .map_err(|e| anyhow!(e.to_string()))?;
-    for path in &results {
+/// Serves until stopped, `com.atproto.repo` record methods keep records in memory
+async fn mock_serve_action(args: &MockServe) -> anyhow::Result<()> {
+    let mut mock = MockServer::from_registry(LexiconRegistry::from_dir(&args.lexdir)?);
+    for (nsid, path) in &args.outputs {
+        let output = serde_json::from_slice(&fs::read(path)?)
+            .map_err(|err| anyhow!("{} is not valid JSON: {err}", path.display()))?;
+        mock = mock.output(nsid, output);
+    }
+    let listener = tokio::net::TcpListener::bind(&args.addr).await?;
+    log::info!(
+        "Serving mock XRPC at http://{}/xrpc",
+        listener.local_addr()?
+    );
+    esquema_server::axum::serve(listener, mock.router()).await?;
+    Ok(())
+}
+
+fn parse_output(value: &str) -> Result<(String, PathBuf), String> {
+    value
+        .split_once('=')
+        .map(|(nsid, path)| (nsid.to_string(), PathBuf::from(path)))
+        .ok_or_else(|| format!("expected NSID=FILE, found `{value}`"))
+}
+
+fn parse_external(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(prefix, path)| (prefix.to_string(), path.to_string()))
+        .ok_or_else(|| format!("expected PREFIX=PATH, found `{value}`"))
+}
+
+/// The default mappings to atrium_api with any `--external` flags applied on top
+fn external_crates(overrides: &[(String, String)]) -> ExternalCrates {
+    let mut externals = ExternalCrates::default();
+    for (prefix, path) in overrides {
+        externals.insert(prefix, path);
+    }
+    externals
+}
+
+/// Generates `lexicon_docs` with the options the `local`, `remote`, `update` and `resolve` commands share
+fn generate_docs(
+    lexicon_docs: Vec<LexiconDoc>,
+    outdir: &Path,
+    module: &Option<String>,
+    externals: &ExternalCrates,
+    server: bool,
+) -> anyhow::Result<()> {
+    let mut builder = Generator::builder()
+        .lexicons(lexicon_docs)
+        .outdir(outdir)
+        .externals(externals.clone())
+        .server(server);
+    if let Some(module) = module {
+        builder = builder.module(module);
+    }
+    let results = builder.build().generate().map_err(generation_error)?;
+    log_results(&results)
+}
+
+/// Names the lexicon and def a [`CodegenError`] comes from, so it's clear which lexicon to fix
+fn generation_error(err: Box<dyn std::error::Error>) -> anyhow::Error {
+    let message = anyhow!(err.to_string());
+    match err.downcast_ref::<CodegenError>() {
+        Some(codegen) => match codegen.def() {
+            Some(def) if !def.is_empty() => message.context(format!(
+                "Failed to generate Rust types for {}#{def}",
+                codegen.nsid()
+            )),
+            _ => message.context("Failed to generate Rust types"),
+        },
+        None => message,
+    }
+}
+
+fn log_results(results: &[impl AsRef<Path>]) -> anyhow::Result<()> {
+    for path in results {
         log::info!(
             "{} ({} bytes)",
             path.as_ref().display(),
             fs::metadata(path.as_ref())?.len()
         );
     }
-
     Ok(())
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+/// Lockfile entries for `docs`, with the URI and CID of the ones fetched from a record
+fn locked_lexicons(
+    docs: &[LexiconDoc],
+    sources: &HashMap<String, (String, Option<String>)>,
+) -> Vec<LockedLexicon> {
+    docs.iter()
+        .map(|doc| match sources.get(&doc.id) {
+            Some((uri, cid)) => LockedLexicon::new(doc, Some(uri.clone()), cid.clone()),
+            None => LockedLexicon::new(doc, None, None),
+        })
+        .collect()
+}
 
-    let cli = Cli::parse();
-    match &cli.command {
-        Commands::Generate(Generate { subcommand }) => match subcommand {
-            GenerateCommands::Local(args) => local_generate_action(args),
-            GenerateCommands::Remote(args) => generate_from_record_action(args).await,
-        },
-    }
+fn default_lexicon_resolver() -> LexiconResolver<HickoryDnsTxtResolver, DefaultHttpClient> {
+    LexiconResolver::new(LexiconResolverConfig {
+        dns_txt_resolver: HickoryDnsTxtResolver::default(),
+        http_client: Arc::new(DefaultHttpClient::default()),
+        plc_directory_url: DEFAULT_PLC_DIRECTORY_URL.to_string(),
+    })
+}
+
+/// The URI and CID of the schema record of a lexicon resolved through DNS
+fn resolved_source(lexicon: &ResolvedLexicon) -> (String, Option<String>) {
+    let cid = lexicon.cid.as_ref().map(|cid| cid.as_ref().to_string());
+    (lexicon.uri.clone(), cid)
 }
 
-struct HickoryDnsTxtResolver {
-    resolver: TokioAsyncResolver,
+/// Adds the lexicons `docs` reference, so the generated paths to them exist, recording the
+/// schema records of the ones resolved through DNS in `record_sources`
+async fn with_dependencies(
+    docs: Vec<LexiconDoc>,
+    same_repo: Option<&LexiconRegistry>,
+    fallback_dir: Option<&Path>,
+    externals: &ExternalCrates,
+    record_sources: &mut HashMap<String, (String, Option<String>)>,
+) -> anyhow::Result<Vec<LexiconDoc>> {
+    let fallback = fallback_dir.map(LexiconRegistry::from_dir).transpose()?;
+    let resolver = default_lexicon_resolver();
+    let sources = DependencySources {
+        same_repo,
+        fallback: fallback.as_ref(),
+        resolver: Some(&resolver),
+        externals,
+    };
+    let dependencies = fetch_dependencies(docs, &sources).await?;
+    for lexicon in &dependencies.resolved {
+        record_sources.insert(lexicon.doc.id.clone(), resolved_source(lexicon));
+    }
+    Ok(dependencies.docs)
 }
 
-impl Default for HickoryDnsTxtResolver {
-    fn default() -> Self {
-        Self {
-            resolver: TokioAsyncResolver::tokio_from_system_conf()
-                .expect("failed to create resolver"),
+/// Lists every record in the collection, following cursors until the PDS runs out of pages
+async fn list_all_records(
+    agent: &AtpAgent<MemorySessionStore, ReqwestClient>,
+    did: &Did,
+    collection: &Nsid,
+) -> anyhow::Result<Vec<list_records::Record>> {
+    let mut records = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let page = agent
+            .api
+            .com
+            .atproto
+            .repo
+            .list_records(
+                list_records::ParametersData {
+                    collection: collection.clone(),
+                    cursor: cursor.clone(),
+                    limit: Some(LimitedNonZeroU8::try_from(100u8).map_err(|e| anyhow!(e))?),
+                    repo: AtIdentifier::Did(did.clone()),
+                    reverse: None,
+                }
+                .into(),
+            )
+            .await?;
+        let page_size = page.data.records.len();
+        records.extend(page.data.records);
+        log::debug!("Fetched {page_size} records, {} total", records.len());
+        match page.data.cursor {
+            // Some PDSs hand back the last cursor on an empty page, stop instead of looping on it
+            Some(next) if page_size > 0 && cursor.as_ref() != Some(&next) => cursor = Some(next),
+            _ => break,
         }
     }
+    Ok(records)
 }
 
-impl DnsTxtResolver for HickoryDnsTxtResolver {
-    async fn resolve(
-        &self,
-        query: &str,
-    ) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync + 'static>> {
-        Ok(self
-            .resolver
-            .txt_lookup(query)
-            .await?
-            .iter()
-            .map(|txt| txt.to_string())
-            .collect())
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+
+    let cli = Cli::parse();
+    match &cli.command {
+        Commands::Generate(Generate { subcommand }) => match subcommand {
+            GenerateCommands::Local(args) => local_generate_action(args),
+            GenerateCommands::Remote(args) => {
+                let mode = if args.locked {
+                    LockMode::Locked
+                } else {
+                    LockMode::Default
+                };
+                generate_from_record_action(&args.repo, mode).await
+            }
+            GenerateCommands::Update(args) => {
+                generate_from_record_action(&args.repo, LockMode::Update).await
+            }
+            GenerateCommands::Resolve(args) => resolve_generate_action(args).await,
+            GenerateCommands::Config(args) => config_generate_action(args).await,
+        },
+        Commands::MockServe(args) => mock_serve_action(args).await,
     }
 }
@@ -0,0 +1,306 @@
+//! Resolves lexicons by NSID the way the atproto spec describes:
+//! `_lexicon.<reversed authority>` TXT record → DID → PDS → `com.atproto.lexicon.schema` record.
+
+use atrium_api::client::AtpServiceClient;
+use atrium_api::com::atproto::repo::get_record;
+use atrium_api::did_doc::DidDocument;
+use atrium_api::types::string::{AtIdentifier, Cid, Did, Nsid, RecordKey};
+use atrium_common::resolver::Resolver;
+use atrium_identity::did::{CommonDidResolver, CommonDidResolverConfig};
+use atrium_identity::handle::DnsTxtResolver;
+use atrium_lex::LexiconDoc;
+use atrium_xrpc::http::{Request, Response};
+use atrium_xrpc::{HttpClient, XrpcClient};
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::sync::Arc;
+
+/// The collection lexicon schema records are published under.
+pub const LEXICON_COLLECTION: &str = "com.atproto.lexicon.schema";
+
+type BoxError = Box<dyn Error + Send + Sync + 'static>;
+
+/// A lexicon fetched from the repo of its authority.
+#[derive(Debug, Clone)]
+pub struct ResolvedLexicon {
+    pub nsid: Nsid,
+    /// The DID of the repo the lexicon was published in
+    pub did: Did,
+    /// The `at://` URI of the schema record
+    pub uri: String,
+    pub cid: Option<Cid>,
+    pub doc: LexiconDoc,
+}
+
+#[derive(Debug)]
+pub enum ResolveError {
+    /// The `_lexicon` TXT lookup failed
+    Dns {
+        domain: String,
+        source: BoxError,
+    },
+    /// No `did=` TXT record was found for the authority
+    NoAuthority {
+        domain: String,
+    },
+    /// More than one distinct `did=` TXT record was found for the authority
+    AmbiguousAuthority {
+        domain: String,
+        dids: Vec<String>,
+    },
+    /// The `did=` TXT record of the authority doesn't hold a valid DID
+    InvalidDid {
+        domain: String,
+        did: String,
+        reason: &'static str,
+    },
+    Did {
+        did: String,
+        source: atrium_identity::Error,
+    },
+    NoPds {
+        did: String,
+    },
+    Fetch {
+        nsid: String,
+        source: BoxError,
+    },
+    Parse {
+        nsid: String,
+        source: serde_json::Error,
+    },
+    /// The schema record describes a different lexicon than the one asked for
+    Mismatch {
+        nsid: String,
+        found: String,
+    },
+}
+
+impl Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::Dns { domain, source } => {
+                write!(f, "DNS lookup of {domain} failed: {source}")
+            }
+            ResolveError::NoAuthority { domain } => {
+                write!(f, "no `did=` TXT record found at {domain}")
+            }
+            ResolveError::AmbiguousAuthority { domain, dids } => {
+                write!(
+                    f,
+                    "{domain} has more than one `did=` TXT record: {}",
+                    dids.join(", ")
+                )
+            }
+            ResolveError::InvalidDid {
+                domain,
+                did,
+                reason,
+            } => write!(
+                f,
+                "the `did=` TXT record at {domain} holds `{did}`: {reason}"
+            ),
+            ResolveError::Did { did, source } => write!(f, "failed to resolve {did}: {source}"),
+            ResolveError::NoPds { did } => write!(f, "no PDS found in the DID document of {did}"),
+            ResolveError::Fetch { nsid, source } => {
+                write!(f, "failed to fetch the schema record for {nsid}: {source}")
+            }
+            ResolveError::Parse { nsid, source } => {
+                write!(
+                    f,
+                    "the schema record for {nsid} is not a valid lexicon: {source}"
+                )
+            }
+            ResolveError::Mismatch { nsid, found } => {
+                write!(f, "the schema record for {nsid} describes {found}")
+            }
+        }
+    }
+}
+
+impl Error for ResolveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ResolveError::Dns { source, .. } | ResolveError::Fetch { source, .. } => {
+                Some(source.as_ref())
+            }
+            ResolveError::Did { source, .. } => Some(source),
+            ResolveError::Parse { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+pub struct LexiconResolverConfig<D, H> {
+    pub dns_txt_resolver: D,
+    pub http_client: Arc<H>,
+    pub plc_directory_url: String,
+}
+
+/// Resolves lexicons by NSID, with the DNS and HTTP layers supplied by the caller.
+pub struct LexiconResolver<D, H> {
+    dns_txt_resolver: D,
+    did_resolver: CommonDidResolver<H>,
+    http_client: Arc<H>,
+}
+
+impl<D, H> LexiconResolver<D, H>
+where
+    D: DnsTxtResolver,
+    H: HttpClient + Send + Sync + 'static,
+{
+    pub fn new(config: LexiconResolverConfig<D, H>) -> Self {
+        Self {
+            dns_txt_resolver: config.dns_txt_resolver,
+            did_resolver: CommonDidResolver::new(CommonDidResolverConfig {
+                plc_directory_url: config.plc_directory_url,
+                http_client: Arc::clone(&config.http_client),
+            }),
+            http_client: config.http_client,
+        }
+    }
+
+    /// Finds the DID that publishes lexicons for the authority of `nsid`.
+    pub async fn resolve_authority(&self, nsid: &Nsid) -> Result<Did, ResolveError> {
+        let domain = lexicon_domain(nsid);
+        let records = self
+            .dns_txt_resolver
+            .resolve(&domain)
+            .await
+            .map_err(|source| ResolveError::Dns {
+                domain: domain.clone(),
+                source,
+            })?;
+        let mut dids = records
+            .iter()
+            .filter_map(|record| record.trim_matches('"').strip_prefix("did="))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        dids.sort();
+        dids.dedup();
+        match dids.as_slice() {
+            [] => Err(ResolveError::NoAuthority { domain }),
+            [did] => did.parse().map_err(|reason| ResolveError::InvalidDid {
+                domain,
+                did: did.clone(),
+                reason,
+            }),
+            _ => Err(ResolveError::AmbiguousAuthority { domain, dids }),
+        }
+    }
+
+    /// Fetches the schema record for `nsid` from the PDS of its authority.
+    pub async fn resolve(&self, nsid: &Nsid) -> Result<ResolvedLexicon, ResolveError> {
+        let did = self.resolve_authority(nsid).await?;
+        let did_doc =
+            self.did_resolver
+                .resolve(&did)
+                .await
+                .map_err(|source| ResolveError::Did {
+                    did: did.to_string(),
+                    source,
+                })?;
+        let pds_url = pds_endpoint(&did_doc).ok_or_else(|| ResolveError::NoPds {
+            did: did.to_string(),
+        })?;
+        let client = AtpServiceClient::new(PdsClient {
+            base_uri: pds_url,
+            http_client: Arc::clone(&self.http_client),
+        });
+        let fetch_error = |source: BoxError| ResolveError::Fetch {
+            nsid: nsid.to_string(),
+            source,
+        };
+        let record = client
+            .service
+            .com
+            .atproto
+            .repo
+            .get_record(
+                get_record::ParametersData {
+                    cid: None,
+                    collection: Nsid::new(LEXICON_COLLECTION.to_string())
+                        .expect("LEXICON_COLLECTION is a valid NSID"),
+                    repo: AtIdentifier::Did(did.clone()),
+                    rkey: RecordKey::new(nsid.to_string()).map_err(|e| fetch_error(e.into()))?,
+                }
+                .into(),
+            )
+            .await
+            .map_err(|e| fetch_error(e.into()))?;
+
+        let doc = parse_lexicon_doc(&record.data.value).map_err(|source| ResolveError::Parse {
+            nsid: nsid.to_string(),
+            source,
+        })?;
+        if doc.id != nsid.as_str() {
+            return Err(ResolveError::Mismatch {
+                nsid: nsid.to_string(),
+                found: doc.id,
+            });
+        }
+        Ok(ResolvedLexicon {
+            nsid: nsid.clone(),
+            did,
+            uri: record.data.uri,
+            cid: record.data.cid,
+            doc,
+        })
+    }
+
+    /// Resolves each NSID in turn, stopping at the first failure.
+    pub async fn resolve_all(&self, nsids: &[Nsid]) -> Result<Vec<ResolvedLexicon>, ResolveError> {
+        let mut resolved = Vec::with_capacity(nsids.len());
+        for nsid in nsids {
+            resolved.push(self.resolve(nsid).await?);
+        }
+        Ok(resolved)
+    }
+}
+
+/// The TXT record name holding the authority DID, e.g. `_lexicon.feed.bsky.app` for `app.bsky.feed.post`.
+pub fn lexicon_domain(nsid: &Nsid) -> String {
+    let authority = nsid
+        .as_str()
+        .rsplit_once('.')
+        .map_or(nsid.as_str(), |(authority, _)| authority);
+    let reversed = authority.split('.').rev().collect::<Vec<_>>().join(".");
+    format!("_lexicon.{reversed}")
+}
+
+/// The endpoint of the `AtprotoPersonalDataServer` service in a DID document.
+pub fn pds_endpoint(did_doc: &DidDocument) -> Option<String> {
+    did_doc.service.as_ref().and_then(|services| {
+        services
+            .iter()
+            .find(|service| service.r#type == "AtprotoPersonalDataServer")
+            .map(|service| service.service_endpoint.clone())
+    })
+}
+
+//HACK (slightly)
+//We are using serde_json directly instead of LexiconDoc::try_from_unknown because it currently
+//uses .unwrap and we want a friendlier error
+pub fn parse_lexicon_doc(
+    value: &atrium_api::types::Unknown,
+) -> Result<LexiconDoc, serde_json::Error> {
+    serde_json::from_value(serde_json::to_value(value)?)
+}
+
+/// Points the shared HTTP client at a single PDS.
+struct PdsClient<H> {
+    base_uri: String,
+    http_client: Arc<H>,
+}
+
+impl<H: HttpClient + Send + Sync> HttpClient for PdsClient<H> {
+    async fn send_http(&self, request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, BoxError> {
+        self.http_client.send_http(request).await
+    }
+}
+
+impl<H: HttpClient + Send + Sync> XrpcClient for PdsClient<H> {
+    fn base_uri(&self) -> String {
+        self.base_uri.clone()
+    }
+}
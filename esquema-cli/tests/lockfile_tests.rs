@@ -0,0 +1,114 @@
+use atrium_lex::LexiconDoc;
+use esquema_cli::lockfile::{LockError, LockMode, LockedLexicon, Lockfile, content_hash};
+use serde_json::json;
+
+fn status_doc(max_graphemes: usize) -> LexiconDoc {
+    serde_json::from_value(json!({
+        "lexicon": 1,
+        "id": "xyz.statusphere.status",
+        "defs": {
+            "main": {
+                "type": "record",
+                "key": "tid",
+                "record": {
+                    "type": "object",
+                    "required": ["status"],
+                    "properties": {
+                        "status": { "type": "string", "maxGraphemes": max_graphemes }
+                    }
+                }
+            },
+            "other": { "type": "token" }
+        }
+    }))
+    .unwrap()
+}
+
+fn locked(doc: &LexiconDoc) -> LockedLexicon {
+    LockedLexicon::new(
+        doc,
+        Some("at://did:plc:abc/com.atproto.lexicon.schema/xyz.statusphere.status".into()),
+        None,
+    )
+}
+
+#[test]
+fn hash_ignores_key_order() {
+    let reordered: LexiconDoc = serde_json::from_str(
+        r#"{
+            "defs": {
+                "other": { "type": "token" },
+                "main": {
+                    "record": {
+                        "properties": { "status": { "maxGraphemes": 1, "type": "string" } },
+                        "required": ["status"],
+                        "type": "object"
+                    },
+                    "key": "tid",
+                    "type": "record"
+                }
+            },
+            "id": "xyz.statusphere.status",
+            "lexicon": 1
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(content_hash(&status_doc(1)), content_hash(&reordered));
+    assert_ne!(content_hash(&status_doc(1)), content_hash(&status_doc(2)));
+}
+
+#[test]
+fn round_trips_through_toml() {
+    let lockfile = Lockfile::default()
+        .apply(vec![locked(&status_doc(1))], LockMode::Default)
+        .unwrap();
+    let dir = std::env::temp_dir().join(format!("esquema-lock-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("esquema.lock");
+    lockfile.write(&path).unwrap();
+    assert_eq!(Lockfile::read(&path).unwrap(), lockfile);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn refuses_changed_content_unless_updating() {
+    let lockfile = Lockfile::default()
+        .apply(vec![locked(&status_doc(1))], LockMode::Default)
+        .unwrap();
+    let changed = vec![locked(&status_doc(2))];
+
+    for mode in [LockMode::Default, LockMode::Locked] {
+        let err = lockfile.apply(changed.clone(), mode).unwrap_err();
+        assert!(matches!(err, LockError::Mismatch { changed, .. } if changed.len() == 1));
+    }
+    let updated = lockfile.apply(changed, LockMode::Update).unwrap();
+    assert_eq!(updated.lexicons[0].hash, content_hash(&status_doc(2)));
+}
+
+#[test]
+fn locked_refuses_new_lexicons() {
+    let err = Lockfile::default()
+        .apply(vec![locked(&status_doc(1))], LockMode::Locked)
+        .unwrap_err();
+    assert!(matches!(err, LockError::Mismatch { added, .. } if added.len() == 1));
+}
+
+#[test]
+fn locked_refuses_stale_entries_even_when_nothing_is_fetched() {
+    let lockfile = Lockfile::default()
+        .apply(vec![locked(&status_doc(1))], LockMode::Default)
+        .unwrap();
+    let dir = std::env::temp_dir().join(format!("esquema-stale-lock-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("esquema.lock");
+    lockfile.write(&path).unwrap();
+
+    let locked = Lockfile::read_and_apply(&path, Vec::new(), LockMode::Locked);
+    let unlocked = Lockfile::read_and_apply(&path, Vec::new(), LockMode::Default);
+    std::fs::remove_dir_all(dir).unwrap();
+    assert!(matches!(
+        locked.unwrap_err(),
+        LockError::Mismatch { removed, .. } if removed == ["xyz.statusphere.status"]
+    ));
+    assert_eq!(unlocked.unwrap(), None);
+}
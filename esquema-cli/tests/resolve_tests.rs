@@ -0,0 +1,181 @@
+use atrium_api::types::string::Nsid;
+use atrium_identity::handle::DnsTxtResolver;
+use atrium_lex::LexiconDoc;
+use atrium_xrpc::HttpClient;
+use atrium_xrpc::http::{Request, Response};
+use esquema_cli::deps::{DependencySources, fetch_dependencies};
+use esquema_cli::resolve::{LexiconResolver, LexiconResolverConfig, ResolveError, lexicon_domain};
+use esquema_codegen::ExternalCrates;
+use esquema_validator::LexiconRegistry;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+const DID: &str = "did:plc:ewvi7nxzyoun6zhxrhs64oiz";
+
+struct FakeDns(HashMap<&'static str, Vec<String>>);
+
+impl DnsTxtResolver for FakeDns {
+    async fn resolve(
+        &self,
+        query: &str,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        Ok(self.0.get(query).cloned().unwrap_or_default())
+    }
+}
+
+/// Serves the DID document from the PLC directory and the schema record from the PDS.
+struct FakeHttp;
+
+impl HttpClient for FakeHttp {
+    async fn send_http(
+        &self,
+        request: Request<Vec<u8>>,
+    ) -> Result<Response<Vec<u8>>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let uri = request.uri();
+        let body = match (uri.host(), uri.path()) {
+            (Some("plc.test"), path) if path == format!("/{DID}") => json!({
+                "@context": ["https://www.w3.org/ns/did/v1"],
+                "id": DID,
+                "alsoKnownAs": ["at://statusphere.xyz"],
+                "verificationMethod": [],
+                "service": [{
+                    "id": "#atproto_pds",
+                    "type": "AtprotoPersonalDataServer",
+                    "serviceEndpoint": "https://pds.test"
+                }]
+            }),
+            (Some("pds.test"), "/xrpc/com.atproto.repo.getRecord") => json!({
+                "uri": format!("at://{DID}/com.atproto.lexicon.schema/xyz.statusphere.status"),
+                "value": {
+                    "$type": "com.atproto.lexicon.schema",
+                    "lexicon": 1,
+                    "id": "xyz.statusphere.status",
+                    "defs": {
+                        "main": {
+                            "type": "record",
+                            "key": "tid",
+                            "record": {
+                                "type": "object",
+                                "required": ["status"],
+                                "properties": { "status": { "type": "string" } }
+                            }
+                        }
+                    }
+                }
+            }),
+            _ => return Ok(Response::builder().status(404).body(Vec::new())?),
+        };
+        Ok(Response::builder()
+            .status(200)
+            .header("content-type", "application/json")
+            .body(serde_json::to_vec(&body)?)?)
+    }
+}
+
+fn resolver(txt: Vec<String>) -> LexiconResolver<FakeDns, FakeHttp> {
+    LexiconResolver::new(LexiconResolverConfig {
+        dns_txt_resolver: FakeDns(HashMap::from([("_lexicon.statusphere.xyz", txt)])),
+        http_client: Arc::new(FakeHttp),
+        plc_directory_url: "https://plc.test".to_string(),
+    })
+}
+
+fn nsid(nsid: &str) -> Nsid {
+    Nsid::new(nsid.to_string()).unwrap()
+}
+
+#[test]
+fn reverses_the_authority() {
+    assert_eq!(
+        lexicon_domain(&nsid("app.bsky.feed.post")),
+        "_lexicon.feed.bsky.app"
+    );
+}
+
+#[tokio::test]
+async fn resolves_through_dns_did_and_pds() {
+    let lexicon = resolver(vec![format!("did={DID}")])
+        .resolve(&nsid("xyz.statusphere.status"))
+        .await
+        .unwrap();
+    assert_eq!(lexicon.did.as_str(), DID);
+    assert_eq!(lexicon.doc.id, "xyz.statusphere.status");
+}
+
+#[tokio::test]
+async fn requires_a_single_authority() {
+    let missing = resolver(Vec::new())
+        .resolve(&nsid("xyz.statusphere.status"))
+        .await;
+    assert!(matches!(missing, Err(ResolveError::NoAuthority { .. })));
+
+    let ambiguous = resolver(vec![
+        format!("did={DID}"),
+        "did=did:plc:aaaaaaaaaaaaaaaaaaaaaaaa".to_string(),
+    ])
+    .resolve(&nsid("xyz.statusphere.status"))
+    .await;
+    assert!(matches!(
+        ambiguous,
+        Err(ResolveError::AmbiguousAuthority { .. })
+    ));
+
+    let invalid = resolver(vec!["did=statusphere.xyz".to_string()])
+        .resolve(&nsid("xyz.statusphere.status"))
+        .await;
+    assert!(matches!(
+        invalid,
+        Err(ResolveError::InvalidDid { did, .. }) if did == "statusphere.xyz"
+    ));
+}
+
+fn object_doc(id: &str, r#ref: Option<&str>) -> LexiconDoc {
+    let properties = match r#ref {
+        Some(r#ref) => json!({ "thing": { "type": "ref", "ref": r#ref } }),
+        None => json!({}),
+    };
+    serde_json::from_value(json!({
+        "lexicon": 1,
+        "id": id,
+        "defs": { "main": { "type": "object", "properties": properties } }
+    }))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn fetches_transitive_dependencies() {
+    let fallback = LexiconRegistry::from_docs(vec![
+        object_doc("com.example.defs", Some("com.example.more")),
+        object_doc("com.example.more", None),
+    ]);
+    let sources = DependencySources::<FakeDns, FakeHttp> {
+        same_repo: None,
+        fallback: Some(&fallback),
+        resolver: None,
+        externals: &ExternalCrates::default(),
+    };
+    // com.atproto is provided by atrium_api, so the strongRef is left alone
+    let dependencies = fetch_dependencies(
+        vec![
+            object_doc("xyz.example.post", Some("com.example.defs")),
+            object_doc("xyz.example.like", Some("com.atproto.repo.strongRef")),
+        ],
+        &sources,
+    )
+    .await
+    .unwrap();
+    assert_eq!(dependencies.docs.len(), 4);
+    assert!(dependencies.resolved.is_empty());
+
+    let unresolved = fetch_dependencies(
+        vec![object_doc(
+            "xyz.example.post",
+            Some("com.example.missing#thing"),
+        )],
+        &sources,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(unresolved.refs[0].r#ref, "com.example.missing#thing");
+}
@@ -0,0 +1,5 @@
+//! Runtime support for the clients esquema generates, for what `atrium_xrpc` doesn't cover.
+
+pub mod paginate;
+pub mod record;
+pub mod subscription;
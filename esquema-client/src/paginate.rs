@@ -0,0 +1,46 @@
+//! Streams of the pages of cursor paginated queries.
+
+use futures::StreamExt;
+use futures::stream::BoxStream;
+use std::collections::HashSet;
+use std::future::Future;
+
+/// The pages of a query, fetched one after another.
+pub type Pages<'a, O, E> = BoxStream<'a, Result<O, E>>;
+
+/// Calls `fetch` with `params`, then again with the cursor of each page set by `set_cursor`, until
+/// a page comes without a cursor, with an empty one, or with one already followed, which would
+/// fetch the same pages again forever.
+///
+/// The stream ends after the first error.
+pub fn pages<'a, P, O, E, F, Fut>(
+    params: P,
+    fetch: F,
+    set_cursor: fn(&mut P, Option<String>),
+    cursor: fn(&O) -> Option<String>,
+) -> Pages<'a, O, E>
+where
+    P: Clone + Send + 'a,
+    O: Send + 'a,
+    E: Send + 'a,
+    F: Fn(P) -> Fut + Send + 'a,
+    Fut: Future<Output = Result<O, E>> + Send + 'a,
+{
+    // the params of the next page, and the cursors followed so far
+    let state = (Some(params), HashSet::<String>::new(), fetch);
+    futures::stream::try_unfold(state, move |(next, mut seen, fetch)| async move {
+        let Some(mut params) = next else {
+            return Ok(None);
+        };
+        let page = fetch(params.clone()).await?;
+        let next = match cursor(&page) {
+            Some(cursor) if !cursor.is_empty() && seen.insert(cursor.clone()) => {
+                set_cursor(&mut params, Some(cursor));
+                Some(params)
+            }
+            _ => None,
+        };
+        Ok::<_, E>(Some((page, (next, seen, fetch))))
+    })
+    .boxed()
+}
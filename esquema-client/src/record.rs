@@ -0,0 +1,307 @@
+//! Typed access to the records of a collection, through `com.atproto.repo.*`.
+
+use atrium_api::client::com::atproto::repo::Service;
+use atrium_api::com::atproto::repo::{
+    create_record, delete_record, get_record, list_records, put_record,
+};
+use atrium_api::types::string::{AtIdentifier, Cid, RecordKey};
+use atrium_api::types::{Collection, LimitedNonZeroU8, Unknown};
+use atrium_xrpc::XrpcClient;
+use esquema_validator::RecordError;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fmt::{self, Debug, Display};
+use std::marker::PhantomData;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The XRPC call failed
+    Xrpc(Box<dyn std::error::Error + Send + Sync>),
+    /// The record can't be turned into the `Unknown` sent to the repo
+    Encode(String),
+    /// A record in the repo isn't a valid record of the collection
+    Decode { uri: String, message: String },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Xrpc(err) => write!(f, "xrpc error: {err}"),
+            Error::Encode(message) => write!(f, "invalid record: {message}"),
+            Error::Decode { uri, message } => write!(f, "invalid record at {uri}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Xrpc(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl<E> From<atrium_xrpc::Error<E>> for Error
+where
+    E: Debug + Display + Send + Sync + 'static,
+{
+    fn from(err: atrium_xrpc::Error<E>) -> Self {
+        Error::Xrpc(Box::new(err))
+    }
+}
+
+/// A record of the collection, with where it is stored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record<R> {
+    pub uri: String,
+    pub cid: Option<Cid>,
+    pub rkey: RecordKey,
+    pub value: R,
+}
+
+/// Where a created or updated record is stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordRef {
+    pub uri: String,
+    pub cid: Cid,
+    pub rkey: RecordKey,
+}
+
+/// A page of `list`, with the cursor of the next page if there is one.
+///
+/// Records that aren't valid records of the collection are in `invalid`, so one of them doesn't
+/// hide the rest of the page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<R> {
+    pub records: Vec<Record<R>>,
+    pub invalid: Vec<InvalidRecord>,
+    pub cursor: Option<String>,
+}
+
+/// A record in the repo that isn't a valid record of the collection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidRecord {
+    pub uri: String,
+    pub message: String,
+}
+
+impl From<RecordError> for Error {
+    fn from(err: RecordError) -> Self {
+        Error::Encode(message(&err))
+    }
+}
+
+impl From<InvalidRecord> for Error {
+    fn from(InvalidRecord { uri, message }: InvalidRecord) -> Self {
+        Error::Decode { uri, message }
+    }
+}
+
+/// The records of the collection `C` in the repo of one account.
+///
+/// Records are sent with the collection's `$type`, and read back as `C::Record`.
+pub struct RecordClient<'a, C, T>
+where
+    T: XrpcClient + Send + Sync,
+{
+    service: &'a Service<T>,
+    repo: AtIdentifier,
+    _collection: PhantomData<C>,
+}
+
+impl<'a, C, T> RecordClient<'a, C, T>
+where
+    C: Collection,
+    T: XrpcClient + Send + Sync,
+{
+    /// `service` is the `com.atproto.repo` of an `AtpServiceClient`, or of an agent's `api`.
+    pub fn new(service: &'a Service<T>, repo: impl Into<AtIdentifier>) -> Self {
+        Self {
+            service,
+            repo: repo.into(),
+            _collection: PhantomData,
+        }
+    }
+
+    /// Creates a record, under a TID record key unless `rkey` is given.
+    pub async fn create(
+        &self,
+        rkey: Option<RecordKey>,
+        record: impl Into<C::Record>,
+    ) -> Result<RecordRef, Error> {
+        let output = self
+            .service
+            .create_record(
+                create_record::InputData {
+                    collection: C::nsid(),
+                    record: to_unknown(C::NSID, &record.into())?,
+                    repo: self.repo.clone(),
+                    rkey,
+                    swap_commit: None,
+                    validate: None,
+                }
+                .into(),
+            )
+            .await?;
+        record_ref(output.data.uri, output.data.cid)
+    }
+
+    /// Creates or replaces the record at `rkey`.
+    pub async fn put(
+        &self,
+        rkey: RecordKey,
+        record: impl Into<C::Record>,
+    ) -> Result<RecordRef, Error> {
+        let output = self
+            .service
+            .put_record(
+                put_record::InputData {
+                    collection: C::nsid(),
+                    record: to_unknown(C::NSID, &record.into())?,
+                    repo: self.repo.clone(),
+                    rkey,
+                    swap_commit: None,
+                    swap_record: None,
+                    validate: None,
+                }
+                .into(),
+            )
+            .await?;
+        record_ref(output.data.uri, output.data.cid)
+    }
+
+    pub async fn get(&self, rkey: RecordKey) -> Result<Record<C::Record>, Error> {
+        let output = self
+            .service
+            .get_record(
+                get_record::ParametersData {
+                    cid: None,
+                    collection: C::nsid(),
+                    repo: self.repo.clone(),
+                    rkey,
+                }
+                .into(),
+            )
+            .await?;
+        record(C::NSID, output.data.uri, output.data.cid, output.data.value).map_err(Error::from)
+    }
+
+    /// A page of records, starting after `cursor`, see [`Page`].
+    pub async fn list(
+        &self,
+        cursor: Option<String>,
+        limit: Option<LimitedNonZeroU8<100>>,
+    ) -> Result<Page<C::Record>, Error> {
+        let output = self
+            .service
+            .list_records(
+                list_records::ParametersData {
+                    collection: C::nsid(),
+                    cursor,
+                    limit,
+                    repo: self.repo.clone(),
+                    reverse: None,
+                }
+                .into(),
+            )
+            .await?;
+        let mut records = Vec::new();
+        let mut invalid = Vec::new();
+        for entry in output.data.records {
+            match record(
+                C::NSID,
+                entry.data.uri,
+                Some(entry.data.cid),
+                entry.data.value,
+            ) {
+                Ok(record) => records.push(record),
+                Err(err) => invalid.push(err),
+            }
+        }
+        Ok(Page {
+            records,
+            invalid,
+            cursor: output.data.cursor,
+        })
+    }
+
+    pub async fn delete(&self, rkey: RecordKey) -> Result<(), Error> {
+        self.service
+            .delete_record(
+                delete_record::InputData {
+                    collection: C::nsid(),
+                    repo: self.repo.clone(),
+                    rkey,
+                    swap_commit: None,
+                    swap_record: None,
+                }
+                .into(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// The record as the `Unknown` the repo endpoints take, tagged with the collection's `$type`.
+fn to_unknown<R: Serialize>(nsid: &str, record: &R) -> Result<Unknown, Error> {
+    #[derive(Serialize)]
+    struct Typed<'a, R> {
+        #[serde(rename = "$type")]
+        r#type: &'a str,
+        #[serde(flatten)]
+        record: &'a R,
+    }
+
+    let typed = Typed {
+        r#type: nsid,
+        record,
+    };
+    Ok(esquema_validator::record::to_unknown(Some(nsid), &typed)?)
+}
+
+fn record<R: DeserializeOwned>(
+    nsid: &str,
+    uri: String,
+    cid: Option<Cid>,
+    value: Unknown,
+) -> Result<Record<R>, InvalidRecord> {
+    let rkey = rkey(&uri)?;
+    match esquema_validator::record::from_unknown(Some(nsid), &value) {
+        Ok(value) => Ok(Record {
+            uri,
+            cid,
+            rkey,
+            value,
+        }),
+        Err(err) => Err(InvalidRecord {
+            uri,
+            message: message(&err),
+        }),
+    }
+}
+
+/// The path and reason of `err`, the collection being named by the record client already.
+fn message(err: &RecordError) -> String {
+    format!("`{}`: {}", err.path, err.reason)
+}
+
+fn record_ref(uri: String, cid: Cid) -> Result<RecordRef, Error> {
+    Ok(RecordRef {
+        rkey: rkey(&uri)?,
+        uri,
+        cid,
+    })
+}
+
+/// The record key, the last segment of a record's `at://` URI.
+fn rkey(uri: &str) -> Result<RecordKey, InvalidRecord> {
+    uri.rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .parse()
+        .map_err(|err| InvalidRecord {
+            uri: uri.to_string(),
+            message: format!("invalid record key: {err}"),
+        })
+}
@@ -0,0 +1,188 @@
+//! Event streams of XRPC subscriptions, like `com.atproto.sync.subscribeRepos`.
+//!
+//! Every WebSocket frame is two DAG-CBOR values, a header with the `op` and the `t` (type) of
+//! the message, followed by the message body. Error frames have an `op` of `-1` and an
+//! `{error, message}` body.
+
+use atrium_xrpc::error::{ErrorResponseBody, XrpcErrorKind};
+use futures::{Stream, StreamExt, ready};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Debug, Display};
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, tungstenite};
+
+/// A subscription's `message`, decoded from the `t` of a frame's header and the frame's body.
+pub trait Message: Sized {
+    fn decode(t: Option<&str>, body: &[u8]) -> Result<Self, DecodeError>;
+}
+
+/// A frame that is not a valid event stream frame, or whose message isn't the expected type.
+#[derive(Debug)]
+pub struct DecodeError(String);
+
+impl DecodeError {
+    pub fn new(message: impl Into<String>) -> Self {
+        DecodeError(message.into())
+    }
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+#[derive(Debug)]
+pub enum Error<E> {
+    /// The connection failed, or broke while reading the stream
+    WebSocket(tungstenite::Error),
+    /// The parameters can't be encoded as a query string
+    Params(serde_html_form::ser::Error),
+    Decode(DecodeError),
+    /// The server sent an error frame, typed when it is one of the lexicon's errors
+    Xrpc(XrpcErrorKind<E>),
+}
+
+impl<E: Display> Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::WebSocket(err) => write!(f, "websocket error: {err}"),
+            Error::Params(err) => write!(f, "invalid parameters: {err}"),
+            Error::Decode(err) => write!(f, "invalid frame: {err}"),
+            Error::Xrpc(XrpcErrorKind::Custom(err)) => write!(f, "xrpc error: {err}"),
+            Error::Xrpc(XrpcErrorKind::Undefined(body)) => write!(
+                f,
+                "xrpc error: {}: {}",
+                body.error.as_deref().unwrap_or("unknown"),
+                body.message.as_deref().unwrap_or_default()
+            ),
+        }
+    }
+}
+
+impl<E: Debug + Display> std::error::Error for Error<E> {}
+
+/// A frame split into its header and body, still to be decoded.
+#[derive(Debug)]
+pub enum Frame<'a> {
+    Message { t: Option<String>, body: &'a [u8] },
+    Error { body: &'a [u8] },
+}
+
+impl<'a> Frame<'a> {
+    pub fn split(data: &'a [u8]) -> Result<Self, DecodeError> {
+        #[derive(Deserialize)]
+        struct Header {
+            op: i64,
+            t: Option<String>,
+        }
+
+        let mut body = data;
+        let header: Header = serde_ipld_dagcbor::de::from_reader_once(&mut body)
+            .map_err(|err| DecodeError::new(format!("invalid header: {err}")))?;
+        match header.op {
+            1 => Ok(Frame::Message { t: header.t, body }),
+            -1 => Ok(Frame::Error { body }),
+            op => Err(DecodeError::new(format!("unknown op {op}"))),
+        }
+    }
+}
+
+/// Decodes the body of a message that is always the same type.
+pub fn decode_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, DecodeError> {
+    serde_ipld_dagcbor::from_slice(body).map_err(|err| DecodeError::new(err.to_string()))
+}
+
+/// The messages of a subscription, ending when the server closes the connection.
+pub struct Subscription<M, E> {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    _phantom: PhantomData<fn() -> (M, E)>,
+}
+
+impl<M, E> Stream for Subscription<M, E>
+where
+    M: Message,
+    E: DeserializeOwned,
+{
+    type Item = Result<M, Error<E>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let message = match ready!(self.stream.poll_next_unpin(cx)) {
+                None | Some(Err(tungstenite::Error::ConnectionClosed)) => return Poll::Ready(None),
+                Some(Err(err)) => return Poll::Ready(Some(Err(Error::WebSocket(err)))),
+                Some(Ok(message)) => message,
+            };
+            match message {
+                tungstenite::Message::Binary(data) => return Poll::Ready(Some(decode(&data))),
+                tungstenite::Message::Close(_) => return Poll::Ready(None),
+                // pings are answered by tungstenite, and text isn't used by event streams
+                _ => continue,
+            }
+        }
+    }
+}
+
+fn decode<M: Message, E: DeserializeOwned>(data: &[u8]) -> Result<M, Error<E>> {
+    match Frame::split(data).map_err(Error::Decode)? {
+        Frame::Message { t, body } => M::decode(t.as_deref(), body).map_err(Error::Decode),
+        Frame::Error { body } => {
+            let error = match serde_ipld_dagcbor::from_slice::<E>(body) {
+                Ok(error) => XrpcErrorKind::Custom(error),
+                Err(_) => XrpcErrorKind::Undefined(
+                    decode_body::<ErrorResponseBody>(body).map_err(Error::Decode)?,
+                ),
+            };
+            Err(Error::Xrpc(error))
+        }
+    }
+}
+
+/// Opens the subscription `nsid` on the service at `base_uri`, an `http(s)://` or `ws(s)://` URL.
+pub async fn subscribe<P, M, E>(
+    base_uri: &str,
+    nsid: &str,
+    params: Option<&P>,
+) -> Result<Subscription<M, E>, Error<E>>
+where
+    P: Serialize,
+{
+    let url = subscription_url(base_uri, nsid, params).map_err(Error::Params)?;
+    let (stream, _) = tokio_tungstenite::connect_async(url)
+        .await
+        .map_err(Error::WebSocket)?;
+    Ok(Subscription {
+        stream,
+        _phantom: PhantomData,
+    })
+}
+
+fn subscription_url<P: Serialize>(
+    base_uri: &str,
+    nsid: &str,
+    params: Option<&P>,
+) -> Result<String, serde_html_form::ser::Error> {
+    let base_uri = base_uri.trim_end_matches('/');
+    let base_uri = if let Some(rest) = base_uri.strip_prefix("https://") {
+        format!("wss://{rest}")
+    } else if let Some(rest) = base_uri.strip_prefix("http://") {
+        format!("ws://{rest}")
+    } else {
+        base_uri.to_string()
+    };
+    let mut url = format!("{base_uri}/xrpc/{nsid}");
+    if let Some(params) = params {
+        let query = serde_html_form::to_string(params)?;
+        if !query.is_empty() {
+            url.push('?');
+            url.push_str(&query);
+        }
+    }
+    Ok(url)
+}
@@ -0,0 +1,96 @@
+use esquema_client::paginate;
+use futures::StreamExt;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Params {
+    cursor: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Page {
+    items: Vec<u32>,
+    cursor: Option<String>,
+}
+
+/// Pages of two items out of `0..5`, the cursor being the next item.
+async fn fetch(params: Params) -> Result<Page, String> {
+    let start = params
+        .cursor
+        .map_or(Ok(0), |cursor| cursor.parse::<u32>())
+        .map_err(|err| err.to_string())?;
+    let items = (start..5).take(2).collect::<Vec<_>>();
+    let cursor = Some(start + 2)
+        .filter(|next| *next < 5)
+        .map(|next| next.to_string());
+    Ok(Page { items, cursor })
+}
+
+fn set_cursor(params: &mut Params, cursor: Option<String>) {
+    params.cursor = cursor;
+}
+
+fn cursor(page: &Page) -> Option<String> {
+    page.cursor.clone()
+}
+
+#[tokio::test]
+async fn follows_cursors_until_the_last_page() {
+    let pages = paginate::pages(Params { cursor: None }, fetch, set_cursor, cursor)
+        .map(Result::unwrap)
+        .map(|page| page.items)
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(pages, [vec![0, 1], vec![2, 3], vec![4]]);
+}
+
+#[tokio::test]
+async fn ends_after_an_error() {
+    let pages = paginate::pages(
+        Params {
+            cursor: Some(String::from("not a number")),
+        },
+        fetch,
+        set_cursor,
+        cursor,
+    )
+    .collect::<Vec<_>>()
+    .await;
+    assert_eq!(pages.len(), 1);
+    assert!(pages[0].is_err());
+}
+
+#[tokio::test]
+async fn stops_when_the_cursor_repeats() {
+    // a server answering every page with the cursor it was asked for
+    let stuck = |params: Params| async move {
+        Ok::<_, String>(Page {
+            items: vec![0],
+            cursor: Some(params.cursor.unwrap_or_else(|| String::from("same"))),
+        })
+    };
+    let pages = paginate::pages(Params { cursor: None }, stuck, set_cursor, cursor)
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(pages.len(), 2);
+}
+
+#[tokio::test]
+async fn stops_when_the_cursors_cycle() {
+    // a server going from page A to page B and back to A
+    let cycling = |params: Params| async move {
+        let cursor = match params.cursor.as_deref() {
+            Some("b") => "a",
+            Some("a") | None => "b",
+            Some(other) => return Err(format!("unexpected cursor `{other}`")),
+        };
+        Ok::<_, String>(Page {
+            items: vec![0],
+            cursor: Some(String::from(cursor)),
+        })
+    };
+    let pages = paginate::pages(Params { cursor: None }, cycling, set_cursor, cursor)
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(pages.len(), 3);
+    assert!(pages.iter().all(Result::is_ok));
+}
@@ -0,0 +1,243 @@
+use atrium_api::client::AtpServiceClient;
+use atrium_api::types::string::{AtIdentifier, Did, RecordKey};
+use atrium_api::types::{Collection, LimitedNonZeroU8};
+use atrium_xrpc::{HttpClient, XrpcClient};
+use esquema_client::record::{Error, RecordClient};
+use http::{Request, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+const CID: &str = "bafyreie5737gdxlw5i64vzichcalba3z2v5n6icifvx5xytvske7mr3hpm";
+const REPO: &str = "did:plc:standin";
+
+#[derive(Debug)]
+struct Statuses;
+
+impl Collection for Statuses {
+    const NSID: &'static str = "xyz.statusphere.status";
+    type Record = Status;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Status {
+    status: String,
+}
+
+/// A repo of a single account in memory, standing in for a PDS.
+#[derive(Default, Clone)]
+struct StandIn {
+    records: Arc<Mutex<BTreeMap<String, Value>>>,
+}
+
+impl StandIn {
+    fn uri(rkey: &str) -> String {
+        format!("at://{REPO}/{}/{rkey}", Statuses::NSID)
+    }
+
+    fn handle(&self, nsid: &str, query: HashMap<String, String>, body: Value) -> (u16, Value) {
+        let mut records = self.records.lock().unwrap();
+        match nsid {
+            "com.atproto.repo.createRecord" | "com.atproto.repo.putRecord" => {
+                let rkey = match body["rkey"].as_str() {
+                    Some(rkey) => rkey.to_string(),
+                    None => format!("{:04}", records.len() + 1),
+                };
+                records.insert(rkey.clone(), body["record"].clone());
+                (200, json!({ "uri": Self::uri(&rkey), "cid": CID }))
+            }
+            "com.atproto.repo.getRecord" => match records.get(&query["rkey"]) {
+                Some(value) => (
+                    200,
+                    json!({ "uri": Self::uri(&query["rkey"]), "cid": CID, "value": value }),
+                ),
+                None => (
+                    400,
+                    json!({ "error": "RecordNotFound", "message": "no such record" }),
+                ),
+            },
+            "com.atproto.repo.listRecords" => {
+                let limit = query
+                    .get("limit")
+                    .map_or(50, |limit| limit.parse().unwrap());
+                let after = query.get("cursor").cloned().unwrap_or_default();
+                let page = records
+                    .iter()
+                    .filter(|(rkey, _)| **rkey > after)
+                    .take(limit)
+                    .collect::<Vec<_>>();
+                let cursor = page
+                    .last()
+                    .filter(|(rkey, _)| records.keys().last() != Some(*rkey))
+                    .map(|(rkey, _)| rkey.to_string());
+                let page = page
+                    .into_iter()
+                    .map(|(rkey, value)| json!({ "uri": Self::uri(rkey), "cid": CID, "value": value }))
+                    .collect::<Vec<_>>();
+                (200, json!({ "records": page, "cursor": cursor }))
+            }
+            "com.atproto.repo.deleteRecord" => {
+                records.remove(body["rkey"].as_str().unwrap());
+                (200, json!({}))
+            }
+            _ => (501, json!({ "error": "MethodNotImplemented" })),
+        }
+    }
+}
+
+impl HttpClient for StandIn {
+    async fn send_http(
+        &self,
+        request: Request<Vec<u8>>,
+    ) -> Result<Response<Vec<u8>>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let nsid = request.uri().path().trim_start_matches("/xrpc/");
+        let query = serde_html_form::from_str(request.uri().query().unwrap_or_default())?;
+        let body = if request.body().is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(request.body())?
+        };
+        let (status, body) = self.handle(nsid, query, body);
+        Ok(Response::builder()
+            .status(status)
+            .header("content-type", "application/json")
+            .body(serde_json::to_vec(&body)?)?)
+    }
+}
+
+impl XrpcClient for StandIn {
+    fn base_uri(&self) -> String {
+        String::from("http://stand-in")
+    }
+}
+
+fn repo() -> AtIdentifier {
+    AtIdentifier::Did(REPO.parse::<Did>().unwrap())
+}
+
+fn status(status: &str) -> Status {
+    Status {
+        status: status.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn records_round_trip_as_the_collection_type() {
+    let stand_in = StandIn::default();
+    let client = AtpServiceClient::new(stand_in.clone());
+    let statuses = RecordClient::<Statuses, _>::new(&client.service.com.atproto.repo, repo());
+
+    let created = statuses.create(None, status("🦀")).await.unwrap();
+    assert_eq!(created.rkey.as_str(), "0001");
+    assert_eq!(
+        created.uri,
+        "at://did:plc:standin/xyz.statusphere.status/0001"
+    );
+    assert_eq!(
+        stand_in.records.lock().unwrap()["0001"],
+        json!({ "$type": "xyz.statusphere.status", "status": "🦀" })
+    );
+
+    let rkey = "custom".parse::<RecordKey>().unwrap();
+    statuses.put(rkey.clone(), status("🐚")).await.unwrap();
+    assert_eq!(
+        statuses.get(rkey.clone()).await.unwrap().value,
+        status("🐚")
+    );
+
+    let limit = LimitedNonZeroU8::try_from(1u8).unwrap();
+    let first = statuses.list(None, Some(limit)).await.unwrap();
+    assert_eq!(first.records[0].value, status("🦀"));
+    let second = statuses.list(first.cursor, Some(limit)).await.unwrap();
+    assert_eq!(second.records[0].value, status("🐚"));
+    assert_eq!(second.cursor, None);
+
+    statuses.delete(rkey.clone()).await.unwrap();
+    assert!(matches!(statuses.get(rkey).await, Err(Error::Xrpc(_))));
+}
+
+#[tokio::test]
+async fn invalid_records_are_listed_apart_naming_their_uri() {
+    let stand_in = StandIn::default();
+    let mut records = stand_in.records.lock().unwrap();
+    records.insert(
+        String::from("0001"),
+        json!({ "$type": "xyz.statusphere.status", "status": 42 }),
+    );
+    records.insert(
+        String::from("0002"),
+        json!({ "$type": "xyz.statusphere.status", "status": "🦀" }),
+    );
+    drop(records);
+    let client = AtpServiceClient::new(stand_in);
+    let statuses = RecordClient::<Statuses, _>::new(&client.service.com.atproto.repo, repo());
+
+    let page = statuses.list(None, None).await.unwrap();
+    assert_eq!(page.records.len(), 1);
+    assert_eq!(page.records[0].value, status("🦀"));
+    assert_eq!(page.invalid.len(), 1);
+    assert_eq!(
+        page.invalid[0].uri,
+        "at://did:plc:standin/xyz.statusphere.status/0001"
+    );
+    assert!(
+        page.invalid[0].message.starts_with("`status`: "),
+        "{}",
+        page.invalid[0].message
+    );
+
+    let err = statuses
+        .get("0001".parse::<RecordKey>().unwrap())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::Decode { uri, message }
+            if uri == "at://did:plc:standin/xyz.statusphere.status/0001"
+                && message.starts_with("`status`: ")
+    ));
+}
+
+#[tokio::test]
+async fn an_invalid_record_keeps_its_page_and_cursor() {
+    let stand_in = StandIn::default();
+    let mut records = stand_in.records.lock().unwrap();
+    records.insert(
+        String::from("0001"),
+        json!({ "$type": "xyz.statusphere.status", "status": "🦀" }),
+    );
+    records.insert(
+        String::from("0002"),
+        json!({ "$type": "xyz.statusphere.status" }),
+    );
+    records.insert(
+        String::from("0003"),
+        json!({ "$type": "xyz.statusphere.status", "status": "🐚" }),
+    );
+    drop(records);
+    let client = AtpServiceClient::new(stand_in);
+    let statuses = RecordClient::<Statuses, _>::new(&client.service.com.atproto.repo, repo());
+
+    let limit = LimitedNonZeroU8::try_from(2u8).unwrap();
+    let first = statuses.list(None, Some(limit)).await.unwrap();
+    assert_eq!(
+        first
+            .records
+            .iter()
+            .map(|record| record.rkey.as_str())
+            .collect::<Vec<_>>(),
+        ["0001"]
+    );
+    assert_eq!(first.invalid.len(), 1);
+    assert_eq!(
+        first.invalid[0].uri,
+        "at://did:plc:standin/xyz.statusphere.status/0002"
+    );
+    assert!(first.invalid[0].message.contains("status"));
+    assert_eq!(first.cursor.as_deref(), Some("0002"));
+
+    let second = statuses.list(first.cursor, Some(limit)).await.unwrap();
+    assert_eq!(second.records[0].value, status("🐚"));
+    assert!(second.invalid.is_empty());
+}
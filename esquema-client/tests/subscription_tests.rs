@@ -0,0 +1,168 @@
+use atrium_xrpc::error::XrpcErrorKind;
+use esquema_client::subscription::{self, DecodeError, Error};
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+
+const NSID: &str = "com.example.subscribeTicks";
+
+/// What is generated for a subscription with a `#tick` union message.
+#[derive(Debug, PartialEq)]
+enum Message {
+    Tick(Box<Tick>),
+    Unknown { t: String, body: Vec<u8> },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Tick {
+    seq: i64,
+}
+
+impl subscription::Message for Message {
+    fn decode(t: Option<&str>, body: &[u8]) -> Result<Self, DecodeError> {
+        let t = t.ok_or_else(|| DecodeError::new("message without a `t`"))?;
+        match t {
+            "com.example.subscribeTicks#tick" | "#tick" => {
+                subscription::decode_body(body).map(|tick| Message::Tick(Box::new(tick)))
+            }
+            _ => Ok(Message::Unknown {
+                t: t.to_string(),
+                body: body.to_vec(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(tag = "error", content = "message")]
+enum TickError {
+    FutureCursor(Option<String>),
+}
+
+impl std::fmt::Display for TickError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+#[derive(Serialize)]
+struct Params {
+    cursor: Option<i64>,
+}
+
+fn frame(header: impl Serialize, body: impl Serialize) -> WsMessage {
+    let mut data = serde_ipld_dagcbor::to_vec(&header).unwrap();
+    data.extend(serde_ipld_dagcbor::to_vec(&body).unwrap());
+    WsMessage::binary(data)
+}
+
+/// Serves a single connection with `frames`, returning the base URI and the requested path.
+async fn serve(frames: Vec<WsMessage>) -> (String, Arc<Mutex<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_uri = format!("http://{}", listener.local_addr().unwrap());
+    let requested = Arc::new(Mutex::new(String::new()));
+    let path = Arc::clone(&requested);
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_hdr_async(stream, |req: &Request, res: Response| {
+            *path.lock().unwrap() = req.uri().to_string();
+            Ok(res)
+        })
+        .await
+        .unwrap();
+        for frame in frames {
+            ws.send(frame).await.unwrap();
+        }
+        ws.close(None).await.unwrap();
+    });
+    (base_uri, requested)
+}
+
+#[derive(Serialize)]
+struct Header<'a> {
+    op: i64,
+    t: &'a str,
+}
+
+#[tokio::test]
+async fn yields_messages_until_the_server_closes() {
+    let (base_uri, requested) = serve(vec![
+        frame(Header { op: 1, t: "#tick" }, Tick { seq: 1 }),
+        frame(Header { op: 1, t: "#tick" }, Tick { seq: 2 }),
+        frame(Header { op: 1, t: "#tock" }, Tick { seq: 3 }),
+    ])
+    .await;
+    let messages = subscription::subscribe::<_, Message, TickError>(
+        &base_uri,
+        NSID,
+        Some(&Params { cursor: Some(5) }),
+    )
+    .await
+    .unwrap()
+    .collect::<Vec<_>>()
+    .await;
+    assert_eq!(
+        requested.lock().unwrap().as_str(),
+        "/xrpc/com.example.subscribeTicks?cursor=5"
+    );
+    let messages = messages.into_iter().map(Result::unwrap).collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        [
+            Message::Tick(Box::new(Tick { seq: 1 })),
+            Message::Tick(Box::new(Tick { seq: 2 })),
+            Message::Unknown {
+                t: String::from("#tock"),
+                body: serde_ipld_dagcbor::to_vec(&Tick { seq: 3 }).unwrap()
+            }
+        ]
+    );
+}
+
+#[tokio::test]
+async fn error_frames_become_xrpc_errors() {
+    #[derive(Serialize)]
+    struct ErrorHeader {
+        op: i64,
+    }
+    #[derive(Serialize)]
+    struct ErrorBody<'a> {
+        error: &'a str,
+        message: &'a str,
+    }
+
+    let (base_uri, _) = serve(vec![
+        frame(
+            ErrorHeader { op: -1 },
+            ErrorBody {
+                error: "FutureCursor",
+                message: "cursor is in the future",
+            },
+        ),
+        frame(
+            ErrorHeader { op: -1 },
+            ErrorBody {
+                error: "ConsumerTooSlow",
+                message: "bye",
+            },
+        ),
+    ])
+    .await;
+    let mut subscription = subscription::subscribe::<(), Message, TickError>(&base_uri, NSID, None)
+        .await
+        .unwrap();
+    assert!(matches!(
+        subscription.next().await,
+        Some(Err(Error::Xrpc(XrpcErrorKind::Custom(TickError::FutureCursor(Some(message))))))
+            if message == "cursor is in the future"
+    ));
+    assert!(matches!(
+        subscription.next().await,
+        Some(Err(Error::Xrpc(XrpcErrorKind::Undefined(body))))
+            if body.error.as_deref() == Some("ConsumerTooSlow")
+    ));
+    assert!(subscription.next().await.is_none());
+}
@@ -0,0 +1,280 @@
+use crate::config::Config;
+use crate::context::Context;
+use crate::error::CodegenError;
+use crate::generator::{
+    HEADER, generate_client, generate_lexicons_mod_or_lib, generate_modules, generate_records,
+    generate_schemas, generate_server,
+};
+use crate::paths::ExternalCrates;
+use crate::{canonicalize_and_validate, read_lexdir};
+use atrium_lex::LexiconDoc;
+use atrium_lex::lexicon::LexUserType;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Generates Rust types from lexicons, configured through [`Generator::builder`].
+///
+/// ```no_run
+/// use esquema_codegen::Generator;
+///
+/// let generated = Generator::builder()
+///     .lexdir("lexicons")
+///     .outdir("src")
+///     .module("lexicons")
+///     .client(false)
+///     .build()
+///     .generate()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Generator {
+    lexdirs: Vec<PathBuf>,
+    lexicons: Vec<LexiconDoc>,
+    outdir: PathBuf,
+    module_name: Option<String>,
+    externals: ExternalCrates,
+    derives: Vec<String>,
+    features: BTreeMap<String, String>,
+    client: bool,
+    server: bool,
+    records: bool,
+    infallible_record_conversions: bool,
+    header: String,
+    format: bool,
+}
+
+/// Options for a [`Generator`], every one of them has a default so new ones can be added without breaking callers.
+#[derive(Debug, Clone)]
+pub struct GeneratorBuilder {
+    generator: Generator,
+}
+
+impl Generator {
+    pub fn builder() -> GeneratorBuilder {
+        GeneratorBuilder {
+            generator: Generator {
+                lexdirs: Vec::new(),
+                lexicons: Vec::new(),
+                outdir: PathBuf::from("."),
+                module_name: None,
+                externals: ExternalCrates::default(),
+                derives: Vec::new(),
+                features: BTreeMap::new(),
+                client: true,
+                server: false,
+                records: true,
+                infallible_record_conversions: false,
+                header: HEADER.to_string(),
+                format: true,
+            },
+        }
+    }
+
+    pub(crate) fn module_name(&self) -> &Option<String> {
+        &self.module_name
+    }
+
+    pub(crate) fn externals(&self) -> &ExternalCrates {
+        &self.externals
+    }
+
+    pub(crate) fn derives(&self) -> &[String] {
+        &self.derives
+    }
+
+    pub(crate) fn infallible_record_conversions(&self) -> bool {
+        self.infallible_record_conversions
+    }
+
+    pub(crate) fn header(&self) -> &str {
+        &self.header
+    }
+
+    pub(crate) fn format(&self) -> bool {
+        self.format
+    }
+
+    /// Writes the generated files, returning their paths.
+    pub fn generate(&self) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let mut outdir =
+            canonicalize_and_validate(&self.outdir, "Output directory does not exist")?;
+        if let Some(module_name) = &self.module_name {
+            outdir.push(module_name);
+        }
+        if !outdir.exists() {
+            return Err(format!("Output directory does not exist: {:?}", outdir).into());
+        }
+        let mut schemas = self.lexicons.clone();
+        for lexdir in &self.lexdirs {
+            schemas.extend(read_lexdir(lexdir)?);
+        }
+        let schemas = schemas
+            .into_iter()
+            .filter(|schema| !self.externals.contains(&schema.id))
+            .collect::<Vec<_>>();
+        let ctx = Context::new(self);
+        let mut results = Vec::new();
+        // the first two segments of the NSIDs, e.g. `com.example`, with the feature gating each
+        let mut namespaces: Vec<(String, Option<&str>)> = Vec::new();
+        for doc in &schemas {
+            let parts = doc.id.split('.').collect::<Vec<_>>();
+            if parts.len() < 3 {
+                return Err(CodegenError::InvalidNsid {
+                    nsid: doc.id.clone(),
+                }
+                .into());
+            }
+            results.extend(generate_schemas(doc, &outdir, &ctx)?);
+            let namespace = format!("{}.{}", parts[0], parts[1]);
+            if !namespaces.iter().any(|(known, _)| *known == namespace) {
+                let feature = self.features.get(&namespace).map(String::as_str);
+                namespaces.push((namespace, feature));
+            }
+        }
+
+        let generate_client_module = self.client
+            && schemas
+                .iter()
+                .flat_map(|schema| schema.defs.values())
+                .any(|def| {
+                    matches!(
+                        def,
+                        LexUserType::XrpcQuery(_)
+                            | LexUserType::XrpcProcedure(_)
+                            | LexUserType::XrpcSubscription(_)
+                    )
+                });
+        let generate_server_module = self.server
+            && schemas.iter().any(|schema| {
+                matches!(
+                    schema.defs.get("main"),
+                    Some(LexUserType::XrpcQuery(_) | LexUserType::XrpcProcedure(_))
+                )
+            });
+        if self.records {
+            results.push(generate_records(&outdir, &schemas, &namespaces, &ctx)?);
+        }
+        if generate_client_module {
+            results.push(generate_client(&outdir, &schemas, &namespaces, &ctx)?);
+        }
+        if generate_server_module {
+            results.push(generate_server(&outdir, &schemas, &namespaces, &ctx)?);
+        }
+
+        results.push(generate_lexicons_mod_or_lib(
+            &outdir,
+            &namespaces,
+            self.module_name.is_none(),
+            self.records,
+            generate_client_module,
+            generate_server_module,
+            &ctx,
+        )?);
+        results.extend(generate_modules(&outdir, &schemas, &namespaces, &ctx)?);
+
+        Ok(results)
+    }
+}
+
+impl GeneratorBuilder {
+    /// Adds a directory of lexicon JSON files, searched recursively.
+    pub fn lexdir(mut self, lexdir: impl AsRef<Path>) -> Self {
+        self.generator.lexdirs.push(lexdir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Adds lexicons that have already been loaded.
+    pub fn lexicons(mut self, lexicons: impl IntoIterator<Item = LexiconDoc>) -> Self {
+        self.generator.lexicons.extend(lexicons);
+        self
+    }
+
+    /// The directory the files are written to, the current directory by default.
+    pub fn outdir(mut self, outdir: impl AsRef<Path>) -> Self {
+        self.generator.outdir = outdir.as_ref().to_path_buf();
+        self
+    }
+
+    /// Generates into `outdir/module/mod.rs` instead of `outdir/lib.rs`.
+    pub fn module(mut self, module_name: impl Into<String>) -> Self {
+        self.generator.module_name = Some(module_name.into());
+        self
+    }
+
+    /// The crates providing lexicons instead of generating them, [`ExternalCrates::default`] by default.
+    pub fn externals(mut self, externals: ExternalCrates) -> Self {
+        self.generator.externals = externals;
+        self
+    }
+
+    /// Adds a derive to every generated struct and enum, e.g. `Hash`. `Default` is left off enums, which can't derive
+    /// it without a `#[default]` variant, and off structs implementing it with the lexicon's `default` and `const`
+    /// values.
+    pub fn derive(mut self, derive: impl Into<String>) -> Self {
+        self.generator.derives.push(derive.into());
+        self
+    }
+
+    /// Gates a namespace (the first two NSID segments, e.g. `xyz.statusphere`) behind a cargo feature.
+    pub fn feature(mut self, namespace: impl Into<String>, feature: impl Into<String>) -> Self {
+        self.generator
+            .features
+            .insert(namespace.into(), feature.into());
+        self
+    }
+
+    /// Whether `client.rs` is generated for XRPC lexicons, on by default.
+    pub fn client(mut self, client: bool) -> Self {
+        self.generator.client = client;
+        self
+    }
+
+    /// Whether `server.rs` is generated, with a `Handler` trait for the queries and procedures and
+    /// an axum router serving it, off by default.
+    pub fn server(mut self, server: bool) -> Self {
+        self.generator.server = server;
+        self
+    }
+
+    /// Whether `record.rs` with the `KnownRecord` enum is generated, on by default.
+    pub fn records(mut self, records: bool) -> Self {
+        self.generator.records = records;
+        self
+    }
+
+    /// Whether records convert from and to `Unknown` with `From`, panicking on a record that doesn't
+    /// fit its type, instead of with `TryFrom`, off by default.
+    pub fn infallible_record_conversions(mut self, infallible: bool) -> Self {
+        self.generator.infallible_record_conversions = infallible;
+        self
+    }
+
+    /// The comment at the top of every generated file, an empty header leaves it out.
+    pub fn header(mut self, header: impl Into<String>) -> Self {
+        self.generator.header = header.into();
+        self
+    }
+
+    /// Whether the output is formatted with prettyplease, on by default.
+    pub fn format(mut self, format: bool) -> Self {
+        self.generator.format = format;
+        self
+    }
+
+    /// Takes the output, externals, derives, features and whether a server is generated from `config`, but not its
+    /// sources.
+    pub fn config(mut self, config: &Config) -> Self {
+        self.generator.outdir = config.output.dir.clone();
+        self.generator.module_name = config.output.module.clone();
+        self.generator.externals = config.external_crates();
+        self.generator.derives = config.derives.clone();
+        self.generator.features = config.features.clone();
+        self.generator.server = config.server;
+        self
+    }
+
+    pub fn build(self) -> Generator {
+        self.generator
+    }
+}
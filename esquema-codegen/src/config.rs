@@ -0,0 +1,181 @@
+//! `esquema.toml`, describing a whole generation so it doesn't have to be repeated on the command line.
+
+use crate::paths::ExternalCrates;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_FILE_NAME: &str = "esquema.toml";
+
+/// The collection lexicon schema records are published in.
+pub const LEXICON_SCHEMA_COLLECTION: &str = "com.atproto.lexicon.schema";
+
+/// A generation, as read from `esquema.toml`.
+///
+/// ```toml
+/// server = true
+///
+/// [[source]]
+/// local = "lexicons"
+///
+/// [[source]]
+/// remote = { handle = "example.com", namespace = "com.example" }
+///
+/// [[source]]
+/// nsids = ["xyz.statusphere.status"]
+///
+/// [output]
+/// dir = "src"
+/// module = "lexicons"
+///
+/// [externals]
+/// "com.example.shared" = "example_shared::com::example::shared"
+///
+/// [features]
+/// "xyz.statusphere" = "statusphere"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    #[serde(default, rename = "source")]
+    pub sources: Vec<Source>,
+    pub output: Output,
+    /// Whether the Bluesky namespaces map to `atrium_api`, see [`ExternalCrates::default`]
+    #[serde(default = "default_true")]
+    pub default_externals: bool,
+    /// NSID prefix to the Rust path of the module providing it
+    #[serde(default)]
+    pub externals: BTreeMap<String, String>,
+    /// Derives added to every generated struct and enum, e.g. `Hash`. `Default` is left off enums and off structs
+    /// implementing it with the lexicon's defaults
+    #[serde(default)]
+    pub derives: Vec<String>,
+    /// Namespace (the first two NSID segments, e.g. `xyz.statusphere`) to the cargo feature gating it
+    #[serde(default)]
+    pub features: BTreeMap<String, String>,
+    /// Whether `server.rs` is generated, with a `Handler` trait and axum router for the queries and procedures
+    #[serde(default)]
+    pub server: bool,
+}
+
+/// Where lexicons are read from.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Source {
+    /// A directory of lexicon JSON files
+    Local(PathBuf),
+    /// The lexicon schema records of an atproto repo
+    Remote(RemoteSource),
+    /// NSIDs resolved through their authority's `_lexicon` DNS record
+    Nsids(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RemoteSource {
+    pub handle: String,
+    /// Only records whose NSID starts with this are generated
+    pub namespace: String,
+    #[serde(default = "default_collection")]
+    pub collection: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Output {
+    pub dir: PathBuf,
+    /// Generates into `dir/module/mod.rs` instead of `dir/lib.rs`
+    #[serde(default)]
+    pub module: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_collection() -> String {
+    LEXICON_SCHEMA_COLLECTION.to_string()
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    /// The source needs network access, which only esquema-cli provides
+    UnsupportedSource(Source),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            ConfigError::Parse { path, source } => {
+                write!(f, "{} is not a valid config: {source}", path.display())
+            }
+            ConfigError::UnsupportedSource(source) => write!(
+                f,
+                "{source:?} can't be read by esquema-codegen, use `esquema-cli generate config` instead"
+            ),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            ConfigError::Parse { source, .. } => Some(source),
+            ConfigError::UnsupportedSource(_) => None,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config at `path`, making relative paths in it relative to the config's directory.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let mut config = toml::from_str::<Self>(&content).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        if let Some(base) = path.parent() {
+            config.rebase(base);
+        }
+        Ok(config)
+    }
+
+    /// The external crate mappings this config generates against.
+    pub fn external_crates(&self) -> ExternalCrates {
+        let mut externals = if self.default_externals {
+            ExternalCrates::default()
+        } else {
+            ExternalCrates::empty()
+        };
+        for (prefix, path) in &self.externals {
+            externals.insert(prefix, path);
+        }
+        externals
+    }
+
+    fn rebase(&mut self, base: &Path) {
+        for source in &mut self.sources {
+            if let Source::Local(dir) = source {
+                *dir = base.join(&*dir);
+            }
+        }
+        self.output.dir = base.join(&self.output.dir);
+    }
+}
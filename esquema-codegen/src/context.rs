@@ -0,0 +1,134 @@
+use crate::builder::Generator;
+use crate::paths::{ExternalCrates, variant_name};
+use heck::{ToPascalCase, ToSnakeCase};
+use itertools::Itertools;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Path, Result};
+
+/// Settings shared by everything generated for one crate.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Context<'a> {
+    module_name: &'a Option<String>,
+    externals: &'a ExternalCrates,
+    derives: &'a [String],
+    infallible_record_conversions: bool,
+    header: &'a str,
+    format: bool,
+}
+
+impl<'a> Context<'a> {
+    pub(crate) fn new(generator: &'a Generator) -> Self {
+        Self {
+            module_name: generator.module_name(),
+            externals: generator.externals(),
+            derives: generator.derives(),
+            infallible_record_conversions: generator.infallible_record_conversions(),
+            header: generator.header(),
+            format: generator.format(),
+        }
+    }
+
+    /// The comment written at the top of every generated file.
+    pub(crate) fn header(&self) -> &str {
+        self.header
+    }
+
+    /// Whether records get panicking `From` conversions from and to `Unknown` instead of `TryFrom`.
+    pub(crate) fn infallible_record_conversions(&self) -> bool {
+        self.infallible_record_conversions
+    }
+
+    /// Whether generated files are formatted with prettyplease.
+    pub(crate) fn format(&self) -> bool {
+        self.format
+    }
+
+    /// Whether a ref made from within a generated lexicon points outside of the generated crate.
+    pub(crate) fn is_external(&self, r#ref: &str) -> bool {
+        let (namespace, _) = r#ref.split_once('#').unwrap_or((r#ref, ""));
+        !namespace.is_empty() && self.externals.contains(namespace)
+    }
+
+    /// The path of `ref`, using the `default` def name when the ref has no fragment.
+    pub(crate) fn resolve(&self, r#ref: &str, default: &str) -> Result<TokenStream> {
+        let (namespace, def) = r#ref.split_once('#').unwrap_or((r#ref, default));
+        let def = if def.chars().all(char::is_uppercase) {
+            def.to_string()
+        } else {
+            def.to_pascal_case()
+        };
+        let path = syn::parse_str::<Path>(&if namespace.is_empty() {
+            def
+        } else {
+            let (prefix, rest) = match self.externals.find(namespace) {
+                Some((prefix, path)) => (path.to_string(), &namespace[prefix.len()..]),
+                None => match self.module_name {
+                    None => (String::from("crate"), namespace),
+                    Some(module_name) => (format!("crate::{module_name}"), namespace),
+                },
+            };
+            let modules = rest
+                .split('.')
+                .filter(|segment| !segment.is_empty())
+                .map(str::to_snake_case);
+            std::iter::once(prefix)
+                .chain(modules)
+                .chain([def])
+                .join("::")
+        })?;
+        Ok(quote!(#path))
+    }
+
+    /// The enum variant for `ref`, named after the path of its generated type, so a ref to
+    /// `com.example.foo#bar` in a `lexicons` module is `LexiconsComExampleFooBar`.
+    pub(crate) fn variant_name(&self, r#ref: &str, default: &str) -> String {
+        let name = variant_name(r#ref, default);
+        match self.module_name {
+            Some(module_name) if !r#ref.starts_with('#') && !self.is_external(r#ref) => {
+                let module = module_name.split("::").map(str::to_pascal_case).join("");
+                format!("{module}{name}")
+            }
+            _ => name,
+        }
+    }
+
+    /// The `#[derive(...)]` attribute for generated structs, including any extra derives.
+    pub(crate) fn derives(&self) -> Result<TokenStream> {
+        derive_attribute(self.derives.iter().map(String::as_str))
+    }
+
+    /// The `#[derive(...)]` attribute for generated enums, and objects with `default` or `const`
+    /// fields, including the extra derives but `Default`. An enum can only derive it with a
+    /// `#[default]` variant, and those objects implement it with the lexicon's values.
+    pub(crate) fn derives_without_default(&self) -> Result<TokenStream> {
+        derive_attribute(
+            self.derives
+                .iter()
+                .filter(|derive| !is_default(derive))
+                .map(String::as_str),
+        )
+    }
+}
+
+fn derive_attribute<'a>(extra: impl Iterator<Item = &'a str>) -> Result<TokenStream> {
+    let mut derives = Vec::new();
+    for derive in [
+        "serde::Serialize",
+        "serde::Deserialize",
+        "Debug",
+        "Clone",
+        "PartialEq",
+        "Eq",
+    ]
+    .into_iter()
+    .chain(extra)
+    {
+        derives.push(syn::parse_str::<Path>(derive)?);
+    }
+    Ok(quote!(#[derive(#(#derives),*)]))
+}
+
+fn is_default(derive: &str) -> bool {
+    derive == "Default" || derive.ends_with("::Default")
+}
@@ -0,0 +1,169 @@
+use esquema_validator::Violation;
+use std::error::Error;
+use std::fmt::{self, Display};
+
+/// Why a lexicon could not be turned into Rust, with where in the lexicon it happened.
+///
+/// `path` is the JSON path within the lexicon document, e.g. `defs.main.record.properties.count`.
+#[derive(Debug)]
+pub enum CodegenError {
+    /// The lexicon uses a construct there is no Rust type for yet
+    Unsupported {
+        nsid: String,
+        def: String,
+        path: String,
+        construct: String,
+    },
+    /// The lexicon's id has too few segments to name the modules it is generated into
+    InvalidNsid { nsid: String },
+    /// The tokens generated for the lexicon are not valid Rust, e.g. a name that isn't an identifier
+    Syntax {
+        nsid: String,
+        def: String,
+        path: String,
+        source: syn::Error,
+    },
+    /// A `default` or `const` of the lexicon doesn't satisfy its own property's constraints
+    InvalidValue {
+        nsid: String,
+        def: String,
+        path: String,
+        reason: String,
+    },
+}
+
+impl CodegenError {
+    /// An unsupported construct, located by the callers through [`CodegenError::at`] and [`CodegenError::in_def`].
+    pub(crate) fn unsupported(construct: impl Into<String>) -> Self {
+        CodegenError::Unsupported {
+            nsid: String::new(),
+            def: String::new(),
+            path: String::new(),
+            construct: construct.into(),
+        }
+    }
+
+    /// A `default` or `const` failing `violations`, located like [`CodegenError::unsupported`].
+    pub(crate) fn invalid_value(violations: &[Violation]) -> Self {
+        CodegenError::InvalidValue {
+            nsid: String::new(),
+            def: String::new(),
+            path: String::new(),
+            reason: violations
+                .iter()
+                .map(|violation| violation.message.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+
+    /// Prepends `segment` to the JSON path.
+    pub(crate) fn at(mut self, segment: impl AsRef<str>) -> Self {
+        if let Some(path) = self.path_mut() {
+            *path = if path.is_empty() {
+                segment.as_ref().to_string()
+            } else {
+                format!("{}.{path}", segment.as_ref())
+            };
+        }
+        self
+    }
+
+    /// Sets the lexicon and def the error happened in.
+    pub(crate) fn in_def(self, schema_id: &str, name: &str) -> Self {
+        let mut located = self.at(format!("defs.{name}"));
+        if let CodegenError::Unsupported { nsid, def, .. }
+        | CodegenError::Syntax { nsid, def, .. }
+        | CodegenError::InvalidValue { nsid, def, .. } = &mut located
+        {
+            *nsid = schema_id.to_string();
+            *def = name.to_string();
+        }
+        located
+    }
+
+    pub fn nsid(&self) -> &str {
+        match self {
+            CodegenError::Unsupported { nsid, .. }
+            | CodegenError::InvalidNsid { nsid }
+            | CodegenError::Syntax { nsid, .. }
+            | CodegenError::InvalidValue { nsid, .. } => nsid,
+        }
+    }
+
+    pub fn def(&self) -> Option<&str> {
+        match self {
+            CodegenError::Unsupported { def, .. }
+            | CodegenError::Syntax { def, .. }
+            | CodegenError::InvalidValue { def, .. } => Some(def),
+            CodegenError::InvalidNsid { .. } => None,
+        }
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            CodegenError::Unsupported { path, .. }
+            | CodegenError::Syntax { path, .. }
+            | CodegenError::InvalidValue { path, .. } => Some(path),
+            CodegenError::InvalidNsid { .. } => None,
+        }
+    }
+
+    fn path_mut(&mut self) -> Option<&mut String> {
+        match self {
+            CodegenError::Unsupported { path, .. }
+            | CodegenError::Syntax { path, .. }
+            | CodegenError::InvalidValue { path, .. } => Some(path),
+            CodegenError::InvalidNsid { .. } => None,
+        }
+    }
+
+    fn location(&self) -> String {
+        match (self.nsid(), self.path()) {
+            ("", None | Some("")) => String::new(),
+            ("", Some(path)) => format!(" at {path}"),
+            (nsid, None | Some("")) => format!(" in {nsid}"),
+            (nsid, Some(path)) => format!(" in {nsid} at {path}"),
+        }
+    }
+}
+
+impl Display for CodegenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodegenError::Unsupported { construct, .. } => {
+                write!(f, "{construct} is not supported{}", self.location())
+            }
+            CodegenError::InvalidNsid { nsid } => write!(
+                f,
+                "`{nsid}` is not a valid NSID, it needs at least an authority and a name"
+            ),
+            CodegenError::Syntax { source, .. } => {
+                write!(f, "generated invalid Rust{}: {source}", self.location())
+            }
+            CodegenError::InvalidValue { reason, .. } => {
+                write!(f, "invalid value{}: {reason}", self.location())
+            }
+        }
+    }
+}
+
+impl Error for CodegenError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CodegenError::Syntax { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<syn::Error> for CodegenError {
+    fn from(source: syn::Error) -> Self {
+        CodegenError::Syntax {
+            nsid: String::new(),
+            def: String::new(),
+            path: String::new(),
+            source,
+        }
+    }
+}
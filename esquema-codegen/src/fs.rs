@@ -1,7 +1,6 @@
 // Forked from atrium-codegen
 // https://github.com/sugyan/atrium/blob/main/lexicon/atrium-codegen/src/fs.rs
 
-use std::ffi::OsStr;
 use std::fs::read_dir;
 use std::io::Result;
 use std::path::{Path, PathBuf};
@@ -21,14 +20,6 @@ where
     Ok(())
 }
 
-pub(crate) fn find_schemas(path: &Path) -> Result<Vec<impl AsRef<Path>>> {
-    let mut results = Vec::new();
-    walk(path, &mut results, &mut |path| {
-        path.extension().and_then(OsStr::to_str) == Some("json")
-    })?;
-    Ok(results)
-}
-
 pub(crate) fn find_dirs(path: &Path) -> Result<Vec<impl AsRef<Path>>> {
     let mut results = Vec::new();
     walk(path, &mut results, &mut |path| path.is_dir())?;
@@ -1,10 +1,11 @@
 // Forked from atrium-codegen
 // https://github.com/sugyan/atrium/blob/main/lexicon/atrium-codegen/src/generator.rs
 
+use crate::context::Context;
 use crate::fs::find_dirs;
 use crate::schema::find_ref_unions;
 use crate::token_stream::{
-    client, collection, enum_common, impl_into_record, lexicon_module, modules, ref_unions,
+    client, collection, enum_common, impl_into_record, lexicon_module, modules, ref_unions, server,
     user_type,
 };
 use atrium_lex::LexiconDoc;
@@ -19,18 +20,18 @@ use std::fs::{File, create_dir_all, read_dir};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
-const HEADER: &str = "// @generated - This file is generated by esquema-codegen (forked from atrium-codegen). DO NOT EDIT.";
+pub(crate) const HEADER: &str = "// @generated - This file is generated by esquema-codegen (forked from atrium-codegen). DO NOT EDIT.";
 
 pub(crate) fn generate_schemas(
     schema: &LexiconDoc,
     outdir: &Path,
+    ctx: &Context,
 ) -> Result<Vec<PathBuf>, Box<dyn Error>> {
     let mut results = Vec::new();
     let mut paths = schema.id.split('.').collect::<Vec<_>>();
     if let Some(basename) = paths.pop() {
         let mut tokens = Vec::new();
         let mut names = Vec::new();
-        let mut needs_try_from_unknown = false;
         for (name, def) in &schema.defs {
             // NSID (for XRPC Query, Procedure, Subscription)
             if matches!(
@@ -44,23 +45,20 @@ pub(crate) fn generate_schemas(
                     pub const NSID: &str = #nsid;
                 });
             }
-            if matches!(def, LexUserType::Record(_)) {
-                needs_try_from_unknown = true;
-            }
 
             // main def
             if name == "main" {
-                tokens.push(user_type(def, &schema.id, basename, true)?);
+                tokens.push(user_type(def, &schema.id, basename, true, ctx)?);
             } else {
                 names.push(name);
             }
         }
         // other defs
         for &name in names.iter().sorted() {
-            tokens.push(user_type(&schema.defs[name], &schema.id, name, false)?);
+            tokens.push(user_type(&schema.defs[name], &schema.id, name, false, ctx)?);
         }
         // ref unions
-        tokens.push(ref_unions(&schema.id, &find_ref_unions(&schema.defs))?);
+        tokens.push(ref_unions(&schema.id, &find_ref_unions(&schema.defs), ctx)?);
 
         let documentation = {
             let doc = format!("Definitions for the `{}` namespace.", schema.id);
@@ -75,18 +73,9 @@ pub(crate) fn generate_schemas(
             }
         };
 
-        let content = if needs_try_from_unknown {
-            quote! {
-                #documentation
-                use atrium_api::types::TryFromUnknown;
-
-                #(#tokens)*
-            }
-        } else {
-            quote! {
-                #documentation
-                #(#tokens)*
-            }
+        let content = quote! {
+            #documentation
+            #(#tokens)*
         };
 
         let dir = outdir.join(paths.join("/"));
@@ -94,7 +83,7 @@ pub(crate) fn generate_schemas(
         let mut filename = PathBuf::from(basename.to_snake_case());
         filename.set_extension("rs");
         let path = dir.join(filename);
-        write_to_file(File::create(&path)?, content)?;
+        write_to_file(File::create(&path)?, content, ctx)?;
         results.push(path);
     }
     Ok(results)
@@ -104,7 +93,7 @@ pub(crate) fn generate_records(
     outdir: &Path,
     schemas: &[LexiconDoc],
     namespaces: &[(String, Option<&str>)],
-    module_name: &Option<String>,
+    ctx: &Context,
 ) -> Result<PathBuf, Box<dyn Error>> {
     let records = schemas
         .iter()
@@ -117,21 +106,45 @@ pub(crate) fn generate_records(
         })
         .sorted()
         .collect_vec();
-    let known_record = enum_common(&records, "KnownRecord", None, namespaces, module_name)?;
-    let impl_into = impl_into_record(&records, namespaces, &module_name)?;
+    let known_record = enum_common(&records, "KnownRecord", None, namespaces, ctx)?;
+    let impl_into = impl_into_record(&records, namespaces, ctx)?;
+    let into_unknown = if ctx.infallible_record_conversions() {
+        quote! {
+            impl From<KnownRecord> for atrium_api::types::Unknown {
+                fn from(record: KnownRecord) -> Self {
+                    esquema_validator::record::to_unknown(None, &record)
+                        .unwrap_or_else(|err| panic!("{err}"))
+                }
+            }
+        }
+    } else {
+        quote! {
+            impl TryFrom<KnownRecord> for atrium_api::types::Unknown {
+                type Error = esquema_validator::RecordError;
+
+                fn try_from(record: KnownRecord) -> core::result::Result<Self, Self::Error> {
+                    esquema_validator::record::to_unknown(None, &record)
+                }
+            }
+        }
+    };
     let content = quote! {
         #![doc = "A collection of known record types."]
         #known_record
         #impl_into
 
-        impl Into<atrium_api::types::Unknown> for KnownRecord {
-            fn into(self) -> atrium_api::types::Unknown {
-                atrium_api::types::TryIntoUnknown::try_into_unknown(&self).unwrap()
+        impl TryFrom<atrium_api::types::Unknown> for KnownRecord {
+            type Error = esquema_validator::RecordError;
+
+            fn try_from(value: atrium_api::types::Unknown) -> core::result::Result<Self, Self::Error> {
+                esquema_validator::record::from_unknown(None, &value)
             }
         }
+
+        #into_unknown
     };
     let path = outdir.join("record.rs");
-    write_to_file(File::create(&path)?, content)?;
+    write_to_file(File::create(&path)?, content, ctx)?;
     Ok(path)
 }
 
@@ -139,15 +152,22 @@ pub(crate) fn generate_client(
     outdir: &Path,
     schemas: &[LexiconDoc],
     namespaces: &[(String, Option<&str>)],
+    ctx: &Context,
 ) -> Result<PathBuf, Box<dyn Error>> {
     let mut schema_map = HashMap::new();
     let mut tree = HashMap::new();
     for schema in schemas {
         if let Some(def) = schema.defs.get("main") {
-            if matches!(
-                def,
-                LexUserType::XrpcQuery(_) | LexUserType::XrpcProcedure(_)
-            ) {
+            // subscriptions without a message schema have nothing to decode frames into
+            let has_client_method = match def {
+                LexUserType::XrpcQuery(_) | LexUserType::XrpcProcedure(_) => true,
+                LexUserType::XrpcSubscription(subscription) => subscription
+                    .message
+                    .as_ref()
+                    .is_some_and(|message| message.schema.is_some()),
+                _ => false,
+            };
+            if has_client_method {
                 schema_map.insert(schema.id.clone(), def);
                 let mut parts = schema.id.split('.').collect_vec();
                 let mut is_leaf = true;
@@ -161,13 +181,39 @@ pub(crate) fn generate_client(
             }
         }
     }
-    let tokens = client(&tree, &schema_map, namespaces)?;
+    let tokens = client(&tree, &schema_map, namespaces, ctx)?;
     let content = quote! {
         #![doc = r#"Structs for ATP client, implements all HTTP APIs of XRPC."#]
         #tokens
     };
     let path = outdir.join("client.rs");
-    write_to_file(File::create(&path)?, content)?;
+    write_to_file(File::create(&path)?, content, ctx)?;
+    Ok(path)
+}
+
+pub(crate) fn generate_server(
+    outdir: &Path,
+    schemas: &[LexiconDoc],
+    namespaces: &[(String, Option<&str>)],
+    ctx: &Context,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let methods = schemas
+        .iter()
+        .filter_map(|schema| match schema.defs.get("main") {
+            Some(def @ (LexUserType::XrpcQuery(_) | LexUserType::XrpcProcedure(_))) => {
+                Some((schema.id.clone(), def))
+            }
+            _ => None,
+        })
+        .sorted_by(|(a, _), (b, _)| a.cmp(b))
+        .collect_vec();
+    let tokens = server(&methods, namespaces, ctx)?;
+    let content = quote! {
+        #![doc = r#"XRPC server for the queries and procedures, served with axum."#]
+        #tokens
+    };
+    let path = outdir.join("server.rs");
+    write_to_file(File::create(&path)?, content, ctx)?;
     Ok(path)
 }
 
@@ -175,15 +221,23 @@ pub(crate) fn generate_lexicons_mod_or_lib(
     outdir: &Path,
     namespaces: &[(String, Option<&str>)],
     lib: bool,
+    generate_records: bool,
     generate_client: bool,
+    generate_server: bool,
+    ctx: &Context,
 ) -> Result<PathBuf, Box<dyn Error>> {
-    let module = lexicon_module(namespaces, generate_client)?;
+    let module = lexicon_module(
+        namespaces,
+        generate_records,
+        generate_client,
+        generate_server,
+    )?;
     let path = if lib {
         outdir.join("lib.rs")
     } else {
         outdir.join("mod.rs")
     };
-    write_to_file(File::create(&path)?, module)?;
+    write_to_file(File::create(&path)?, module, ctx)?;
 
     Ok(path)
 }
@@ -192,6 +246,7 @@ pub(crate) fn generate_modules(
     outdir: &Path,
     schemas: &[LexiconDoc],
     namespaces: &[(String, Option<&str>)],
+    ctx: &Context,
 ) -> Result<Vec<PathBuf>, Box<dyn Error>> {
     let mut paths = find_dirs(outdir)?;
     paths.reverse();
@@ -258,14 +313,24 @@ pub(crate) fn generate_modules(
             #modules
             #(#collections)*
         };
-        write_to_file(File::create(filepath)?, content)?;
+        write_to_file(File::create(filepath)?, content, ctx)?;
     }
     Ok(files)
 }
 
-fn write_to_file(mut file: impl Write, content: TokenStream) -> Result<(), Box<dyn Error>> {
+fn write_to_file(
+    mut file: impl Write,
+    content: TokenStream,
+    ctx: &Context,
+) -> Result<(), Box<dyn Error>> {
     let parsed = syn::parse_file(&content.to_string())?;
-    writeln!(file, "{HEADER}")?;
-    write!(file, "{}", prettyplease::unparse(&parsed))?;
+    if !ctx.header().is_empty() {
+        writeln!(file, "{}", ctx.header())?;
+    }
+    if ctx.format() {
+        write!(file, "{}", prettyplease::unparse(&parsed))?;
+    } else {
+        writeln!(file, "{content}")?;
+    }
     Ok(())
 }
@@ -1,24 +1,30 @@
 // Forked from atrium-codegen
 // https://github.com/sugyan/atrium/blob/main/lexicon/atrium-codegen/src/lib.rs
 
+mod builder;
+pub mod config;
+mod context;
+mod error;
 mod fs;
 mod generator;
+mod paths;
 mod schema;
 mod token_stream;
+mod validate;
 
-use crate::generator::{
-    generate_client, generate_lexicons_mod_or_lib, generate_modules, generate_records,
-    generate_schemas,
-};
+use crate::config::{Config, ConfigError, Source};
 use atrium_lex::LexiconDoc;
-use atrium_lex::lexicon::LexUserType;
 use serde_json::from_reader;
 use std::error::Error;
 use std::fs::File;
 use std::option::Option;
 use std::path::{Path, PathBuf};
 
-fn canonicalize_and_validate(
+pub use crate::builder::{Generator, GeneratorBuilder};
+pub use crate::error::CodegenError;
+pub use crate::paths::ExternalCrates;
+
+pub(crate) fn canonicalize_and_validate(
     path: impl AsRef<Path>,
     error_msg: &str,
 ) -> Result<PathBuf, Box<dyn Error>> {
@@ -38,15 +44,46 @@ pub fn genapi(
     outdir: impl AsRef<Path>,
     module_name: &Option<String>,
 ) -> Result<Vec<impl AsRef<Path>>, Box<dyn Error>> {
-    let lexdir = canonicalize_and_validate(lexdir, "Lexicon directory does not exist")?;
-    let outdir = canonicalize_and_validate(outdir, "Output directory does not exist")?;
+    genapi_with_externals(lexdir, outdir, module_name, &ExternalCrates::default())
+}
+
+/// Like [`genapi`], resolving refs to the lexicons in `externals` to their crate instead of generating them.
+pub fn genapi_with_externals(
+    lexdir: impl AsRef<Path>,
+    outdir: impl AsRef<Path>,
+    module_name: &Option<String>,
+    externals: &ExternalCrates,
+) -> Result<Vec<impl AsRef<Path>>, Box<dyn Error>> {
+    builder(outdir, module_name, externals)
+        .lexdir(lexdir)
+        .build()
+        .generate()
+}
 
-    let paths = fs::find_schemas(&lexdir)?;
+/// Generates everything described by `config`, which may only have local sources.
+///
+/// `esquema-cli generate config` reads local sources and generates the same way, also fetching
+/// remote sources and the lexicons they reference.
+pub fn genapi_with_config(config: &Config) -> Result<Vec<impl AsRef<Path>>, Box<dyn Error>> {
+    let mut schemas = Vec::new();
+    for source in &config.sources {
+        match source {
+            Source::Local(lexdir) => schemas.extend(read_lexdir(lexdir)?),
+            source => return Err(ConfigError::UnsupportedSource(source.clone()).into()),
+        }
+    }
+    gen_from_lexicon_docs_with_config(schemas, config)
+}
+
+/// Reads every lexicon under `lexdir`, recursively.
+pub fn read_lexdir(lexdir: impl AsRef<Path>) -> Result<Vec<LexiconDoc>, Box<dyn Error>> {
+    let lexdir = canonicalize_and_validate(lexdir, "Lexicon directory does not exist")?;
+    let paths = esquema_validator::find_schemas(&lexdir)?;
     let mut schemas = Vec::with_capacity(paths.len());
     for path in &paths {
         schemas.push(from_reader::<_, LexiconDoc>(File::open(path)?)?);
     }
-    gen_from_lexicon_docs(schemas, outdir, &module_name)
+    Ok(schemas)
 }
 
 pub fn gen_from_lexicon_docs(
@@ -54,61 +91,46 @@ pub fn gen_from_lexicon_docs(
     outdir: impl AsRef<Path>,
     module_name: &Option<String>,
 ) -> Result<Vec<impl AsRef<Path>>, Box<dyn Error>> {
-    let mut outdir = canonicalize_and_validate(outdir, "Output directory does not exist")?;
-    if let Some(module_name) = module_name {
-        outdir.push(module_name);
-    }
-    if !outdir.exists() {
-        return Err(format!("Output directory does not exist: {:?}", outdir).into());
-    }
-    let mut results = Vec::new();
-    //HACK had to change to String instead of &str, but keeping as tuple for now to match atrium-codegen
-    let mut namespaces: Vec<(String, Option<&str>)> = Vec::new();
-
-    let mut client_doc_found = false;
-    //HACK not sure if I need that clone
-    for doc in schemas.clone() {
-        if !client_doc_found {
-            //HACK another clone i feel like I can skip on
-            for def in doc.defs.clone() {
-                if matches!(
-                    def.1,
-                    LexUserType::XrpcQuery(_)
-                        | LexUserType::XrpcProcedure(_)
-                        | LexUserType::XrpcSubscription(_)
-                ) {
-                    client_doc_found = true;
-                }
-            }
-        }
-        results.extend(generate_schemas(&doc.clone(), &outdir)?);
-        //TODO do proper error handling
-        let parts: Vec<&str> = doc.id.split('.').collect();
-        let namespace = format!("{}.{}", parts[0], parts[1]);
-        //TODO prob just move to [String] for name spaces since im hard coding in None
-        if namespaces.iter().any(|x| x.0 == namespace) {
-            continue;
-        }
-        namespaces.push((namespace, None));
-    }
+    gen_from_lexicon_docs_with_externals(schemas, outdir, module_name, &ExternalCrates::default())
+}
 
-    results.push(generate_records(
-        &outdir,
-        &schemas,
-        &namespaces,
-        &module_name,
-    )?);
-    if client_doc_found {
-        results.push(generate_client(&outdir, &schemas, &namespaces)?);
-    }
+/// Like [`gen_from_lexicon_docs`], resolving refs to the lexicons in `externals` to their crate instead of generating them.
+pub fn gen_from_lexicon_docs_with_externals(
+    schemas: Vec<LexiconDoc>,
+    outdir: impl AsRef<Path>,
+    module_name: &Option<String>,
+    externals: &ExternalCrates,
+) -> Result<Vec<impl AsRef<Path>>, Box<dyn Error>> {
+    builder(outdir, module_name, externals)
+        .lexicons(schemas)
+        .build()
+        .generate()
+}
 
-    results.push(generate_lexicons_mod_or_lib(
-        &outdir,
-        &namespaces,
-        module_name.is_none(),
-        client_doc_found,
-    )?);
-    results.extend(generate_modules(&outdir, &schemas, &namespaces)?);
+/// Like [`gen_from_lexicon_docs`], with the output, external crates, derives, features and server from `config`.
+///
+/// The sources of `config` are ignored, `schemas` are expected to be read from them already.
+pub fn gen_from_lexicon_docs_with_config(
+    schemas: Vec<LexiconDoc>,
+    config: &Config,
+) -> Result<Vec<impl AsRef<Path>>, Box<dyn Error>> {
+    Generator::builder()
+        .config(config)
+        .lexicons(schemas)
+        .build()
+        .generate()
+}
 
-    Ok(results)
+fn builder(
+    outdir: impl AsRef<Path>,
+    module_name: &Option<String>,
+    externals: &ExternalCrates,
+) -> GeneratorBuilder {
+    let builder = Generator::builder()
+        .outdir(outdir)
+        .externals(externals.clone());
+    match module_name {
+        Some(module_name) => builder.module(module_name),
+        None => builder,
+    }
 }
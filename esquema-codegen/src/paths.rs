@@ -0,0 +1,80 @@
+use heck::ToPascalCase;
+use itertools::Itertools;
+
+/// NSID prefixes whose types come from an existing crate instead of being generated.
+///
+/// The default maps the Bluesky namespaces to `atrium_api`, so a ref to
+/// `com.atproto.repo.strongRef` becomes `atrium_api::com::atproto::repo::strong_ref::Main`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalCrates {
+    mappings: Vec<(String, String)>,
+}
+
+impl Default for ExternalCrates {
+    fn default() -> Self {
+        Self::empty()
+            .with("com.atproto", "atrium_api::com::atproto")
+            .with("app.bsky", "atrium_api::app::bsky")
+            .with("chat.bsky", "atrium_api::chat::bsky")
+            .with("tools.ozone", "atrium_api::tools::ozone")
+    }
+}
+
+impl ExternalCrates {
+    /// No mappings, every referenced lexicon is generated locally.
+    pub fn empty() -> Self {
+        Self {
+            mappings: Vec::new(),
+        }
+    }
+
+    /// Maps `prefix` (e.g. `com.atproto`) to the Rust path of its module (e.g. `atrium_api::com::atproto`),
+    /// replacing any existing mapping for the same prefix.
+    pub fn insert(&mut self, prefix: impl Into<String>, path: impl Into<String>) {
+        let prefix = prefix.into();
+        let path = path.into();
+        match self.mappings.iter_mut().find(|(p, _)| *p == prefix) {
+            Some(mapping) => mapping.1 = path,
+            None => self.mappings.push((prefix, path)),
+        }
+    }
+
+    pub fn with(mut self, prefix: impl Into<String>, path: impl Into<String>) -> Self {
+        self.insert(prefix, path);
+        self
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.mappings
+            .iter()
+            .map(|(prefix, path)| (prefix.as_str(), path.as_str()))
+    }
+
+    /// Whether the lexicon `nsid` is provided by an external crate.
+    pub fn contains(&self, nsid: &str) -> bool {
+        self.find(nsid).is_some()
+    }
+
+    /// The longest mapped prefix of `nsid`, matching whole segments only.
+    pub(crate) fn find(&self, nsid: &str) -> Option<(&str, &str)> {
+        self.iter()
+            .filter(|(prefix, _)| {
+                nsid.strip_prefix(prefix)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+    }
+}
+
+/// A Rust identifier for `ref` that is unique within the generated crate, e.g. `ComExampleFooBar`.
+///
+/// An empty `default` leaves the def name out, as record variants are named after their NSID alone.
+pub(crate) fn variant_name(r#ref: &str, default: &str) -> String {
+    let (namespace, def) = r#ref.split_once('#').unwrap_or((r#ref, default));
+    namespace
+        .split('.')
+        .chain([def])
+        .filter(|segment| !segment.is_empty())
+        .map(str::to_pascal_case)
+        .join("")
+}
@@ -37,7 +37,7 @@ pub(crate) fn find_ref_unions(defs: &HashMap<String, LexUserType>) -> Vec<(Strin
                     if let Some(schema) = &message.schema {
                         find_ref_unions_in_subscription_message_schema(
                             schema,
-                            "Message",
+                            "MessageObject",
                             &mut unions,
                         );
                     }
@@ -62,7 +62,7 @@ fn find_ref_unions_in_body_schema(
     unions: &mut Vec<(String, LexRefUnion)>,
 ) {
     match schema {
-        LexXrpcBodySchema::Union(_) => unimplemented!(),
+        LexXrpcBodySchema::Union(union) => unions.push((format!("{name}Refs"), union.clone())),
         LexXrpcBodySchema::Object(object) => find_ref_unions_in_object(object, name, unions),
         _ => {}
     }
@@ -74,9 +74,8 @@ fn find_ref_unions_in_subscription_message_schema(
     unions: &mut Vec<(String, LexRefUnion)>,
 ) {
     match schema {
-        LexXrpcSubscriptionMessageSchema::Union(union) => {
-            unions.push((name.into(), union.clone()));
-        }
+        // a union message is generated as a `Message` enum decoded from event stream frames, an
+        // object one as `MessageObject`, wrapped by a `Message` newtype
         LexXrpcSubscriptionMessageSchema::Object(object) => {
             find_ref_unions_in_object(object, name, unions)
         }
@@ -1,13 +1,20 @@
 // Forked from atrium-codegen
 // https://github.com/sugyan/atrium/blob/main/lexicon/atrium-codegen/src/token_stream.rs
 
+use crate::context::Context;
+use crate::error::CodegenError;
+use crate::validate::{impl_validate_enum, impl_validate_noop, impl_validate_object};
 use atrium_lex::lexicon::*;
+use esquema_validator::{Validator, parameters_object};
 use heck::{ToPascalCase, ToShoutySnakeCase, ToSnakeCase};
 use itertools::Itertools;
-use proc_macro2::TokenStream;
+use proc_macro2::{Ident, TokenStream};
 use quote::{format_ident, quote};
+use serde_json::Value;
 use std::collections::{HashMap, HashSet};
-use syn::{Path, Result};
+use syn::Path;
+
+type Result<T> = std::result::Result<T, CodegenError>;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum OutputType {
@@ -21,28 +28,49 @@ pub fn user_type(
     schema_id: &str,
     name: &str,
     is_main: bool,
+    ctx: &Context,
 ) -> Result<TokenStream> {
     let user_type = match def {
-        LexUserType::Record(record) => lex_record(record)?,
-        LexUserType::XrpcQuery(query) => lex_query(query)?,
-        LexUserType::XrpcProcedure(procedure) => lex_procedure(procedure)?,
-        LexUserType::XrpcSubscription(subscription) => lex_subscription(subscription)?,
-        LexUserType::Array(array) => lex_array(array, name)?,
-        LexUserType::Token(token) => lex_token(token, name, schema_id)?,
-        LexUserType::Object(object) => lex_object(object, if is_main { "Main" } else { name })?,
-        LexUserType::String(string) => lex_string(string, name)?,
-        _ => unimplemented!("{def:?}"),
-    };
+        LexUserType::Record(record) => lex_record(record, schema_id, ctx),
+        LexUserType::XrpcQuery(query) => lex_query(query, ctx),
+        LexUserType::XrpcProcedure(procedure) => lex_procedure(procedure, ctx),
+        LexUserType::XrpcSubscription(subscription) => {
+            lex_subscription(subscription, schema_id, ctx)
+        }
+        LexUserType::Array(array) => lex_array(array, name, ctx),
+        LexUserType::Token(token) => lex_token(token, name, schema_id),
+        LexUserType::Object(object) => lex_object(object, if is_main { "Main" } else { name }, ctx),
+        LexUserType::String(string) => lex_string(string, name, ctx),
+        LexUserType::Blob(blob) => blob_type(blob).map(|typ| lex_alias(typ, name, is_main)),
+        LexUserType::Bytes(bytes) => bytes_type(bytes).map(|typ| lex_alias(typ, name, is_main)),
+        LexUserType::Integer(integer) => {
+            integer_type(integer).map(|typ| lex_alias(typ, name, is_main))
+        }
+        LexUserType::Boolean(boolean) => {
+            boolean_type(boolean).map(|typ| lex_alias(typ, name, is_main))
+        }
+        LexUserType::Unknown(unknown) => {
+            unknown_type(unknown).map(|typ| lex_alias(typ, name, is_main))
+        }
+        LexUserType::CidLink(cid_link) => {
+            cid_link_type(cid_link).map(|typ| lex_alias(typ, name, is_main))
+        }
+    }
+    .map_err(|err| err.in_def(schema_id, name))?;
     Ok(quote! {
         // #[doc = #description]
         #user_type
     })
 }
 
-pub fn ref_unions(schema_id: &str, ref_unions: &[(String, LexRefUnion)]) -> Result<TokenStream> {
+pub fn ref_unions(
+    schema_id: &str,
+    ref_unions: &[(String, LexRefUnion)],
+    ctx: &Context,
+) -> Result<TokenStream> {
     let mut enums = Vec::new();
     for (name, ref_union) in ref_unions {
-        enums.push(refs_enum(&ref_union.refs, name, Some(schema_id))?);
+        enums.push(refs_enum(&ref_union.refs, name, Some(schema_id), ctx)?);
     }
     Ok(quote!(#(#enums)*))
 }
@@ -60,80 +88,58 @@ pub fn collection(name: &str, nsid: &str) -> TokenStream {
     }
 }
 
-fn lex_record(record: &LexRecord) -> Result<TokenStream> {
+fn lex_record(record: &LexRecord, schema_id: &str, ctx: &Context) -> Result<TokenStream> {
     let LexRecordRecord::Object(object) = &record.record;
-    let result = lex_object(object, "Record")?;
-    Ok(quote! {
-        #result
+    let result = lex_object(object, "Record", ctx).map_err(|err| err.at("record"))?;
+    let from_unknown = if ctx.infallible_record_conversions() {
+        quote! {
+            impl From<atrium_api::types::Unknown> for RecordData {
+                fn from(value: atrium_api::types::Unknown) -> Self {
+                    esquema_validator::record::from_unknown(Some(#schema_id), &value)
+                        .unwrap_or_else(|err| panic!("{err}"))
+                }
+            }
+        }
+    } else {
+        quote! {
+            impl TryFrom<atrium_api::types::Unknown> for RecordData {
+                type Error = esquema_validator::RecordError;
 
-        impl From<atrium_api::types::Unknown> for RecordData {
-            fn from(value: atrium_api::types::Unknown) -> Self {
-                Self::try_from_unknown(value).unwrap()
+                fn try_from(value: atrium_api::types::Unknown) -> core::result::Result<Self, Self::Error> {
+                    esquema_validator::record::from_unknown(Some(#schema_id), &value)
+                }
             }
         }
+    };
+    Ok(quote! {
+        #result
+        #from_unknown
     })
 }
 
-fn xrpc_parameters(parameters: &LexXrpcParameters) -> Result<TokenStream> {
-    let properties = parameters
-        .properties
-        .iter()
-        .map(|(k, v)| {
-            let value = match v {
-                LexXrpcParametersProperty::Boolean(boolean) => {
-                    LexObjectProperty::Boolean(boolean.clone())
-                }
-                LexXrpcParametersProperty::Integer(integer) => {
-                    LexObjectProperty::Integer(integer.clone())
-                }
-                LexXrpcParametersProperty::String(string) => {
-                    LexObjectProperty::String(string.clone())
-                }
-                LexXrpcParametersProperty::Unknown(unknown) => {
-                    LexObjectProperty::Unknown(unknown.clone())
-                }
-                LexXrpcParametersProperty::Array(primitive_array) => {
-                    LexObjectProperty::Array(LexArray {
-                        description: primitive_array.description.clone(),
-                        items: match &primitive_array.items {
-                            LexPrimitiveArrayItem::Boolean(b) => LexArrayItem::Boolean(b.clone()),
-                            LexPrimitiveArrayItem::Integer(i) => LexArrayItem::Integer(i.clone()),
-                            LexPrimitiveArrayItem::String(s) => LexArrayItem::String(s.clone()),
-                            LexPrimitiveArrayItem::Unknown(u) => LexArrayItem::Unknown(u.clone()),
-                        },
-                        min_length: primitive_array.min_length,
-                        max_length: primitive_array.max_length,
-                    })
-                }
-            };
-            (k.clone(), value)
-        })
-        .collect();
-    lex_object(
-        &LexObject {
-            description: parameters.description.clone(),
-            required: parameters.required.clone(),
-            nullable: None,
-            properties,
-        },
-        "Parameters",
-    )
+fn xrpc_parameters(parameters: &LexXrpcParameters, ctx: &Context) -> Result<TokenStream> {
+    lex_object(&parameters_object(parameters), "Parameters", ctx)
+        .map_err(|err| err.at("parameters"))
 }
 
-fn xrpc_body(body: &LexXrpcBody, name: &str) -> Result<TokenStream> {
+fn xrpc_body(body: &LexXrpcBody, name: &str, ctx: &Context) -> Result<TokenStream> {
     let description = description(&body.description);
     let schema = if let Some(schema) = &body.schema {
         match schema {
             LexXrpcBodySchema::Ref(r#ref) => {
                 let type_name = format_ident!("{}", name.to_pascal_case());
-                let (description, ref_type) = ref_type(r#ref)?;
+                let (description, ref_type) =
+                    ref_type(r#ref, ctx).map_err(|err| err.at("schema"))?;
                 quote! {
                     #description
                     pub type #type_name = #ref_type;
                 }
             }
-            LexXrpcBodySchema::Object(object) => lex_object(object, name)?,
-            _ => unimplemented!("{schema:?}"),
+            LexXrpcBodySchema::Object(object) => {
+                lex_object(object, name, ctx).map_err(|err| err.at("schema"))?
+            }
+            // the `InputRefs`/`OutputRefs` enum is generated with the other ref unions
+            LexXrpcBodySchema::Union(_) => return Ok(quote!()),
         }
     } else {
         return Ok(quote!());
@@ -144,8 +150,8 @@ fn xrpc_body(body: &LexXrpcBody, name: &str) -> Result<TokenStream> {
     })
 }
 
-fn xrpc_errors(errors: &Option<Vec<LexXrpcError>>) -> Result<TokenStream> {
-    let derives = derives()?;
+fn xrpc_errors(errors: &Option<Vec<LexXrpcError>>, ctx: &Context) -> Result<TokenStream> {
+    let derives = ctx.derives_without_default()?;
     let errors = errors.as_ref().map_or(Vec::new(), |e| {
         e.iter()
             .map(|error| (error.name.clone(), error.description.clone()))
@@ -201,18 +207,18 @@ fn xrpc_errors(errors: &Option<Vec<LexXrpcError>>) -> Result<TokenStream> {
     })
 }
 
-fn lex_query(query: &LexXrpcQuery) -> Result<TokenStream> {
+fn lex_query(query: &LexXrpcQuery, ctx: &Context) -> Result<TokenStream> {
     let params = if let Some(LexXrpcQueryParameter::Params(parameters)) = &query.parameters {
-        xrpc_parameters(parameters)?
+        xrpc_parameters(parameters, ctx)?
     } else {
         quote!()
     };
     let outputs = if let Some(body) = &query.output {
-        xrpc_body(body, "Output")?
+        xrpc_body(body, "Output", ctx).map_err(|err| err.at("output"))?
     } else {
         quote!()
     };
-    let errors = xrpc_errors(&query.errors)?;
+    let errors = xrpc_errors(&query.errors, ctx)?;
     Ok(quote! {
         #params
         #outputs
@@ -220,18 +226,18 @@ fn lex_query(query: &LexXrpcQuery) -> Result<TokenStream> {
     })
 }
 
-fn lex_procedure(procedure: &LexXrpcProcedure) -> Result<TokenStream> {
+fn lex_procedure(procedure: &LexXrpcProcedure, ctx: &Context) -> Result<TokenStream> {
     let inputs = if let Some(body) = &procedure.input {
-        xrpc_body(body, "Input")?
+        xrpc_body(body, "Input", ctx).map_err(|err| err.at("input"))?
     } else {
         quote!()
     };
     let outputs = if let Some(body) = &procedure.output {
-        xrpc_body(body, "Output")?
+        xrpc_body(body, "Output", ctx).map_err(|err| err.at("output"))?
     } else {
         quote!()
     };
-    let errors = xrpc_errors(&procedure.errors)?;
+    let errors = xrpc_errors(&procedure.errors, ctx)?;
     Ok(quote! {
         #inputs
         #outputs
@@ -239,26 +245,166 @@ fn lex_procedure(procedure: &LexXrpcProcedure) -> Result<TokenStream> {
     })
 }
 
-fn lex_subscription(subscription: &LexXrpcSubscription) -> Result<TokenStream> {
+fn lex_subscription(
+    subscription: &LexXrpcSubscription,
+    schema_id: &str,
+    ctx: &Context,
+) -> Result<TokenStream> {
     let params =
         if let Some(LexXrpcSubscriptionParameter::Params(parameters)) = &subscription.parameters {
-            xrpc_parameters(parameters)?
+            xrpc_parameters(parameters, ctx)?
         } else {
             quote!()
         };
-    let errors = xrpc_errors(&subscription.errors)?;
+    let message = subscription_message(subscription, schema_id, ctx)?;
+    let errors = xrpc_errors(&subscription.errors, ctx)?;
     Ok(quote! {
         #params
+        #message
         #errors
     })
 }
 
-fn lex_array(array: &LexArray, name: &str) -> Result<TokenStream> {
-    let (description, array_type) = array_type(array, name, None)?;
+/// The `Message` type of a subscription, decoded from event stream frames by the generated client.
+fn subscription_message(
+    subscription: &LexXrpcSubscription,
+    schema_id: &str,
+    ctx: &Context,
+) -> Result<TokenStream> {
+    let Some(schema) = subscription
+        .message
+        .as_ref()
+        .and_then(|message| message.schema.as_ref())
+    else {
+        return Ok(quote!());
+    };
+    let (definition, typ) = match schema {
+        LexXrpcSubscriptionMessageSchema::Ref(r#ref) => {
+            ref_type(r#ref, ctx).map_err(|err| err.at("message.schema"))?
+        }
+        LexXrpcSubscriptionMessageSchema::Union(union) => {
+            return message_enum(union, schema_id, ctx).map_err(|err| err.at("message.schema"));
+        }
+        LexXrpcSubscriptionMessageSchema::Object(object) => (
+            lex_object(object, "MessageObject", ctx).map_err(|err| err.at("message.schema"))?,
+            quote!(MessageObject),
+        ),
+    };
+    // without `Default`, which the wrapped `Object` or ref type only has when its data does
+    let derives = ctx.derives_without_default()?;
+    // a newtype, as the trait can't be implemented for the `Object` of an object message or a
+    // ref's type from another crate
+    Ok(quote! {
+        #definition
+        #derives
+        #[serde(transparent)]
+        pub struct Message(pub #typ);
+
+        impl esquema_client::subscription::Message for Message {
+            #[allow(unused_variables)]
+            fn decode(
+                t: Option<&str>,
+                body: &[u8],
+            ) -> core::result::Result<Self, esquema_client::subscription::DecodeError> {
+                esquema_client::subscription::decode_body(body).map(Self)
+            }
+        }
+    })
+}
+
+/// The enum of a subscription's union message, decoded by the `t` of the frame header.
+///
+/// Frames name the type of local defs in the short `#name` form, so this isn't the `$type`
+/// tagged enum of other unions. Open unions keep messages of types added later as `Unknown`. It
+/// still gets the derives of other enums, serde's included.
+fn message_enum(union: &LexRefUnion, schema_id: &str, ctx: &Context) -> Result<TokenStream> {
+    let description = description(&union.description);
+    let mut variants = Vec::new();
+    let mut arms = Vec::new();
+    for r#ref in &union.refs {
+        let path = ctx.resolve(r#ref, "main")?;
+        let name = format_ident!("{}", ctx.variant_name(r#ref, "main"));
+        let full = if r#ref.starts_with('#') {
+            format!("{schema_id}{}", r#ref)
+        } else {
+            r#ref.clone()
+        };
+        let mut types = vec![full.clone()];
+        if let Some(def) = full.strip_prefix(&format!("{schema_id}#")) {
+            types.push(format!("#{def}"));
+        }
+        if !full.contains('#') {
+            types.push(format!("{full}#main"));
+        }
+        variants.push(quote!(#name(Box<#path>)));
+        arms.push(quote! {
+            #(#types)|* => esquema_client::subscription::decode_body(body)
+                .map(|message| Message::#name(Box::new(message))),
+        });
+    }
+    let (unknown, fallback) = if union.closed.unwrap_or_default() {
+        (
+            quote!(),
+            quote! {
+                _ => Err(esquema_client::subscription::DecodeError::new(
+                    format!("unknown message type `{t}`"),
+                )),
+            },
+        )
+    } else {
+        (
+            quote! {
+                /// A message of a type the lexicon didn't have when this was generated
+                Unknown { t: String, body: Vec<u8> },
+            },
+            quote! {
+                _ => Ok(Message::Unknown {
+                    t: t.to_string(),
+                    body: body.to_vec(),
+                }),
+            },
+        )
+    };
+    let derives = ctx.derives_without_default()?;
+    Ok(quote! {
+        #description
+        #derives
+        pub enum Message {
+            #(#variants,)*
+            #unknown
+        }
+
+        impl esquema_client::subscription::Message for Message {
+            fn decode(
+                t: Option<&str>,
+                body: &[u8],
+            ) -> core::result::Result<Self, esquema_client::subscription::DecodeError> {
+                let t = t.ok_or_else(|| {
+                    esquema_client::subscription::DecodeError::new("message without a `t`")
+                })?;
+                match t {
+                    #(#arms)*
+                    #fallback
+                }
+            }
+        }
+    })
+}
+
+fn lex_array(array: &LexArray, name: &str, ctx: &Context) -> Result<TokenStream> {
+    let (description, array_type) = array_type(array, name, None, ctx)?;
     let type_name = format_ident!("{}", name.to_pascal_case());
+    let item_enum = match &array.items {
+        LexArrayItem::String(string) if is_string_enum(string) => {
+            string_enum(string, &array_item_name(None, name), ctx)?
+        }
+        _ => quote!(),
+    };
     Ok(quote! {
         #description
         pub type #type_name = #array_type;
+
+        #item_enum
     })
 }
 
@@ -272,9 +418,8 @@ fn lex_token(token: &LexToken, name: &str, schema_id: &str) -> Result<TokenStrea
     })
 }
 
-fn lex_object(object: &LexObject, name: &str) -> Result<TokenStream> {
+fn lex_object(object: &LexObject, name: &str, ctx: &Context) -> Result<TokenStream> {
     let description = description(&object.description);
-    let derives = derives()?;
     let struct_name = format_ident!("{}Data", name.to_pascal_case());
     let object_name = format_ident!("{}", name.to_pascal_case());
     let mut required = if let Some(required) = &object.required {
@@ -289,13 +434,53 @@ fn lex_object(object: &LexObject, name: &str) -> Result<TokenStream> {
     }
     let mut fields = Vec::new();
     for key in object.properties.keys().sorted() {
-        fields.push(lex_object_property(
-            &object.properties[key],
-            key,
-            required.contains(key),
-            name,
-        )?);
+        fields.push(
+            lex_object_property(
+                &object.properties[key],
+                key,
+                required.contains(key),
+                name,
+                ctx,
+            )
+            .map_err(|err| err.at(format!("properties.{key}")))?,
+        );
     }
+    // fields with a `default` or `const` are always present
+    let present = object
+        .properties
+        .keys()
+        .sorted()
+        .zip(&fields)
+        .filter(|(_, field)| !matches!(field.value, FieldValue::Optional))
+        .map(|(key, _)| key)
+        .collect();
+    let mut string_enums = Vec::new();
+    for key in object.properties.keys().sorted() {
+        match &object.properties[key] {
+            LexObjectProperty::String(string) if is_string_enum(string) => {
+                string_enums.push(string_enum(string, &property_name(name, key), ctx)?);
+            }
+            LexObjectProperty::Array(LexArray {
+                items: LexArrayItem::String(string),
+                ..
+            }) if is_string_enum(string) => {
+                string_enums.push(string_enum(string, &array_item_name(Some(name), key), ctx)?);
+            }
+            _ => {}
+        }
+    }
+    // a derived `Default` would leave out the values `object_constructor` fills in
+    let derives = if fields
+        .iter()
+        .all(|field| matches!(field.value, FieldValue::Required | FieldValue::Optional))
+    {
+        ctx.derives()?
+    } else {
+        ctx.derives_without_default()?
+    };
+    let constructor = object_constructor(&struct_name, &fields);
+    let validate = impl_validate_object(object, &struct_name, &present, ctx);
+    let fields = fields.iter().map(|field| &field.tokens);
     Ok(quote! {
 
         #description
@@ -307,17 +492,40 @@ fn lex_object(object: &LexObject, name: &str) -> Result<TokenStream> {
 
         pub type #object_name = atrium_api::types::Object<#struct_name>;
 
+        #constructor
+
+        #validate
+
+        #(#string_enums)*
     })
 }
 
+/// A field of a generated object struct.
+struct Field {
+    tokens: TokenStream,
+    name: Ident,
+    typ: TokenStream,
+    value: FieldValue,
+}
+
+/// What a field holds when it is left out, the `default` and `const` values are
+/// expressions of the field's type.
+enum FieldValue {
+    Required,
+    Optional,
+    Default(TokenStream),
+    Const { value: TokenStream, message: String },
+}
+
 fn lex_object_property(
     property: &LexObjectProperty,
     name: &str,
     is_required: bool,
     object_name: &str,
-) -> Result<TokenStream> {
+    ctx: &Context,
+) -> Result<Field> {
     let (description, mut field_type) = match property {
-        LexObjectProperty::Ref(r#ref) => ref_type(r#ref)?,
+        LexObjectProperty::Ref(r#ref) => ref_type(r#ref, ctx)?,
         LexObjectProperty::Union(union) => union_type(
             union,
             format!(
@@ -329,21 +537,27 @@ fn lex_object_property(
         )?,
         LexObjectProperty::Bytes(bytes) => bytes_type(bytes)?,
         LexObjectProperty::CidLink(cid_link) => cid_link_type(cid_link)?,
-        LexObjectProperty::Array(array) => array_type(array, name, Some(object_name))?,
+        LexObjectProperty::Array(array) => array_type(array, name, Some(object_name), ctx)?,
         LexObjectProperty::Blob(blob) => blob_type(blob)?,
         LexObjectProperty::Boolean(boolean) => boolean_type(boolean)?,
         LexObjectProperty::Integer(integer) => integer_type(integer)?,
-        LexObjectProperty::String(string) => string_type(string)?,
+        LexObjectProperty::String(string) => {
+            string_type(string, &property_name(object_name, name))?
+        }
         LexObjectProperty::Unknown(unknown) => unknown_type(unknown)?,
     };
-    let field_name = format_ident!(
-        "{}",
-        if name == "ref" || name == "type" {
-            format!("r#{name}")
-        } else {
-            name.to_snake_case()
-        }
-    );
+    let field_name = field_name(name);
+    let struct_name = format!("{}Data", object_name.to_pascal_case());
+    let snake_name = name.to_snake_case();
+    let value = match fixed_values(property)? {
+        (Some((value, expected)), _) => FieldValue::Const {
+            value,
+            message: format!("`{name}` must be {expected}"),
+        },
+        (None, Some(value)) if !is_required => FieldValue::Default(value),
+        _ if is_required => FieldValue::Required,
+        _ => FieldValue::Optional,
+    };
     let mut attributes = match property {
         LexObjectProperty::Bytes(_) => {
             let default = if is_required {
@@ -358,21 +572,227 @@ fn lex_object_property(
         }
         _ => quote!(),
     };
-    if !is_required {
-        field_type = quote!(core::option::Option<#field_type>);
-        attributes = quote! {
+    match &value {
+        FieldValue::Required => {}
+        FieldValue::Optional => {
+            field_type = quote!(core::option::Option<#field_type>);
+            attributes = quote! {
+                #attributes
+                #[serde(skip_serializing_if = "core::option::Option::is_none")]
+            };
+        }
+        FieldValue::Default(_) => {
+            let default = format!("{struct_name}::default_{snake_name}");
+            attributes = quote! {
+                #attributes
+                #[serde(default = #default)]
+            };
+        }
+        FieldValue::Const { .. } => {
+            let deserialize_with = format!("{struct_name}::deserialize_{snake_name}");
+            attributes = if is_required {
+                quote! {
+                    #attributes
+                    #[serde(deserialize_with = #deserialize_with)]
+                }
+            } else {
+                let default = format!("{struct_name}::const_{snake_name}");
+                quote! {
+                    #attributes
+                    #[serde(default = #default, deserialize_with = #deserialize_with)]
+                }
+            };
+        }
+    }
+    Ok(Field {
+        tokens: quote! {
+            #description
             #attributes
-            #[serde(skip_serializing_if = "core::option::Option::is_none")]
-        };
+            pub #field_name: #field_type,
+        },
+        name: field_name,
+        typ: field_type,
+        value,
+    })
+}
+
+/// The `const` and `default` values of a property, as expressions of its type.
+///
+/// The values are deserialized from the lexicon's literal, so bounded integers, string enums and
+/// formatted strings get them as they would from a record. The literals are checked against the
+/// property's constraints first, so that can't fail. The `const` comes with how it reads in error
+/// messages.
+fn fixed_values(
+    property: &LexObjectProperty,
+) -> Result<(Option<(TokenStream, String)>, Option<TokenStream>)> {
+    fn deserialize(deserializer: TokenStream) -> TokenStream {
+        quote! {
+            serde::Deserialize::deserialize(#deserializer)
+                .expect("the lexicon's value is valid for its own type")
+        }
     }
-    Ok(quote! {
-        #description
-        #attributes
-        pub #field_name: #field_type,
+    let literals = match property {
+        LexObjectProperty::Boolean(boolean) => [
+            boolean.r#const.map(Value::from),
+            boolean.default.map(Value::from),
+        ],
+        LexObjectProperty::Integer(integer) => [
+            integer.r#const.map(Value::from),
+            integer.default.map(Value::from),
+        ],
+        LexObjectProperty::String(string) => [
+            string.r#const.as_deref().map(Value::from),
+            string.default.as_deref().map(Value::from),
+        ],
+        _ => [None, None],
+    };
+    let validator = Validator::new(Vec::new());
+    for (key, literal) in ["const", "default"].into_iter().zip(literals) {
+        if let Some(literal) = literal {
+            validator
+                .validate_property("", property, &literal)
+                .map_err(|err| CodegenError::invalid_value(&err.violations).at(key))?;
+        }
+    }
+    let value_error = quote!(serde::de::value::Error);
+    Ok(match property {
+        LexObjectProperty::Boolean(boolean) => {
+            let value = |value: bool| {
+                deserialize(quote!(serde::de::value::BoolDeserializer::<#value_error>::new(#value)))
+            };
+            (
+                boolean.r#const.map(|c| (value(c), c.to_string())),
+                boolean.default.map(value),
+            )
+        }
+        LexObjectProperty::Integer(integer) => {
+            let value = |value: i64| {
+                deserialize(quote!(serde::de::value::I64Deserializer::<#value_error>::new(#value)))
+            };
+            (
+                integer.r#const.map(|c| (value(c), c.to_string())),
+                integer.default.map(value),
+            )
+        }
+        LexObjectProperty::String(string) => {
+            let value = |value: &String| {
+                deserialize(quote!(serde::de::value::StrDeserializer::<#value_error>::new(#value)))
+            };
+            (
+                string
+                    .r#const
+                    .as_ref()
+                    .map(|c| (value(c), format!("{c:?}"))),
+                string.default.as_ref().map(value),
+            )
+        }
+        _ => (None, None),
     })
 }
 
-fn lex_string(string: &LexString, name: &str) -> Result<TokenStream> {
+/// `new` for an object with `default` or `const` fields, taking its required fields and filling in
+/// the rest, with the functions serde uses for those fields.
+///
+/// Without required fields it is `Default` instead. `Default` is never derived for these objects,
+/// as that would leave the lexicon's values out.
+fn object_constructor(struct_name: &Ident, fields: &[Field]) -> TokenStream {
+    if fields
+        .iter()
+        .all(|field| matches!(field.value, FieldValue::Required | FieldValue::Optional))
+    {
+        return quote!();
+    }
+    let mut methods = Vec::new();
+    let mut parameters = Vec::new();
+    let mut initializers = Vec::new();
+    for Field {
+        name, typ, value, ..
+    } in fields
+    {
+        let snake_name = name.to_string().trim_start_matches("r#").to_string();
+        match value {
+            FieldValue::Required => {
+                parameters.push(quote!(#name: #typ));
+                initializers.push(quote!(#name));
+            }
+            FieldValue::Optional => initializers.push(quote!(#name: None)),
+            FieldValue::Default(value) => {
+                let default = format_ident!("default_{snake_name}");
+                methods.push(quote! {
+                    pub fn #default() -> #typ {
+                        #value
+                    }
+                });
+                initializers.push(quote!(#name: Self::#default()));
+            }
+            FieldValue::Const { value, message } => {
+                let const_fn = format_ident!("const_{snake_name}");
+                let deserialize = format_ident!("deserialize_{snake_name}");
+                methods.push(quote! {
+                    pub fn #const_fn() -> #typ {
+                        #value
+                    }
+                    fn #deserialize<'de, D: serde::Deserializer<'de>>(
+                        deserializer: D,
+                    ) -> core::result::Result<#typ, D::Error> {
+                        let value = <#typ as serde::Deserialize>::deserialize(deserializer)?;
+                        if value != Self::#const_fn() {
+                            return Err(serde::de::Error::custom(#message));
+                        }
+                        Ok(value)
+                    }
+                });
+                initializers.push(quote!(#name: Self::#const_fn()));
+            }
+        }
+    }
+    let constructor = if parameters.is_empty() {
+        quote! {
+            impl Default for #struct_name {
+                fn default() -> Self {
+                    Self {
+                        #(#initializers),*
+                    }
+                }
+            }
+        }
+    } else {
+        quote! {
+            impl #struct_name {
+                /// Creates the data from its required fields, with `default` and `const` values filled in.
+                #[allow(clippy::too_many_arguments)]
+                pub fn new(#(#parameters),*) -> Self {
+                    Self {
+                        #(#initializers),*
+                    }
+                }
+            }
+        }
+    };
+    quote! {
+        impl #struct_name {
+            #(#methods)*
+        }
+
+        #constructor
+    }
+}
+
+pub(crate) fn field_name(name: &str) -> Ident {
+    format_ident!(
+        "{}",
+        if name == "ref" || name == "type" {
+            format!("r#{name}")
+        } else {
+            name.to_snake_case()
+        }
+    )
+}
+
+fn lex_string(string: &LexString, name: &str, ctx: &Context) -> Result<TokenStream> {
+    if is_string_enum(string) {
+        return string_enum(string, &name.to_pascal_case(), ctx);
+    }
     let description = description(&string.description);
     let string_name = format_ident!("{}", name.to_pascal_case());
     Ok(quote! {
@@ -381,9 +801,29 @@ fn lex_string(string: &LexString, name: &str) -> Result<TokenStream> {
     })
 }
 
-fn ref_type(r#ref: &LexRef) -> Result<(TokenStream, TokenStream)> {
+/// A type alias for a primitive def, named `Main` for the main def so refs to the bare NSID find it.
+fn lex_alias(
+    (description, typ): (TokenStream, TokenStream),
+    name: &str,
+    is_main: bool,
+) -> TokenStream {
+    let type_name = format_ident!(
+        "{}",
+        if is_main {
+            "Main".into()
+        } else {
+            name.to_pascal_case()
+        }
+    );
+    quote! {
+        #description
+        pub type #type_name = #typ;
+    }
+}
+
+fn ref_type(r#ref: &LexRef, ctx: &Context) -> Result<(TokenStream, TokenStream)> {
     let description = description(&r#ref.description);
-    Ok((description, resolve_path(&r#ref.r#ref, "main", &None)?))
+    Ok((description, ctx.resolve(&r#ref.r#ref, "main")?))
 }
 
 fn union_type(union: &LexRefUnion, enum_name: &str) -> Result<(TokenStream, TokenStream)> {
@@ -413,28 +853,43 @@ fn array_type(
     array: &LexArray,
     name: &str,
     object_name: Option<&str>,
+    ctx: &Context,
 ) -> Result<(TokenStream, TokenStream)> {
     let description = description(&array.description);
     let (_, item_type) = match &array.items {
         LexArrayItem::Integer(integer) => integer_type(integer)?,
-        LexArrayItem::String(string) => string_type(string)?,
+        LexArrayItem::String(string) => string_type(string, &array_item_name(object_name, name))?,
         LexArrayItem::Unknown(unknown) => unknown_type(unknown)?,
         LexArrayItem::CidLink(cid_link) => cid_link_type(cid_link)?,
-        LexArrayItem::Ref(r#ref) => ref_type(r#ref)?,
-        LexArrayItem::Union(union) => union_type(
-            union,
-            format!(
-                "{}{}Item",
-                object_name.map_or(String::new(), str::to_pascal_case),
-                name.to_pascal_case()
-            )
-            .as_str(),
-        )?,
-        _ => unimplemented!("{:?}", array.items),
+        LexArrayItem::Ref(r#ref) => ref_type(r#ref, ctx)?,
+        LexArrayItem::Union(union) => union_type(union, &array_item_name(object_name, name))?,
+        LexArrayItem::Boolean(_) => {
+            return Err(CodegenError::unsupported("an array of booleans").at("items"));
+        }
+        LexArrayItem::Bytes(_) => {
+            return Err(CodegenError::unsupported("an array of bytes").at("items"));
+        }
+        LexArrayItem::Blob(_) => {
+            return Err(CodegenError::unsupported("an array of blobs").at("items"));
+        }
     };
     Ok((description, quote!(Vec<#item_type>)))
 }
 
+/// The name of the type generated for property `name` of an object, e.g. `RecordStatus`.
+fn property_name(object_name: &str, name: &str) -> String {
+    format!("{}{}", object_name.to_pascal_case(), name.to_pascal_case())
+}
+
+/// The name of the type generated for the items of array `name`, e.g. `RecordTagsItem`.
+fn array_item_name(object_name: Option<&str>, name: &str) -> String {
+    format!(
+        "{}{}Item",
+        object_name.map_or(String::new(), str::to_pascal_case),
+        name.to_pascal_case()
+    )
+}
+
 fn blob_type(blob: &LexBlob) -> Result<(TokenStream, TokenStream)> {
     let description = description(&blob.description);
     Ok((description, quote!(atrium_api::types::BlobRef)))
@@ -548,21 +1003,42 @@ fn integer_type(integer: &LexInteger) -> Result<(TokenStream, TokenStream)> {
                 (-0x0000_8000, 0x0000_7fff) => quote!(i16),
                 (-0x8000_0000, 0x7fff_ffff) => quote!(i32),
                 (i64::MIN, i64::MAX) => quote!(i64),
-                // TODO: Implement newtype wrappers for bounded signed integers.
-                _ => unimplemented!("i64(min: {}, max: {})", min, max),
+                // Otherwise use the smallest bounded newtype that fits both bounds.
+                _ => bounded_signed(min, max),
             },
-            None => quote!(i64),
+            None if min == i64::MIN => quote!(i64),
+            // Without a maximum, only the minimum acceptable value is enforced.
+            None => quote!(esquema_validator::types::BoundedI64<#min, { i64::MAX }>),
         },
+        // Without a minimum, only the maximum acceptable value is enforced.
         None => match integer.maximum {
-            Some(max) => unimplemented!("i64(max: {})", max),
+            Some(max) => quote!(esquema_validator::types::BoundedI64<{ i64::MIN }, #max>),
             None => quote!(i64),
         },
     };
     Ok((description, typ))
 }
 
-fn string_type(string: &LexString) -> Result<(TokenStream, TokenStream)> {
+/// `esquema_validator::types::BoundedI*<MIN, MAX>` for a range that isn't a whole signed primitive.
+fn bounded_signed(min: i64, max: i64) -> TokenStream {
+    if let (Ok(min), Ok(max)) = (i8::try_from(min), i8::try_from(max)) {
+        quote!(esquema_validator::types::BoundedI8<#min, #max>)
+    } else if let (Ok(min), Ok(max)) = (i16::try_from(min), i16::try_from(max)) {
+        quote!(esquema_validator::types::BoundedI16<#min, #max>)
+    } else if let (Ok(min), Ok(max)) = (i32::try_from(min), i32::try_from(max)) {
+        quote!(esquema_validator::types::BoundedI32<#min, #max>)
+    } else {
+        quote!(esquema_validator::types::BoundedI64<#min, #max>)
+    }
+}
+
+/// The type of a string, `enum_name` being the enum generated for it if it has `enum` or `knownValues`.
+fn string_type(string: &LexString, enum_name: &str) -> Result<(TokenStream, TokenStream)> {
     let description = description(&string.description);
+    if is_string_enum(string) {
+        let enum_name = format_ident!("{enum_name}");
+        return Ok((description, quote!(#enum_name)));
+    }
     let typ = match string.format {
         Some(LexStringFormat::AtIdentifier) => quote!(atrium_api::types::string::AtIdentifier),
         Some(LexStringFormat::Cid) => quote!(atrium_api::types::string::Cid),
@@ -573,12 +1049,126 @@ fn string_type(string: &LexString) -> Result<(TokenStream, TokenStream)> {
         Some(LexStringFormat::Language) => quote!(atrium_api::types::string::Language),
         Some(LexStringFormat::Tid) => quote!(atrium_api::types::string::Tid),
         Some(LexStringFormat::RecordKey) => quote!(atrium_api::types::string::RecordKey),
-        // TODO: other formats (uri, at-uri)
-        _ => quote!(String),
+        Some(LexStringFormat::Uri) => quote!(esquema_validator::types::Uri),
+        Some(LexStringFormat::AtUri) => quote!(esquema_validator::types::AtUri),
+        None => quote!(String),
     };
     Ok((description, typ))
 }
 
+/// Whether a string gets its own enum, closed for `enum` and open for `knownValues`.
+fn is_string_enum(string: &LexString) -> bool {
+    string.format.is_none() && (string.r#enum.is_some() || string.known_values.is_some())
+}
+
+/// An enum of the values of a string with `enum` or `knownValues`.
+///
+/// `knownValues` are open, so their enum gets an `Other` variant holding any other value.
+fn string_enum(string: &LexString, name: &str, ctx: &Context) -> Result<TokenStream> {
+    let description = description(&string.description);
+    let derives = ctx.derives_without_default()?;
+    let enum_name = format_ident!("{name}");
+    let (values, open) = match (&string.r#enum, &string.known_values) {
+        (Some(values), _) => (values, false),
+        (None, Some(values)) => (values, true),
+        (None, None) => return Ok(quote!()),
+    };
+    let variants = string_enum_variants(values);
+    let (other_variant, other_as_str, other_from_str) = if open {
+        (
+            quote!(Other(String),),
+            quote!(Self::Other(value) => value,),
+            quote!(_ => Ok(Self::Other(value.into())),),
+        )
+    } else {
+        (
+            quote!(),
+            quote!(),
+            quote!(_ => Err(format!("unknown {} value: {value}", #name)),),
+        )
+    };
+    let validate = impl_validate_noop(&enum_name);
+    Ok(quote! {
+        #description
+        #derives
+        #[serde(try_from = "String", into = "String")]
+        pub enum #enum_name {
+            #(#variants,)*
+            #other_variant
+        }
+
+        impl #enum_name {
+            pub fn as_str(&self) -> &str {
+                match self {
+                    #(Self::#variants => #values,)*
+                    #other_as_str
+                }
+            }
+        }
+
+        impl std::str::FromStr for #enum_name {
+            type Err = String;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                match value {
+                    #(#values => Ok(Self::#variants),)*
+                    #other_from_str
+                }
+            }
+        }
+
+        impl TryFrom<String> for #enum_name {
+            type Error = String;
+
+            fn try_from(value: String) -> Result<Self, Self::Error> {
+                value.parse()
+            }
+        }
+
+        impl From<#enum_name> for String {
+            fn from(value: #enum_name) -> Self {
+                value.as_str().to_string()
+            }
+        }
+
+        impl std::fmt::Display for #enum_name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        #validate
+    })
+}
+
+/// Variant names for string values, using the fragment of token refs like `com.example.defs#big`.
+fn string_enum_variants(values: &[String]) -> Vec<Ident> {
+    let mut names = Vec::<String>::with_capacity(values.len());
+    for value in values {
+        let short = value
+            .rsplit_once('#')
+            .map_or(value.as_str(), |(_, fragment)| fragment);
+        let mut name = to_variant(short);
+        if names.contains(&name) || name == "Other" {
+            name = to_variant(value);
+        }
+        while names.contains(&name) || name == "Other" {
+            name.push('_');
+        }
+        names.push(name);
+    }
+    names.iter().map(|name| format_ident!("{name}")).collect()
+}
+
+fn to_variant(value: &str) -> String {
+    let name = value.to_pascal_case();
+    if name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name
+    } else {
+        format!("Value{name}")
+    }
+}
+
 fn unknown_type(unknown: &LexUnknown) -> Result<(TokenStream, TokenStream)> {
     let description = description(&unknown.description);
     let typ = quote!(atrium_api::types::Unknown);
@@ -593,8 +1183,13 @@ fn description(description: &Option<String>) -> TokenStream {
     }
 }
 
-fn refs_enum(refs: &[String], name: &str, schema_id: Option<&str>) -> Result<TokenStream> {
-    enum_common(refs, name, schema_id, &[], &None)
+fn refs_enum(
+    refs: &[String],
+    name: &str,
+    schema_id: Option<&str>,
+    ctx: &Context,
+) -> Result<TokenStream> {
+    enum_common(refs, name, schema_id, &[], ctx)
 }
 
 pub fn enum_common(
@@ -602,18 +1197,16 @@ pub fn enum_common(
     name: &str,
     schema_id: Option<&str>,
     namespaces: &[(String, Option<&str>)],
-    module_name: &Option<String>,
+    ctx: &Context,
 ) -> Result<TokenStream> {
     let is_record = schema_id.is_none();
-    let derives = derives()?;
+    let derives = ctx.derives_without_default()?;
     let enum_name = format_ident!("{name}");
     let mut variants = Vec::new();
+    let mut variant_names = Vec::new();
+    let mut external = Vec::new();
     for r#ref in refs {
-        let path = resolve_path(
-            r#ref,
-            if is_record { "record" } else { "main" },
-            &module_name,
-        )?;
+        let path = ctx.resolve(r#ref, if is_record { "record" } else { "main" })?;
         let rename = if r#ref.starts_with('#') {
             format!(
                 "{}{}",
@@ -623,17 +1216,10 @@ pub fn enum_common(
         } else {
             r#ref.clone()
         };
-        let s = path.to_string().replace(' ', "");
-        let mut parts = s
-            .strip_prefix("crate::")
-            .unwrap_or(&s)
-            .split("::")
-            .map(str::to_pascal_case)
-            .collect_vec();
-        if is_record {
-            parts.pop();
-        }
-        let name = format_ident!("{}", parts.join(""));
+        let name = format_ident!(
+            "{}",
+            ctx.variant_name(r#ref, if is_record { "" } else { "main" })
+        );
         let mut feature = quote!();
         if is_record {
             if let Some((_, Some(feature_name))) = namespaces
@@ -651,34 +1237,34 @@ pub fn enum_common(
             #[serde(rename = #rename)]
             #name(Box<#path>)
         });
+        variant_names.push(name);
+        external.push(ctx.is_external(r#ref));
     }
+    let validate = if is_record {
+        quote!()
+    } else {
+        impl_validate_enum(&enum_name, &variant_names, &external)
+    };
     Ok(quote! {
         #derives
         #[serde(tag = "$type")]
         pub enum #enum_name {
             #(#variants),*
         }
+        #validate
     })
 }
 
 pub fn impl_into_record(
     refs: &[String],
     namespaces: &[(String, Option<&str>)],
-    module_name: &Option<String>,
+    ctx: &Context,
 ) -> Result<TokenStream> {
     let mut impls = Vec::new();
     for r#ref in refs {
-        let record_path = resolve_path(r#ref, "record", &module_name)?;
-        let record_data_path = resolve_path(r#ref, "record_data", module_name)?;
-        let s = record_path.to_string().replace(' ', "");
-        let mut parts = s
-            .strip_prefix("crate::")
-            .unwrap_or(&s)
-            .split("::")
-            .map(str::to_pascal_case)
-            .collect_vec();
-        parts.pop();
-        let name = format_ident!("{}", parts.join(""));
+        let record_path = ctx.resolve(r#ref, "record")?;
+        let record_data_path = ctx.resolve(r#ref, "record_data")?;
+        let name = format_ident!("{}", ctx.variant_name(r#ref, ""));
         let mut feature = quote!();
         if let Some((_, Some(feature_name))) = namespaces
             .iter()
@@ -739,32 +1325,46 @@ pub fn modules(
 
 pub fn lexicon_module(
     namespaces: &[(String, Option<&str>)],
+    generate_records: bool,
     generate_client: bool,
+    generate_server: bool,
 ) -> Result<TokenStream> {
     let v = namespaces
         .iter()
-        .filter_map(|s| {
-            let possible_namespace = s.0.split('.').next();
-            if possible_namespace.is_none() {
-                panic!("unexpected namespace whole generating lexicon module");
-            }
-            let namespace = possible_namespace.unwrap();
+        .filter_map(|(namespace, _)| namespace.split('.').next())
+        .unique()
+        .map(|namespace| {
             let m = format_ident!("{namespace}");
             quote! {
                 pub mod #m;
             }
-            .into()
         })
         .collect_vec();
-    let top = if generate_client {
-        quote! {
+    let record = if generate_records {
+        quote!(
             pub mod record;
+        )
+    } else {
+        quote!()
+    };
+    let client = if generate_client {
+        quote!(
             pub mod client;
-        }
+        )
     } else {
-        quote! {
-            pub mod record;
-        }
+        quote!()
+    };
+    let server = if generate_server {
+        quote!(
+            pub mod server;
+        )
+    } else {
+        quote!()
+    };
+    let top = quote! {
+        #record
+        #client
+        #server
     };
 
     Ok(quote!(
@@ -773,25 +1373,11 @@ pub fn lexicon_module(
     ))
 }
 
-fn derives() -> Result<TokenStream> {
-    let mut derives = Vec::new();
-    for derive in &[
-        "serde::Serialize",
-        "serde::Deserialize",
-        "Debug",
-        "Clone",
-        "PartialEq",
-        "Eq",
-    ] {
-        derives.push(syn::parse_str::<Path>(derive)?);
-    }
-    Ok(quote!(#[derive(#(#derives),*)]))
-}
-
 pub fn client(
     tree: &HashMap<String, HashSet<(&str, bool)>>,
     schemas: &HashMap<String, &LexUserType>,
     namespaces: &[(String, Option<&str>)],
+    ctx: &Context,
 ) -> Result<TokenStream> {
     let services = client_services("", tree, namespaces)?;
     let mut impls = Vec::new();
@@ -807,8 +1393,13 @@ pub fn client(
         for (name, _) in tree[key].iter().filter(|(_, b)| *b).sorted() {
             let nsid = format!("{key}.{name}");
             let method = match schemas[&nsid] {
-                LexUserType::XrpcQuery(query) => xrpc_impl_query(query, &nsid)?,
-                LexUserType::XrpcProcedure(procedure) => xrpc_impl_procedure(procedure, &nsid)?,
+                LexUserType::XrpcQuery(query) => xrpc_impl_query(query, &nsid, ctx)?,
+                LexUserType::XrpcProcedure(procedure) => {
+                    xrpc_impl_procedure(procedure, &nsid, ctx)?
+                }
+                LexUserType::XrpcSubscription(subscription) => {
+                    xrpc_impl_subscription(subscription, &nsid, ctx)?
+                }
                 _ => unreachable!(),
             };
             methods.push(method);
@@ -963,7 +1554,7 @@ fn client_new(
     })
 }
 
-fn xrpc_impl_query(query: &LexXrpcQuery, nsid: &str) -> Result<TokenStream> {
+fn xrpc_impl_query(query: &LexXrpcQuery, nsid: &str, ctx: &Context) -> Result<TokenStream> {
     let description = description(&query.description);
     let has_params = query.parameters.is_some();
     let output = query.output.as_ref();
@@ -977,7 +1568,7 @@ fn xrpc_impl_query(query: &LexXrpcQuery, nsid: &str) -> Result<TokenStream> {
 
     let mut args = vec![quote!(&self)];
     if has_params {
-        let parameters = resolve_path(nsid, "Parameters", &None)?;
+        let parameters = ctx.resolve(nsid, "Parameters")?;
         args.push(quote!(params: #parameters));
     }
     let generic_args = vec![
@@ -995,7 +1586,7 @@ fn xrpc_impl_query(query: &LexXrpcQuery, nsid: &str) -> Result<TokenStream> {
     } else {
         quote!(None)
     };
-    let nsid_path = resolve_path(nsid, "NSID", &None)?;
+    let nsid_path = ctx.resolve(nsid, "NSID")?;
     let xrpc_call = quote! {
         self.xrpc.send_xrpc::<#(#generic_args),*>(&atrium_xrpc::XrpcRequest {
             method: http::Method::GET,
@@ -1006,10 +1597,93 @@ fn xrpc_impl_query(query: &LexXrpcQuery, nsid: &str) -> Result<TokenStream> {
         })
         .await?
     };
-    xrpc_impl_common(nsid, &description, &xrpc_call, &args, output_type)
+    let method = xrpc_impl_common(
+        nsid,
+        &description,
+        &xrpc_call,
+        &args,
+        output_type,
+        output,
+        ctx,
+    )?;
+    let stream = xrpc_impl_query_stream(query, nsid, ctx)?;
+    Ok(quote! {
+        #method
+        #stream
+    })
+}
+
+/// `*_stream` for a query with a `cursor` parameter and a `cursor` in its output, following the
+/// cursors from page to page.
+fn xrpc_impl_query_stream(query: &LexXrpcQuery, nsid: &str, ctx: &Context) -> Result<TokenStream> {
+    let Some(LexXrpcQueryParameter::Params(parameters)) = &query.parameters else {
+        return Ok(quote!());
+    };
+    let Some(LexXrpcBodySchema::Object(output)) = query
+        .output
+        .as_ref()
+        .and_then(|output| output.schema.as_ref())
+    else {
+        return Ok(quote!());
+    };
+    let takes_cursor = match parameters.properties.get("cursor") {
+        Some(LexXrpcParametersProperty::String(string)) => {
+            is_cursor(string) && !contains_cursor(&parameters.required)
+        }
+        _ => false,
+    };
+    let returns_cursor = match output.properties.get("cursor") {
+        Some(LexObjectProperty::String(string)) => is_cursor(string),
+        _ => false,
+    };
+    if !takes_cursor || !returns_cursor {
+        return Ok(quote!());
+    }
+    let output_cursor = if contains_cursor(&output.required) && !contains_cursor(&output.nullable) {
+        quote!(Some(output.cursor.clone()))
+    } else {
+        quote!(output.cursor.clone())
+    };
+    let name = nsid.split('.').last().unwrap().to_snake_case();
+    let method_name = format_ident!("{name}");
+    let stream_name = format_ident!("{name}_stream");
+    let doc = format!("The pages of [`Self::{name}`], following their cursors until the last one.");
+    let parameters = ctx.resolve(nsid, "Parameters")?;
+    let output = ctx.resolve(nsid, "Output")?;
+    let error = ctx.resolve(nsid, "Error")?;
+    Ok(quote! {
+        #[doc = #doc]
+        pub fn #stream_name(
+            &self,
+            params: #parameters,
+        ) -> esquema_client::paginate::Pages<'_, #output, atrium_xrpc::Error<#error>> {
+            esquema_client::paginate::pages(
+                params,
+                move |params| self.#method_name(params),
+                |params, cursor| params.cursor = cursor,
+                |output| #output_cursor,
+            )
+        }
+    })
 }
 
-fn xrpc_impl_procedure(procedure: &LexXrpcProcedure, nsid: &str) -> Result<TokenStream> {
+/// Whether a `cursor` string is generated as a plain `String`, without a format or fixed value.
+fn is_cursor(string: &LexString) -> bool {
+    string.format.is_none()
+        && !is_string_enum(string)
+        && string.default.is_none()
+        && string.r#const.is_none()
+}
+
+fn contains_cursor(names: &Option<Vec<String>>) -> bool {
+    names.iter().flatten().any(|name| name == "cursor")
+}
+
+fn xrpc_impl_procedure(
+    procedure: &LexXrpcProcedure,
+    nsid: &str,
+    ctx: &Context,
+) -> Result<TokenStream> {
     let description = description(&procedure.description);
     let input = procedure.input.as_ref();
     let output = procedure.output.as_ref();
@@ -1023,7 +1697,7 @@ fn xrpc_impl_procedure(procedure: &LexXrpcProcedure, nsid: &str) -> Result<Token
     let mut args = vec![quote!(&self)];
     if let Some(body) = &input {
         if body.schema.is_some() {
-            let input = resolve_path(nsid, "Input", &None)?;
+            let input = body_type(Some(body), nsid, "Input", ctx)?;
             args.push(quote!(input: #input));
         } else {
             args.push(quote!(input: Vec<u8>));
@@ -1062,7 +1736,7 @@ fn xrpc_impl_procedure(procedure: &LexXrpcProcedure, nsid: &str) -> Result<Token
     } else {
         quote!(None)
     };
-    let nsid_path = resolve_path(nsid, "NSID", &None)?;
+    let nsid_path = ctx.resolve(nsid, "NSID")?;
     let xrpc_call = quote! {
         self.xrpc.send_xrpc::<#(#generic_args),*>(&atrium_xrpc::XrpcRequest {
             method: http::Method::POST,
@@ -1073,7 +1747,46 @@ fn xrpc_impl_procedure(procedure: &LexXrpcProcedure, nsid: &str) -> Result<Token
         })
         .await?
     };
-    xrpc_impl_common(nsid, &description, &xrpc_call, &args, output_type)
+    xrpc_impl_common(
+        nsid,
+        &description,
+        &xrpc_call,
+        &args,
+        output_type,
+        output,
+        ctx,
+    )
+}
+
+fn xrpc_impl_subscription(
+    subscription: &LexXrpcSubscription,
+    nsid: &str,
+    ctx: &Context,
+) -> Result<TokenStream> {
+    let description = description(&subscription.description);
+    let name = nsid.split('.').last().unwrap();
+    let method_name = format_ident!("{}", name.to_snake_case());
+    let nsid_path = ctx.resolve(nsid, "NSID")?;
+    let message = ctx.resolve(nsid, "Message")?;
+    let error = ctx.resolve(nsid, "Error")?;
+    let (args, params) = if subscription.parameters.is_some() {
+        let parameters = ctx.resolve(nsid, "Parameters")?;
+        (quote!(params: #parameters), quote!(Some(&params)))
+    } else {
+        (quote!(), quote!(None::<&()>))
+    };
+    Ok(quote! {
+        #description
+        pub async fn #method_name(
+            &self,
+            #args
+        ) -> core::result::Result<
+            esquema_client::subscription::Subscription<#message, #error>,
+            esquema_client::subscription::Error<#error>,
+        > {
+            esquema_client::subscription::subscribe(&self.xrpc.base_uri(), #nsid_path, #params).await
+        }
+    })
 }
 
 fn xrpc_impl_common(
@@ -1082,10 +1795,12 @@ fn xrpc_impl_common(
     xrpc_call: &TokenStream,
     args: &[TokenStream],
     output_type: OutputType,
+    output: Option<&LexXrpcBody>,
+    ctx: &Context,
 ) -> Result<TokenStream> {
     let name = nsid.split('.').last().unwrap();
     let method_name = format_ident!("{}", name.to_snake_case());
-    let error = resolve_path(nsid, "Error", &None)?;
+    let error = ctx.resolve(nsid, "Error")?;
     let body = match output_type {
         OutputType::None => {
             quote! {
@@ -1101,7 +1816,7 @@ fn xrpc_impl_common(
             }
         }
         OutputType::Data => {
-            let output = resolve_path(nsid, "Output", &None)?;
+            let output = body_type(output, nsid, "Output", ctx)?;
             quote! {
                 pub async fn #method_name(
                     #(#args),*
@@ -1134,29 +1849,153 @@ fn xrpc_impl_common(
     })
 }
 
-fn resolve_path(r#ref: &str, default: &str, module_name: &Option<String>) -> Result<TokenStream> {
-    let (namespace, def) = r#ref.split_once('#').unwrap_or((r#ref, default));
-    let path = syn::parse_str::<Path>(&if namespace.is_empty() {
-        def.to_pascal_case()
-    } else {
-        //TODO here
-        let name_space_prefix = match module_name {
-            None => String::from("crate"),
-            Some(module_name) => {
-                format!("crate::{}", module_name)
+/// The `Handler` trait with a method per query and procedure, and the axum router serving them
+/// at `/xrpc/<nsid>`.
+///
+/// Methods are named after the last NSID segment, or the whole NSID where that is ambiguous. They
+/// default to `MethodNotImplemented`, so a handler only implements the methods it serves.
+pub fn server(
+    schemas: &[(String, &LexUserType)],
+    namespaces: &[(String, Option<&str>)],
+    ctx: &Context,
+) -> Result<TokenStream> {
+    let names = schemas
+        .iter()
+        .map(|(nsid, _)| nsid.split('.').last().unwrap().to_snake_case())
+        .collect_vec();
+    let mut methods = Vec::new();
+    let mut routes = Vec::new();
+    for ((nsid, def), name) in schemas.iter().zip(&names) {
+        let method_name = if names.iter().filter(|other| *other == name).count() > 1 {
+            format_ident!("{}", nsid.replace('.', "_").to_snake_case())
+        } else {
+            format_ident!("{name}")
+        };
+        let (doc, method, input, output) = match def {
+            LexUserType::XrpcQuery(query) => (
+                &query.description,
+                quote!(get),
+                query.parameters.as_ref().map(|_| {
+                    let parameters = ctx.resolve(nsid, "Parameters")?;
+                    // the query string is decoded into the data, as the flattened fields of
+                    // `Parameters` would only see strings
+                    let data = ctx.resolve(nsid, "ParametersData")?;
+                    Ok::<_, CodegenError>((
+                        quote!(params: #parameters),
+                        quote!(esquema_server::Query(params): esquema_server::Query<#data>),
+                        quote!(params.into()),
+                    ))
+                }),
+                query.output.as_ref(),
+            ),
+            LexUserType::XrpcProcedure(procedure) => (
+                &procedure.description,
+                quote!(post),
+                procedure.input.as_ref().map(|body| {
+                    if body.schema.is_some() {
+                        let input = body_type(Some(body), nsid, "Input", ctx)?;
+                        Ok::<_, CodegenError>((
+                            quote!(input: #input),
+                            quote!(esquema_server::Json(input): esquema_server::Json<#input>),
+                            quote!(input),
+                        ))
+                    } else {
+                        Ok((
+                            quote!(input: Vec<u8>),
+                            quote!(input: esquema_server::axum::body::Bytes),
+                            quote!(input.to_vec()),
+                        ))
+                    }
+                }),
+                procedure.output.as_ref(),
+            ),
+            _ => unreachable!(),
+        };
+        let (arg, extractor, value) = match input.transpose()? {
+            Some((arg, extractor, value)) => (quote!(#arg,), quote!(#extractor,), quote!(#value)),
+            None => (quote!(), quote!(), quote!()),
+        };
+        let call = quote!(handler.#method_name(#value).await);
+        let (output_type, respond) = match output {
+            Some(body) if body.schema.is_some() => (
+                body_type(Some(body), nsid, "Output", ctx)?,
+                quote!(esquema_server::response::json(#call)),
+            ),
+            Some(body) => {
+                let encoding = &body.encoding;
+                (
+                    quote!(Vec<u8>),
+                    quote!(esquema_server::response::bytes(#encoding, #call)),
+                )
             }
+            None => (quote!(()), quote!(esquema_server::response::empty(#call))),
+        };
+        let description = description(doc);
+        let error = ctx.resolve(nsid, "Error")?;
+        let feature = if let Some((_, Some(feature_name))) = namespaces
+            .iter()
+            .find(|(prefix, _)| nsid.starts_with(prefix))
+        {
+            quote!(#[cfg(feature = #feature_name)])
+        } else {
+            quote!()
         };
+        methods.push(quote! {
+            #description
+            #feature
+            #[allow(unused_variables)]
+            fn #method_name(
+                &self,
+                #arg
+            ) -> impl core::future::Future<
+                Output = core::result::Result<#output_type, esquema_server::Error<#error>>,
+            > + Send {
+                async { Err(esquema_server::Error::NotImplemented) }
+            }
+        });
+        let path = format!("/xrpc/{nsid}");
+        routes.push(quote! {
+            #feature
+            let router = router.route(
+                #path,
+                esquema_server::axum::routing::#method(
+                    |esquema_server::axum::extract::State(handler): esquema_server::axum::extract::State<std::sync::Arc<H>>,
+                     #extractor| async move { #respond },
+                ),
+            );
+        });
+    }
+    Ok(quote! {
+        #[doc = "Implements the queries and procedures of the lexicons, mounted by [`router`]."]
+        pub trait Handler: Send + Sync + 'static {
+            #(#methods)*
+        }
+
+        #[doc = "Serves every method of `handler` at `/xrpc/<nsid>`."]
+        pub fn router<H: Handler>(handler: H) -> esquema_server::axum::Router {
+            let router = esquema_server::axum::Router::new();
+            #(#routes)*
+            router.with_state(std::sync::Arc::new(handler))
+        }
+    })
+}
 
-        format!(
-            "{}::{}::{}",
-            name_space_prefix,
-            namespace.split('.').map(str::to_snake_case).join("::"),
-            if def.chars().all(char::is_uppercase) {
-                def.to_string()
+/// The type of an XRPC input or output body, the `InputRefs`/`OutputRefs` enum when it is a union.
+fn body_type(
+    body: Option<&LexXrpcBody>,
+    nsid: &str,
+    name: &str,
+    ctx: &Context,
+) -> Result<TokenStream> {
+    match body.and_then(|body| body.schema.as_ref()) {
+        Some(LexXrpcBodySchema::Union(union)) => {
+            let refs = ctx.resolve(nsid, &format!("{name}Refs"))?;
+            if union.closed.unwrap_or_default() {
+                Ok(refs)
             } else {
-                def.to_pascal_case()
+                Ok(quote!(atrium_api::types::Union<#refs>))
             }
-        )
-    })?;
-    Ok(quote!(#path))
+        }
+        _ => Ok(ctx.resolve(nsid, name)?),
+    }
 }
@@ -0,0 +1,228 @@
+use crate::context::Context;
+use crate::token_stream::field_name;
+use atrium_lex::lexicon::*;
+use itertools::Itertools;
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use std::collections::HashSet;
+
+/// `impl esquema_validator::Validate` for a generated `*Data` struct.
+///
+/// `present` are the properties whose fields aren't an `Option`.
+pub(crate) fn impl_validate_object(
+    object: &LexObject,
+    struct_name: &Ident,
+    present: &HashSet<&String>,
+    ctx: &Context,
+) -> TokenStream {
+    let mut blocks = Vec::new();
+    for key in object.properties.keys().sorted() {
+        let checks = property_checks(&object.properties[key], ctx);
+        if checks.is_empty() {
+            continue;
+        }
+        let field_name = field_name(key);
+        let binding = if present.contains(key) {
+            quote!(let value = &self.#field_name; #(#checks)*)
+        } else {
+            quote! {
+                if let Some(value) = &self.#field_name {
+                    #(#checks)*
+                }
+            }
+        };
+        blocks.push(quote! {
+            {
+                let path = esquema_validator::join_path(path, #key);
+                #binding
+            }
+        });
+    }
+    impl_validate(struct_name, quote!(#(#blocks)*))
+}
+
+/// `impl esquema_validator::Validate` for a generated union enum.
+///
+/// Variants holding types from an external crate are not checked, those types don't implement `Validate`.
+pub(crate) fn impl_validate_enum(
+    enum_name: &Ident,
+    variants: &[Ident],
+    external: &[bool],
+) -> TokenStream {
+    let arms = variants.iter().zip(external).map(|(variant, external)| {
+        if *external {
+            quote!(#enum_name::#variant(_) => {})
+        } else {
+            quote! {
+                #enum_name::#variant(value) => esquema_validator::Validate::validate_at(value, path, violations),
+            }
+        }
+    });
+    impl_validate(
+        enum_name,
+        quote! {
+            match self {
+                #(#arms)*
+            }
+        },
+    )
+}
+
+/// `impl esquema_validator::Validate` for a generated type whose constraints are enforced by the type itself.
+pub(crate) fn impl_validate_noop(type_name: &Ident) -> TokenStream {
+    impl_validate(type_name, quote!())
+}
+
+fn impl_validate(type_name: &Ident, body: TokenStream) -> TokenStream {
+    quote! {
+        impl esquema_validator::Validate for #type_name {
+            #[allow(unused_variables)]
+            fn validate_at(&self, path: &str, violations: &mut Vec<esquema_validator::Violation>) {
+                #body
+            }
+        }
+    }
+}
+
+/// Checks against a `value: &T` binding, reporting at `path`.
+fn property_checks(property: &LexObjectProperty, ctx: &Context) -> Vec<TokenStream> {
+    match property {
+        LexObjectProperty::Ref(r#ref) if ctx.is_external(&r#ref.r#ref) => Vec::new(),
+        LexObjectProperty::Ref(_) | LexObjectProperty::Union(_) => vec![validate_nested()],
+        LexObjectProperty::Bytes(bytes) => length_checks(bytes.min_length, bytes.max_length),
+        LexObjectProperty::Array(array) => array_checks(array, ctx),
+        LexObjectProperty::Blob(blob) => blob_checks(blob),
+        LexObjectProperty::Boolean(boolean) => boolean_checks(boolean),
+        LexObjectProperty::Integer(integer) => integer_checks(integer),
+        LexObjectProperty::String(string) => string_checks(string),
+        LexObjectProperty::CidLink(_) | LexObjectProperty::Unknown(_) => Vec::new(),
+    }
+}
+
+fn validate_nested() -> TokenStream {
+    quote!(esquema_validator::Validate::validate_at(value, &path, violations);)
+}
+
+fn length_checks(min: Option<usize>, max: Option<usize>) -> Vec<TokenStream> {
+    let mut checks = Vec::new();
+    if let Some(min) = min {
+        checks.push(
+            quote!(esquema_validator::rules::min_length(&path, value.len(), #min, violations);),
+        );
+    }
+    if let Some(max) = max {
+        checks.push(
+            quote!(esquema_validator::rules::max_length(&path, value.len(), #max, violations);),
+        );
+    }
+    checks
+}
+
+fn array_checks(array: &LexArray, ctx: &Context) -> Vec<TokenStream> {
+    let mut checks = length_checks(array.min_length, array.max_length);
+    let item_checks = match &array.items {
+        LexArrayItem::Ref(r#ref) if ctx.is_external(&r#ref.r#ref) => Vec::new(),
+        LexArrayItem::Ref(_) | LexArrayItem::Union(_) => {
+            checks.push(validate_nested());
+            Vec::new()
+        }
+        LexArrayItem::Boolean(boolean) => boolean_checks(boolean),
+        LexArrayItem::Integer(integer) => integer_checks(integer),
+        LexArrayItem::String(string) => string_checks(string),
+        LexArrayItem::Bytes(bytes) => length_checks(bytes.min_length, bytes.max_length),
+        LexArrayItem::Blob(blob) => blob_checks(blob),
+        LexArrayItem::CidLink(_) | LexArrayItem::Unknown(_) => Vec::new(),
+    };
+    if !item_checks.is_empty() {
+        checks.push(quote! {
+            for (i, value) in value.iter().enumerate() {
+                let path = esquema_validator::join_path(&path, i);
+                #(#item_checks)*
+            }
+        });
+    }
+    checks
+}
+
+fn blob_checks(blob: &LexBlob) -> Vec<TokenStream> {
+    if blob.accept.is_none() && blob.max_size.is_none() {
+        return Vec::new();
+    }
+    let accept = match &blob.accept {
+        Some(accept) => quote!(Some(&[#(#accept),*][..])),
+        None => quote!(None::<&[&str]>),
+    };
+    let max_size = match blob.max_size {
+        Some(max_size) => quote!(Some(#max_size)),
+        None => quote!(None),
+    };
+    vec![quote!(esquema_validator::rules::blob(&path, value, #accept, #max_size, violations);)]
+}
+
+fn boolean_checks(boolean: &LexBoolean) -> Vec<TokenStream> {
+    match boolean.r#const {
+        Some(expected) => {
+            vec![quote!(esquema_validator::rules::constant(&path, value, &#expected, violations);)]
+        }
+        None => Vec::new(),
+    }
+}
+
+fn integer_checks(integer: &LexInteger) -> Vec<TokenStream> {
+    let mut checks = Vec::new();
+    if let Some(min) = integer.minimum {
+        checks.push(quote!(esquema_validator::rules::minimum(&path, n, #min, violations);));
+    }
+    if let Some(max) = integer.maximum {
+        checks.push(quote!(esquema_validator::rules::maximum(&path, n, #max, violations);));
+    }
+    if let Some(expected) = integer.r#const {
+        checks.push(quote!(esquema_validator::rules::constant(&path, &n, &#expected, violations);));
+    }
+    if let Some(allowed) = &integer.r#enum {
+        checks.push(
+            quote!(esquema_validator::rules::one_of(&path, &n, &[#(#allowed),*], violations);),
+        );
+    }
+    if checks.is_empty() {
+        return checks;
+    }
+    vec![quote! {
+        let n = esquema_validator::AsI64::as_i64(value);
+        #(#checks)*
+    }]
+}
+
+fn string_checks(string: &LexString) -> Vec<TokenStream> {
+    let mut checks = length_checks(string.min_length, string.max_length);
+    if let Some(min) = string.min_graphemes {
+        checks
+            .push(quote!(esquema_validator::rules::min_graphemes(&path, value, #min, violations);));
+    }
+    if let Some(max) = string.max_graphemes {
+        checks
+            .push(quote!(esquema_validator::rules::max_graphemes(&path, value, #max, violations);));
+    }
+    if let Some(expected) = &string.r#const {
+        checks
+            .push(quote!(esquema_validator::rules::constant(&path, value, #expected, violations);));
+    }
+    // `enum` values are enforced by the generated enum
+    if checks.is_empty() {
+        return checks;
+    }
+    // checked as `&str` so the enums generated for `knownValues` are covered too, formatted
+    // strings are parsed into types that only enforce their syntax
+    let as_str = match string.format {
+        Some(LexStringFormat::Cid) => quote! {
+            let value = value.as_ref().to_string();
+            let value: &str = &value;
+        },
+        Some(_) => quote!(let value: &str = value.as_ref();),
+        None => quote!(let value: &str = value.as_str();),
+    };
+    vec![quote! {
+        #as_str
+        #(#checks)*
+    }]
+}
@@ -0,0 +1,73 @@
+use esquema_codegen::config::{CONFIG_FILE_NAME, Config, LEXICON_SCHEMA_COLLECTION, Source};
+use std::path::PathBuf;
+
+#[test]
+fn loads_sources_relative_to_the_config() {
+    let dir = std::env::temp_dir().join(format!("esquema-config-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(CONFIG_FILE_NAME);
+    std::fs::write(
+        &path,
+        r#"
+derives = ["Hash"]
+server = true
+
+[[source]]
+local = "lexicons"
+
+[[source]]
+remote = { handle = "example.com", namespace = "com.example" }
+
+[[source]]
+nsids = ["xyz.statusphere.status"]
+
+[output]
+dir = "src"
+module = "lexicons"
+
+[externals]
+"com.example.shared" = "example_shared::com::example::shared"
+
+[features]
+"xyz.statusphere" = "statusphere"
+"#,
+    )
+    .unwrap();
+
+    let config = Config::load(&path).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(config.sources[0], Source::Local(dir.join("lexicons")));
+    assert!(
+        matches!(&config.sources[1], Source::Remote(remote) if remote.collection == LEXICON_SCHEMA_COLLECTION)
+    );
+    assert_eq!(
+        config.sources[2],
+        Source::Nsids(vec!["xyz.statusphere.status".into()])
+    );
+    assert_eq!(config.output.dir, dir.join("src"));
+    assert_eq!(config.output.module.as_deref(), Some("lexicons"));
+    assert_eq!(config.features["xyz.statusphere"], "statusphere");
+    assert!(config.server);
+
+    let externals = config.external_crates();
+    assert!(externals.contains("com.example.shared.defs"));
+    assert!(externals.contains("app.bsky.feed.post"));
+}
+
+#[test]
+fn can_leave_out_the_atrium_api_mappings() {
+    let config: Config = toml::from_str(
+        r#"
+default-externals = false
+
+[output]
+dir = "."
+"#,
+    )
+    .unwrap();
+    assert!(config.sources.is_empty());
+    assert!(!config.external_crates().contains("app.bsky.feed.post"));
+    assert_eq!(config.output.dir, PathBuf::from("."));
+    assert!(!config.server);
+}
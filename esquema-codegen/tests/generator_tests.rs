@@ -1 +1,927 @@
+use atrium_lex::LexiconDoc;
+use esquema_codegen::{CodegenError, Generator, GeneratorBuilder};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
 
+fn outdir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("esquema-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn generate(name: &str, lexicon: Value) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    generate_file(name, lexicon, "").map(|(paths, _)| paths)
+}
+
+/// Generates `lexicon`, returning the generated paths and the content of `file` in the output directory.
+fn generate_file(
+    name: &str,
+    lexicon: Value,
+    file: &str,
+) -> Result<(Vec<PathBuf>, String), Box<dyn Error>> {
+    let dir = outdir(name);
+    let doc: LexiconDoc = serde_json::from_value(lexicon).unwrap();
+    let result = Generator::builder()
+        .lexicons([doc])
+        .outdir(&dir)
+        .build()
+        .generate()
+        .map(|paths| {
+            let content = std::fs::read_to_string(dir.join(file)).unwrap_or_default();
+            (paths, content)
+        });
+    std::fs::remove_dir_all(dir).unwrap();
+    result
+}
+
+/// Generates `lexicon` with the options `configure` sets, returning the content of every generated file by its path in
+/// the output directory.
+fn generate_with(
+    name: &str,
+    lexicon: Value,
+    configure: impl FnOnce(GeneratorBuilder) -> GeneratorBuilder,
+) -> BTreeMap<PathBuf, String> {
+    let dir = outdir(name);
+    let doc: LexiconDoc = serde_json::from_value(lexicon).unwrap();
+    let paths = configure(Generator::builder().lexicons([doc]).outdir(&dir))
+        .build()
+        .generate()
+        .unwrap();
+    let root = dir.canonicalize().unwrap();
+    let files = paths
+        .iter()
+        .map(|path| {
+            let content = std::fs::read_to_string(path).unwrap();
+            (path.strip_prefix(&root).unwrap().to_path_buf(), content)
+        })
+        .collect();
+    std::fs::remove_dir_all(dir).unwrap();
+    files
+}
+
+#[test]
+fn unsupported_constructs_name_their_location() {
+    let err = generate(
+        "unsupported",
+        json!({
+            "lexicon": 1,
+            "id": "com.example.counter",
+            "defs": {
+                "main": {
+                    "type": "record",
+                    "key": "tid",
+                    "record": {
+                        "type": "object",
+                        "properties": {
+                            "flags": { "type": "array", "items": { "type": "boolean" } }
+                        }
+                    }
+                }
+            }
+        }),
+    )
+    .unwrap_err();
+    let err = err.downcast_ref::<CodegenError>().unwrap();
+    assert!(matches!(err, CodegenError::Unsupported { .. }));
+    assert_eq!(err.nsid(), "com.example.counter");
+    assert_eq!(err.def(), Some("main"));
+    assert_eq!(err.path(), Some("defs.main.record.properties.flags.items"));
+}
+
+#[test]
+fn rejects_ids_without_a_name() {
+    let err = generate(
+        "invalid-nsid",
+        json!({
+            "lexicon": 1,
+            "id": "example",
+            "defs": { "main": { "type": "token" } }
+        }),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<CodegenError>(),
+        Some(CodegenError::InvalidNsid { nsid }) if nsid == "example"
+    ));
+}
+
+#[test]
+fn primitive_defs_become_type_aliases() {
+    let (_, content) = generate_file(
+        "primitives",
+        json!({
+            "lexicon": 1,
+            "id": "com.example.defs",
+            "defs": {
+                "score": { "type": "integer", "minimum": 0 },
+                "flag": { "type": "boolean" },
+                "data": { "type": "bytes" },
+                "image": { "type": "blob", "accept": ["image/*"] },
+                "link": { "type": "cid-link" },
+                "extra": { "type": "unknown" }
+            }
+        }),
+        "com/example/defs.rs",
+    )
+    .unwrap();
+    for alias in [
+        "pub type Score = usize;",
+        "pub type Flag = bool;",
+        "pub type Data = Vec<u8>;",
+        "pub type Image = atrium_api::types::BlobRef;",
+        "pub type Link = atrium_api::types::CidLink;",
+        "pub type Extra = atrium_api::types::Unknown;",
+    ] {
+        assert!(content.contains(alias), "missing `{alias}` in:\n{content}");
+    }
+}
+
+#[test]
+fn bounded_signed_integers_use_the_smallest_newtype() {
+    let (_, content) = generate_file(
+        "signed",
+        json!({
+            "lexicon": 1,
+            "id": "com.example.weather",
+            "defs": {
+                "rating": { "type": "integer", "minimum": -100, "maximum": 100 },
+                "temperature": { "type": "integer", "minimum": -300, "maximum": 1000 },
+                "depth": { "type": "integer", "maximum": 0 },
+                "offset": { "type": "integer", "minimum": -12 }
+            }
+        }),
+        "com/example/weather.rs",
+    )
+    .unwrap();
+    for alias in [
+        "pub type Rating = esquema_validator::types::BoundedI8<-100i8, 100i8>;",
+        "pub type Temperature = esquema_validator::types::BoundedI16<-300i16, 1000i16>;",
+        "pub type Depth = esquema_validator::types::BoundedI64<{ i64::MIN }, 0i64>;",
+        "pub type Offset = esquema_validator::types::BoundedI64<-12i64, { i64::MAX }>;",
+    ] {
+        assert!(content.contains(alias), "missing `{alias}` in:\n{content}");
+    }
+}
+
+#[test]
+fn union_bodies_generate_refs_enums_used_by_the_client() {
+    let lexicon = json!({
+        "lexicon": 1,
+        "id": "com.example.putThing",
+        "defs": {
+            "main": {
+                "type": "procedure",
+                "input": {
+                    "encoding": "application/json",
+                    "schema": { "type": "union", "refs": ["#circle", "#square"], "closed": true }
+                },
+                "output": {
+                    "encoding": "application/json",
+                    "schema": { "type": "union", "refs": ["#circle", "#square"] }
+                }
+            },
+            "circle": { "type": "object", "properties": { "radius": { "type": "integer" } } },
+            "square": { "type": "object", "properties": { "side": { "type": "integer" } } }
+        }
+    });
+    let (_, schema) =
+        generate_file("union-body", lexicon.clone(), "com/example/put_thing.rs").unwrap();
+    assert!(schema.contains("pub enum InputRefs"));
+    assert!(schema.contains("pub enum OutputRefs"));
+
+    let (_, client) = generate_file("union-client", lexicon, "client.rs").unwrap();
+    // prettyplease may wrap the long signatures
+    let client = client.split_whitespace().collect::<String>();
+    assert!(client.contains("input:crate::com::example::put_thing::InputRefs"));
+    assert!(
+        client.contains("atrium_api::types::Union<crate::com::example::put_thing::OutputRefs>")
+    );
+}
+
+#[test]
+fn string_enums_and_known_values_become_enums() {
+    let (_, content) = generate_file(
+        "string-enums",
+        json!({
+            "lexicon": 1,
+            "id": "com.example.post",
+            "defs": {
+                "main": {
+                    "type": "record",
+                    "key": "tid",
+                    "record": {
+                        "type": "object",
+                        "required": ["visibility"],
+                        "properties": {
+                            "visibility": { "type": "string", "enum": ["public", "followers-only"] },
+                            "labels": {
+                                "type": "array",
+                                "items": {
+                                    "type": "string",
+                                    "knownValues": ["com.example.defs#spoiler", "!hide"]
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }),
+        "com/example/post.rs",
+    )
+    .unwrap();
+    let content = content.split_whitespace().collect::<String>();
+    for expected in [
+        "pubvisibility:RecordVisibility,",
+        "pubenumRecordVisibility{Public,FollowersOnly,}",
+        "pubenumRecordLabelsItem{Spoiler,Hide,Other(String),}",
+        "\"followers-only\"=>Ok(Self::FollowersOnly),",
+        "Self::Other(value)=>value,",
+    ] {
+        assert!(
+            content.contains(expected),
+            "missing `{expected}` in:\n{content}"
+        );
+    }
+}
+
+#[test]
+fn defaults_and_consts_are_filled_in() {
+    let (_, content) = generate_file(
+        "defaults",
+        json!({
+            "lexicon": 1,
+            "id": "com.example.game",
+            "defs": {
+                "main": {
+                    "type": "record",
+                    "key": "tid",
+                    "record": {
+                        "type": "object",
+                        "required": ["name"],
+                        "properties": {
+                            "name": { "type": "string" },
+                            "lives": { "type": "integer", "minimum": 0, "maximum": 9, "default": 3 },
+                            "version": { "type": "integer", "const": 2 },
+                            "note": { "type": "string" }
+                        }
+                    }
+                }
+            }
+        }),
+        "com/example/game.rs",
+    )
+    .unwrap();
+    let content = content.split_whitespace().collect::<String>();
+    for expected in [
+        "#[serde(default=\"RecordData::default_lives\")]publives:atrium_api::types::LimitedU8<9u8>,",
+        "#[serde(default=\"RecordData::const_version\",deserialize_with=\"RecordData::deserialize_version\")]pubversion:i64,",
+        "pubnote:core::option::Option<String>,",
+        "serde::de::value::I64Deserializer::<serde::de::value::Error>::new(3i64)",
+        "\"`version`mustbe2\"",
+        "pubfnnew(name:String)->Self{",
+        "lives:Self::default_lives(),name,note:None,version:Self::const_version()",
+    ] {
+        assert!(
+            content.contains(expected),
+            "missing `{expected}` in:\n{content}"
+        );
+    }
+}
+
+#[test]
+fn subscriptions_get_a_client_method_decoding_their_messages() {
+    let lexicon = json!({
+        "lexicon": 1,
+        "id": "com.example.subscribeTicks",
+        "defs": {
+            "main": {
+                "type": "subscription",
+                "parameters": {
+                    "type": "params",
+                    "properties": { "cursor": { "type": "integer" } }
+                },
+                "message": {
+                    "schema": { "type": "union", "refs": ["#tick"] }
+                },
+                "errors": [{ "name": "FutureCursor" }]
+            },
+            "tick": { "type": "object", "properties": { "seq": { "type": "integer" } } }
+        }
+    });
+    let (_, schema) = generate_file(
+        "subscription",
+        lexicon.clone(),
+        "com/example/subscribe_ticks.rs",
+    )
+    .unwrap();
+    let schema = schema.split_whitespace().collect::<String>();
+    assert!(schema.contains("pubenumMessage{"));
+    assert!(schema.contains("implesquema_client::subscription::MessageforMessage{"));
+    assert!(schema.contains("\"com.example.subscribeTicks#tick\"|\"#tick\"=>"));
+
+    let (_, client) = generate_file("subscription-client", lexicon, "client.rs").unwrap();
+    let client = client.split_whitespace().collect::<String>();
+    assert!(client.contains(
+        "pubasyncfnsubscribe_ticks(&self,params:crate::com::example::subscribe_ticks::Parameters"
+    ));
+    assert!(client.contains(
+        "esquema_client::subscription::Subscription<crate::com::example::subscribe_ticks::Message,crate::com::example::subscribe_ticks::Error"
+    ));
+}
+
+#[test]
+fn union_messages_decode_frame_types_and_keep_unknown_ones() {
+    let message = |closed: bool| {
+        json!({
+            "lexicon": 1,
+            "id": "com.example.subscribeEvents",
+            "defs": {
+                "main": {
+                    "type": "subscription",
+                    "message": {
+                        "schema": {
+                            "type": "union",
+                            "refs": ["#commit", "com.example.defs#ping"],
+                            "closed": closed
+                        }
+                    }
+                },
+                "commit": { "type": "object", "properties": { "seq": { "type": "integer" } } }
+            }
+        })
+    };
+    let (_, open) = generate_file(
+        "open-message",
+        message(false),
+        "com/example/subscribe_events.rs",
+    )
+    .unwrap();
+    let open = open.split_whitespace().collect::<String>();
+    for expected in [
+        "#[derive(serde::Serialize,serde::Deserialize,Debug,Clone,PartialEq,Eq)]pubenumMessage{Commit(Box<Commit>),ComExampleDefsPing(Box<crate::com::example::defs::Ping>),",
+        "Unknown{t:String,body:Vec<u8>},",
+        "\"com.example.subscribeEvents#commit\"|\"#commit\"=>",
+        "\"com.example.defs#ping\"=>",
+    ] {
+        assert!(open.contains(expected), "missing `{expected}` in:\n{open}");
+    }
+    assert!(!open.contains("tag=\"$type\""));
+
+    let (_, closed) = generate_file(
+        "closed-message",
+        message(true),
+        "com/example/subscribe_events.rs",
+    )
+    .unwrap();
+    let closed = closed.split_whitespace().collect::<String>();
+    assert!(!closed.contains("Unknown{"));
+    assert!(closed.contains("unknownmessagetype"));
+}
+
+#[test]
+fn cursor_paginated_queries_get_a_stream_method() {
+    let query = |output_cursor: bool| {
+        let mut output = json!({ "items": { "type": "array", "items": { "type": "string" } } });
+        if output_cursor {
+            output["cursor"] = json!({ "type": "string" });
+        }
+        json!({
+            "lexicon": 1,
+            "id": "com.example.listThings",
+            "defs": {
+                "main": {
+                    "type": "query",
+                    "parameters": {
+                        "type": "params",
+                        "properties": {
+                            "limit": { "type": "integer", "minimum": 1, "maximum": 100 },
+                            "cursor": { "type": "string" }
+                        }
+                    },
+                    "output": {
+                        "encoding": "application/json",
+                        "schema": { "type": "object", "required": ["items"], "properties": output }
+                    }
+                }
+            }
+        })
+    };
+    let (_, client) = generate_file("paginated", query(true), "client.rs").unwrap();
+    let client = client.split_whitespace().collect::<String>();
+    for expected in [
+        "pubfnlist_things_stream(&self,params:crate::com::example::list_things::Parameters",
+        "esquema_client::paginate::Pages<'_,crate::com::example::list_things::Output",
+        "move|params|self.list_things(params),|params,cursor|params.cursor=cursor,|output|output.cursor.clone()",
+    ] {
+        assert!(
+            client.contains(expected),
+            "missing `{expected}` in:\n{client}"
+        );
+    }
+
+    let (_, client) = generate_file("unpaginated", query(false), "client.rs").unwrap();
+    assert!(!client.contains("list_things_stream"));
+}
+
+#[test]
+fn server_generates_a_handler_trait_and_router() {
+    let docs = [
+        json!({
+            "lexicon": 1,
+            "id": "com.example.getThing",
+            "defs": {
+                "main": {
+                    "type": "query",
+                    "parameters": {
+                        "type": "params",
+                        "required": ["id"],
+                        "properties": { "id": { "type": "string" } }
+                    },
+                    "output": {
+                        "encoding": "application/json",
+                        "schema": { "type": "object", "properties": { "name": { "type": "string" } } }
+                    }
+                }
+            }
+        }),
+        json!({
+            "lexicon": 1,
+            "id": "com.example.putThing",
+            "defs": {
+                "main": {
+                    "type": "procedure",
+                    "input": {
+                        "encoding": "application/json",
+                        "schema": { "type": "object", "properties": { "name": { "type": "string" } } }
+                    }
+                }
+            }
+        }),
+    ]
+    .map(|doc| serde_json::from_value::<LexiconDoc>(doc).unwrap());
+    let dir = outdir("server");
+    let generated = Generator::builder()
+        .lexicons(docs)
+        .outdir(&dir)
+        .server(true)
+        .build()
+        .generate()
+        .map(|_| std::fs::read_to_string(dir.join("server.rs")).unwrap());
+    let lib = std::fs::read_to_string(dir.join("lib.rs")).unwrap_or_default();
+    std::fs::remove_dir_all(dir).unwrap();
+    let server = generated.unwrap().split_whitespace().collect::<String>();
+
+    assert!(lib.contains("pub mod server;"));
+    for expected in [
+        "pubtraitHandler:Send+Sync+'static{",
+        "fnget_thing(&self,params:crate::com::example::get_thing::Parameters",
+        "Output=core::result::Result<crate::com::example::get_thing::Output,esquema_server::Error<crate::com::example::get_thing::Error>",
+        "fnput_thing(&self,input:crate::com::example::put_thing::Input",
+        "Output=core::result::Result<(),esquema_server::Error<crate::com::example::put_thing::Error>",
+        "pubfnrouter<H:Handler>(handler:H)->esquema_server::axum::Router",
+        "\"/xrpc/com.example.getThing\",esquema_server::axum::routing::get(",
+        "esquema_server::Query(params):esquema_server::Query<crate::com::example::get_thing::ParametersData>",
+        "esquema_server::response::json(handler.get_thing(params.into()).await)",
+        "\"/xrpc/com.example.putThing\",esquema_server::axum::routing::post(",
+        "esquema_server::response::empty(handler.put_thing(input).await)",
+    ] {
+        assert!(
+            server.contains(expected),
+            "missing `{expected}` in:\n{server}"
+        );
+    }
+}
+
+#[test]
+fn server_is_off_by_default() {
+    let (paths, _) = generate_file(
+        "no-server",
+        json!({
+            "lexicon": 1,
+            "id": "com.example.getThing",
+            "defs": { "main": { "type": "query" } }
+        }),
+        "",
+    )
+    .unwrap();
+    assert!(!paths.iter().any(|path| path.ends_with("server.rs")));
+}
+
+#[test]
+fn record_conversions_are_fallible_unless_opted_out() {
+    let generate = |name: &str, infallible: bool| {
+        let doc: LexiconDoc = serde_json::from_value(json!({
+            "lexicon": 1,
+            "id": "com.example.thing",
+            "defs": {
+                "main": {
+                    "type": "record",
+                    "key": "tid",
+                    "record": {
+                        "type": "object",
+                        "required": ["name"],
+                        "properties": { "name": { "type": "string" } }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let dir = outdir(name);
+        Generator::builder()
+            .lexicons([doc])
+            .outdir(&dir)
+            .infallible_record_conversions(infallible)
+            .build()
+            .generate()
+            .unwrap();
+        let read = |file: &str| {
+            std::fs::read_to_string(dir.join(file))
+                .unwrap()
+                .split_whitespace()
+                .collect::<String>()
+        };
+        let files = (read("com/example/thing.rs"), read("record.rs"));
+        std::fs::remove_dir_all(dir).unwrap();
+        files
+    };
+
+    let (thing, record) = generate("fallible-records", false);
+    for expected in [
+        "implTryFrom<atrium_api::types::Unknown>forRecordData{typeError=esquema_validator::RecordError;",
+        "esquema_validator::record::from_unknown(Some(\"com.example.thing\"),&value)",
+    ] {
+        assert!(
+            thing.contains(expected),
+            "missing `{expected}` in:\n{thing}"
+        );
+    }
+    assert!(!thing.contains("unwrap"));
+    for expected in [
+        "implTryFrom<atrium_api::types::Unknown>forKnownRecord{",
+        "implTryFrom<KnownRecord>foratrium_api::types::Unknown{",
+    ] {
+        assert!(
+            record.contains(expected),
+            "missing `{expected}` in:\n{record}"
+        );
+    }
+
+    let (thing, record) = generate("infallible-records", true);
+    assert!(thing.contains("implFrom<atrium_api::types::Unknown>forRecordData{"));
+    assert!(!thing.contains("TryFrom"));
+    assert!(record.contains("implFrom<KnownRecord>foratrium_api::types::Unknown{"));
+    assert!(record.contains("implTryFrom<atrium_api::types::Unknown>forKnownRecord{"));
+}
+
+#[test]
+fn objects_get_a_validate_impl_checking_their_constraints() {
+    let (_, content) = generate_file(
+        "validate",
+        json!({
+            "lexicon": 1,
+            "id": "com.example.profile",
+            "defs": {
+                "main": {
+                    "type": "record",
+                    "key": "self",
+                    "record": {
+                        "type": "object",
+                        "required": ["handle"],
+                        "properties": {
+                            "handle": { "type": "string", "format": "handle", "maxLength": 64 },
+                            "note": { "type": "string", "maxLength": 300 },
+                            "link": { "type": "ref", "ref": "#link" },
+                            "tags": { "type": "array", "maxLength": 4, "items": { "type": "integer", "minimum": 0 } }
+                        }
+                    }
+                },
+                "link": { "type": "object", "properties": { "uri": { "type": "string", "format": "uri" } } }
+            }
+        }),
+        "com/example/profile.rs",
+    )
+    .unwrap();
+    let content = content.split_whitespace().collect::<String>();
+    for expected in [
+        "implesquema_validator::ValidateforRecordData{",
+        "letvalue=&self.handle;letvalue:&str=value.as_ref();esquema_validator::rules::max_length(&path,value.len(),64usize,violations);",
+        "esquema_validator::join_path(path,\"link\");ifletSome(value)=&self.link{esquema_validator::Validate::validate_at(value,&path,violations);}",
+        "ifletSome(value)=&self.note{letvalue:&str=value.as_str();esquema_validator::rules::max_length(&path,value.len(),300usize,violations);}",
+        "esquema_validator::rules::max_length(&path,value.len(),4usize,violations);for(i,value)invalue.iter().enumerate(){letpath=esquema_validator::join_path(&path,i);letn=esquema_validator::AsI64::as_i64(value);esquema_validator::rules::minimum(&path,n,0i64,violations);}",
+        // `uri` has no constraints past its format, which its type enforces
+        "implesquema_validator::ValidateforLinkData{#[allow(unused_variables)]fnvalidate_at(&self,path:&str,violations:&mutVec<esquema_validator::Violation>){}}",
+    ] {
+        assert!(
+            content.contains(expected),
+            "missing `{expected}` in:\n{content}"
+        );
+    }
+}
+
+#[test]
+fn graphemes_are_checked_on_the_str() {
+    let (_, content) = generate_file(
+        "graphemes",
+        json!({
+            "lexicon": 1,
+            "id": "com.example.post",
+            "defs": {
+                "main": {
+                    "type": "record",
+                    "key": "tid",
+                    "record": {
+                        "type": "object",
+                        "required": ["text"],
+                        "properties": {
+                            "text": { "type": "string", "maxLength": 3000, "minGraphemes": 1, "maxGraphemes": 300 },
+                            "langs": { "type": "array", "items": { "type": "string", "maxGraphemes": 8 } }
+                        }
+                    }
+                }
+            }
+        }),
+        "com/example/post.rs",
+    )
+    .unwrap();
+    let content = content.split_whitespace().collect::<String>();
+    for expected in [
+        "letvalue=&self.text;letvalue:&str=value.as_str();esquema_validator::rules::max_length(&path,value.len(),3000usize,violations);esquema_validator::rules::min_graphemes(&path,value,1usize,violations);esquema_validator::rules::max_graphemes(&path,value,300usize,violations);",
+        "letpath=esquema_validator::join_path(&path,i);letvalue:&str=value.as_str();esquema_validator::rules::max_graphemes(&path,value,8usize,violations);",
+    ] {
+        assert!(
+            content.contains(expected),
+            "missing `{expected}` in:\n{content}"
+        );
+    }
+}
+
+#[test]
+fn known_record_variants_are_named_after_their_module_path() {
+    let doc: LexiconDoc = serde_json::from_value(json!({
+        "lexicon": 1,
+        "id": "com.example.thing",
+        "defs": {
+            "main": {
+                "type": "record",
+                "key": "tid",
+                "record": { "type": "object", "properties": {} }
+            }
+        }
+    }))
+    .unwrap();
+    let dir = outdir("module-variants");
+    std::fs::create_dir_all(dir.join("lexicons")).unwrap();
+    Generator::builder()
+        .lexicons([doc])
+        .outdir(&dir)
+        .module("lexicons")
+        .build()
+        .generate()
+        .unwrap();
+    let record = std::fs::read_to_string(dir.join("lexicons/record.rs")).unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    let record = record.split_whitespace().collect::<String>();
+    assert!(
+        record
+            .contains("LexiconsComExampleThing(Box<crate::lexicons::com::example::thing::Record>)")
+    );
+    assert!(record.contains("KnownRecord::LexiconsComExampleThing(Box::new(record))"));
+}
+
+#[test]
+fn builder_options_choose_what_is_written() {
+    let lexicon = json!({
+        "lexicon": 1,
+        "id": "com.example.getThing",
+        "defs": {
+            "main": {
+                "type": "query",
+                "output": {
+                    "encoding": "application/json",
+                    "schema": { "type": "object", "properties": { "name": { "type": "string" } } }
+                }
+            }
+        }
+    });
+    let defaults = generate_with("default-options", lexicon.clone(), |builder| builder);
+    assert!(defaults.contains_key(Path::new("client.rs")));
+    assert!(defaults.contains_key(Path::new("record.rs")));
+    assert!(
+        defaults
+            .values()
+            .all(|content| content.starts_with("// @generated"))
+    );
+
+    let trimmed = generate_with("trimmed-options", lexicon, |builder| {
+        builder
+            .client(false)
+            .records(false)
+            .header("// custom header")
+            .format(false)
+    });
+    assert!(!trimmed.contains_key(Path::new("client.rs")));
+    assert!(!trimmed.contains_key(Path::new("record.rs")));
+    let lib = trimmed[Path::new("lib.rs")]
+        .split_whitespace()
+        .collect::<String>();
+    assert!(lib.contains("pubmodcom;"));
+    assert!(!lib.contains("pubmodclient;"));
+    assert!(!lib.contains("pubmodrecord;"));
+    for (path, content) in &trimmed {
+        // unformatted, the tokens of a file are written on a single line after the header
+        assert!(
+            content.starts_with("// custom header\n"),
+            "{}",
+            path.display()
+        );
+        assert_eq!(content.lines().count(), 2, "{}", path.display());
+    }
+}
+
+#[test]
+fn enums_skip_a_default_derive() {
+    let lexicon = json!({
+        "lexicon": 1,
+        "id": "com.example.post",
+        "defs": {
+            "main": {
+                "type": "record",
+                "key": "tid",
+                "record": {
+                    "type": "object",
+                    "required": ["visibility"],
+                    "properties": {
+                        "visibility": { "type": "string", "enum": ["public", "followers-only"] }
+                    }
+                }
+            }
+        }
+    });
+    let files = generate_with("enum-derives", lexicon, |builder| {
+        builder.derive("Hash").derive("Default")
+    });
+    let content = files[Path::new("com/example/post.rs")]
+        .split_whitespace()
+        .collect::<String>();
+    assert!(content.contains(
+        "#[derive(serde::Serialize,serde::Deserialize,Debug,Clone,PartialEq,Eq,Hash,Default)]pubstructRecordData{"
+    ));
+    assert!(content.contains(
+        "#[derive(serde::Serialize,serde::Deserialize,Debug,Clone,PartialEq,Eq,Hash)]#[serde(try_from=\"String\",into=\"String\")]pubenumRecordVisibility{"
+    ));
+    let record = files[Path::new("record.rs")]
+        .split_whitespace()
+        .collect::<String>();
+    assert!(record.contains(
+        "#[derive(serde::Serialize,serde::Deserialize,Debug,Clone,PartialEq,Eq,Hash)]#[serde(tag=\"$type\")]pubenumKnownRecord{"
+    ));
+}
+
+#[test]
+fn message_newtypes_skip_a_default_derive() {
+    let lexicon = json!({
+        "lexicon": 1,
+        "id": "com.example.subscribeEchoes",
+        "defs": {
+            "main": {
+                "type": "subscription",
+                "message": {
+                    "schema": {
+                        "type": "object",
+                        "required": ["text"],
+                        "properties": { "text": { "type": "string" } }
+                    }
+                }
+            }
+        }
+    });
+    let files = generate_with("message-derives", lexicon, |builder| {
+        builder.derive("Default")
+    });
+    let content = files[Path::new("com/example/subscribe_echoes.rs")]
+        .split_whitespace()
+        .collect::<String>();
+    assert!(content.contains(
+        "#[derive(serde::Serialize,serde::Deserialize,Debug,Clone,PartialEq,Eq)]#[serde(transparent)]pubstructMessage(pubMessageObject);"
+    ));
+}
+
+#[test]
+fn defaults_and_consts_outside_their_constraints_are_rejected() {
+    for (name, property, expected) in [
+        (
+            "default-maximum",
+            json!({ "type": "integer", "maximum": 9, "default": 20 }),
+            "defs.main.record.properties.value.default",
+        ),
+        (
+            "default-enum",
+            json!({ "type": "string", "enum": ["red", "blue"], "default": "green" }),
+            "defs.main.record.properties.value.default",
+        ),
+        (
+            "const-format",
+            json!({ "type": "string", "format": "datetime", "const": "yesterday" }),
+            "defs.main.record.properties.value.const",
+        ),
+    ] {
+        let err = generate(
+            name,
+            json!({
+                "lexicon": 1,
+                "id": "com.example.setting",
+                "defs": {
+                    "main": {
+                        "type": "record",
+                        "key": "tid",
+                        "record": { "type": "object", "properties": { "value": property } }
+                    }
+                }
+            }),
+        )
+        .unwrap_err();
+        let err = err.downcast_ref::<CodegenError>().unwrap();
+        assert!(matches!(err, CodegenError::InvalidValue { .. }), "{err}");
+        assert_eq!(err.nsid(), "com.example.setting");
+        assert_eq!(err.path(), Some(expected));
+    }
+}
+
+#[test]
+fn objects_with_defaults_implement_default_instead_of_deriving_it() {
+    let lexicon = json!({
+        "lexicon": 1,
+        "id": "com.example.settings",
+        "defs": {
+            "main": {
+                "type": "record",
+                "key": "tid",
+                "record": {
+                    "type": "object",
+                    "properties": {
+                        "lives": { "type": "integer", "minimum": 0, "maximum": 9, "default": 3 }
+                    }
+                }
+            },
+            "plain": {
+                "type": "object",
+                "properties": { "note": { "type": "string" } }
+            }
+        }
+    });
+    let files = generate_with("default-derive", lexicon, |builder| {
+        builder.derive("Default")
+    });
+    let content = files[Path::new("com/example/settings.rs")]
+        .split_whitespace()
+        .collect::<String>();
+    assert!(content.contains(
+        "#[derive(serde::Serialize,serde::Deserialize,Debug,Clone,PartialEq,Eq)]#[serde(rename_all=\"camelCase\")]pubstructRecordData{"
+    ));
+    assert!(content.contains(
+        "implDefaultforRecordData{fndefault()->Self{Self{lives:Self::default_lives()}}}"
+    ));
+    assert!(content.contains(
+        "#[derive(serde::Serialize,serde::Deserialize,Debug,Clone,PartialEq,Eq,Default)]#[serde(rename_all=\"camelCase\")]pubstructPlainData{"
+    ));
+}
+
+#[test]
+fn object_messages_are_wrapped_in_a_newtype() {
+    let (_, content) = generate_file(
+        "object-message",
+        json!({
+            "lexicon": 1,
+            "id": "com.example.subscribeEchoes",
+            "defs": {
+                "main": {
+                    "type": "subscription",
+                    "message": {
+                        "schema": {
+                            "type": "object",
+                            "required": ["text"],
+                            "properties": { "text": { "type": "string" } }
+                        }
+                    }
+                }
+            }
+        }),
+        "com/example/subscribe_echoes.rs",
+    )
+    .unwrap();
+    let content = content.split_whitespace().collect::<String>();
+    for expected in [
+        "pubstructMessageObjectData{pubtext:String,}",
+        "pubtypeMessageObject=atrium_api::types::Object<MessageObjectData>;",
+        "#[serde(transparent)]pubstructMessage(pubMessageObject);",
+        "implesquema_client::subscription::MessageforMessage{",
+        "esquema_client::subscription::decode_body(body).map(Self)",
+    ] {
+        assert!(
+            content.contains(expected),
+            "missing `{expected}` in:\n{content}"
+        );
+    }
+}
@@ -46,7 +46,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 collection: Status::NSID.parse()?,
                 repo: atrium_api::types::string::AtIdentifier::Did(session.did.clone()),
                 rkey: None,
-                record: status.into(),
+                record: status.try_into()?,
                 swap_commit: None,
                 validate: None,
             }
@@ -74,7 +74,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .await?;
 
     for record in result.records.clone() {
-        let data: RecordData = record.value.clone().into();
+        //A record that doesn't match the lexicon is an error naming the property, not a panic
+        let data = RecordData::try_from(record.value.clone())?;
         println!("record uri: {:?}", record.uri);
         println!(
             "atptools: {:?}",
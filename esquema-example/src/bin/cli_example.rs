@@ -1,16 +1,15 @@
 use atrium_api::{
     agent::atp_agent::{AtpAgent, store::MemorySessionStore},
-    types::{Collection, LimitedNonZeroU8, string::Datetime},
+    types::{
+        LimitedNonZeroU8,
+        string::{AtIdentifier, Datetime},
+    },
 };
 use atrium_xrpc_client::reqwest::ReqwestClient;
 use dotenv::dotenv;
-use esquema_example::lexicons::{
-    record::KnownRecord,
-    xyz::{
-        self,
-        statusphere::{Status, status::RecordData},
-    },
-};
+use esquema_client::record::RecordClient;
+use esquema_example::lexicons::xyz;
+use esquema_validator::Validate;
 
 //In you project you would uncomment this line for your main.rs
 //pub mod lexicons;
@@ -31,58 +30,34 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let session = agent.login(handle, password).await?;
 
-    let status: KnownRecord = xyz::statusphere::status::RecordData {
+    let status_data = xyz::statusphere::status::RecordData {
         created_at: Datetime::now(),
         status: "🦀".to_string(),
-    }
-    .into();
+    };
+    //Checks the record against the lexicon's constraints before sending it to the PDS
+    status_data.validate()?;
 
-    let create_result = agent
-        .api
-        .com
-        .atproto
-        .repo
-        .create_record(
-            atrium_api::com::atproto::repo::create_record::InputData {
-                collection: Status::NSID.parse()?,
-                repo: atrium_api::types::string::AtIdentifier::Did(session.did.clone()),
-                rkey: None,
-                record: status.into(),
-                swap_commit: None,
-                validate: None,
-            }
-            .into(),
-        )
-        .await?;
+    //A client for the records of one collection, taking and returning the generated types
+    let statuses = RecordClient::<xyz::statusphere::Status, _>::new(
+        &agent.api.com.atproto.repo,
+        AtIdentifier::Did(session.did.clone()),
+    );
 
-    println!("{:?}", create_result.clone());
+    let created = statuses.create(None, status_data).await?;
+    println!("{:?}", created);
 
-    let result = agent
-        .api
-        .com
-        .atproto
-        .repo
-        .list_records(
-            atrium_api::com::atproto::repo::list_records::ParametersData {
-                collection: Status::NSID.parse()?,
-                cursor: None,
-                limit: Some(LimitedNonZeroU8::try_from(3u8)?),
-                repo: atrium_api::types::string::AtIdentifier::Did(session.did.clone()),
-                reverse: None,
-            }
-            .into(),
-        )
+    let page = statuses
+        .list(None, Some(LimitedNonZeroU8::try_from(3u8)?))
         .await?;
 
-    for record in result.records.clone() {
-        let data: RecordData = record.value.clone().into();
+    for record in page.records {
         println!("record uri: {:?}", record.uri);
         println!(
             "atptools: {:?}",
             format!("https://atp.tools/{}", record.uri)
         );
-        println!("status: {:?}", data.status);
-        println!("created_at: {:?}\n", data.created_at);
+        println!("status: {:?}", record.value.status);
+        println!("created_at: {:?}\n", record.value.created_at);
     }
 
     Ok(())
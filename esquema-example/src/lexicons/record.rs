@@ -16,8 +16,17 @@ impl From<crate::lexicons::xyz::statusphere::status::RecordData> for KnownRecord
         KnownRecord::LexiconsXyzStatusphereStatus(Box::new(record_data.into()))
     }
 }
-impl Into<atrium_api::types::Unknown> for KnownRecord {
-    fn into(self) -> atrium_api::types::Unknown {
-        atrium_api::types::TryIntoUnknown::try_into_unknown(&self).unwrap()
+impl TryFrom<atrium_api::types::Unknown> for KnownRecord {
+    type Error = esquema_validator::RecordError;
+    fn try_from(
+        value: atrium_api::types::Unknown,
+    ) -> core::result::Result<Self, Self::Error> {
+        esquema_validator::record::from_unknown(None, &value)
+    }
+}
+impl TryFrom<KnownRecord> for atrium_api::types::Unknown {
+    type Error = esquema_validator::RecordError;
+    fn try_from(record: KnownRecord) -> core::result::Result<Self, Self::Error> {
+        esquema_validator::record::to_unknown(None, &record)
     }
 }
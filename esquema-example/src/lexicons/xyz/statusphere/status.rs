@@ -1,6 +1,5 @@
 // @generated - This file is generated by esquema-codegen (forked from atrium-codegen). DO NOT EDIT.
 //!Definitions for the `xyz.statusphere.status` namespace.
-use atrium_api::types::TryFromUnknown;
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct RecordData {
@@ -8,8 +7,24 @@ pub struct RecordData {
     pub status: String,
 }
 pub type Record = atrium_api::types::Object<RecordData>;
-impl From<atrium_api::types::Unknown> for RecordData {
-    fn from(value: atrium_api::types::Unknown) -> Self {
-        Self::try_from_unknown(value).unwrap()
+impl esquema_validator::Validate for RecordData {
+    #[allow(unused_variables)]
+    fn validate_at(&self, path: &str, violations: &mut Vec<esquema_validator::Violation>) {
+        {
+            let path = esquema_validator::join_path(path, "status");
+            let value = &self.status;
+            let value: &str = value.as_str();
+            esquema_validator::rules::min_length(&path, value.len(), 1usize, violations);
+            esquema_validator::rules::max_length(&path, value.len(), 32usize, violations);
+            esquema_validator::rules::max_graphemes(&path, value, 1usize, violations);
+        }
+    }
+}
+impl TryFrom<atrium_api::types::Unknown> for RecordData {
+    type Error = esquema_validator::RecordError;
+    fn try_from(
+        value: atrium_api::types::Unknown,
+    ) -> core::result::Result<Self, Self::Error> {
+        esquema_validator::record::from_unknown(Some("xyz.statusphere.status"), &value)
     }
 }
@@ -0,0 +1,21 @@
+use esquema_codegen::Generator;
+use std::path::PathBuf;
+
+fn main() {
+    println!("cargo:rerun-if-changed=lexicons");
+    let out_dir = PathBuf::from(
+        std::env::var("OUT_DIR").expect("OUT_DIR environment variable must be specified"),
+    );
+    // start over, so modules of lexicons removed since the last build aren't declared
+    let module = out_dir.join("lexicons");
+    let _ = std::fs::remove_dir_all(&module);
+    std::fs::create_dir_all(&module).unwrap();
+    Generator::builder()
+        .lexdir("lexicons")
+        .outdir(&out_dir)
+        .module("lexicons")
+        .server(true)
+        .build()
+        .generate()
+        .unwrap();
+}
@@ -0,0 +1,6 @@
+//! Lexicons generated by the [esquema-codegen](../esquema-codegen) of this workspace, for the tests to check that
+//! the output compiles and behaves at runtime.
+
+pub mod lexicons {
+    include!(concat!(env!("OUT_DIR"), "/lexicons/mod.rs"));
+}
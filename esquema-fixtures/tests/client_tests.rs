@@ -0,0 +1,119 @@
+use atrium_api::types::Union;
+use atrium_xrpc::{HttpClient, XrpcClient};
+use esquema_fixtures::lexicons::client::AtpServiceClient;
+use esquema_fixtures::lexicons::com::example::{get_shape, list_things, measure_shape, shape};
+use futures::StreamExt;
+use http::{Request, Response};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+
+/// Answers `com.example.listThings` with pages of `thing0` to `thing4`, the cursor being the
+/// index of the next thing, and the shape methods with a circle and the area of a square.
+struct Things;
+
+impl Things {
+    fn list(query: &HashMap<String, String>) -> Value {
+        let start = query
+            .get("cursor")
+            .map_or(0, |cursor| cursor.parse().unwrap());
+        let limit = query
+            .get("limit")
+            .map_or(50, |limit| limit.parse().unwrap());
+        let things = (start..5)
+            .take(limit)
+            .map(|i| format!("thing{i}"))
+            .collect::<Vec<_>>();
+        let next = start + things.len();
+        if next < 5 {
+            json!({ "things": things, "cursor": next.to_string() })
+        } else {
+            json!({ "things": things })
+        }
+    }
+}
+
+impl HttpClient for Things {
+    async fn send_http(
+        &self,
+        request: Request<Vec<u8>>,
+    ) -> Result<Response<Vec<u8>>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let nsid = request.uri().path().trim_start_matches("/xrpc/");
+        let query = serde_html_form::from_str(request.uri().query().unwrap_or_default())?;
+        let input = if request.body().is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice::<Value>(request.body())?
+        };
+        let (status, body) = match nsid {
+            "com.example.listThings" => (200, Self::list(&query)),
+            "com.example.getShape" => (
+                200,
+                json!({ "$type": "com.example.shape#circle", "radius": 2 }),
+            ),
+            "com.example.measureShape" if input["$type"] == "com.example.shape#square" => {
+                let side = input["side"].as_i64().unwrap_or_default();
+                (200, json!({ "area": side * side }))
+            }
+            "com.example.measureShape" => (
+                400,
+                json!({ "error": "InvalidRequest", "message": "only squares are measured" }),
+            ),
+            _ => (501, json!({ "error": "MethodNotImplemented" })),
+        };
+        Ok(Response::builder()
+            .status(status)
+            .header("content-type", "application/json")
+            .body(serde_json::to_vec(&body)?)?)
+    }
+}
+
+impl XrpcClient for Things {
+    fn base_uri(&self) -> String {
+        String::from("http://things")
+    }
+}
+
+#[tokio::test]
+async fn paginated_queries_stream_their_pages() {
+    let client = AtpServiceClient::new(Things);
+    let params = list_things::ParametersData {
+        cursor: None,
+        limit: Some(2u8.try_into().unwrap()),
+        reverse: None,
+    };
+    let pages = client
+        .service
+        .com
+        .example
+        .list_things_stream(params.into())
+        .map(|page| page.unwrap().data.things)
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(
+        pages,
+        [
+            vec!["thing0", "thing1"],
+            vec!["thing2", "thing3"],
+            vec!["thing4"]
+        ]
+    );
+}
+
+#[tokio::test]
+async fn union_bodies_are_sent_and_received_by_their_type() {
+    let client = AtpServiceClient::new(Things);
+    let example = &client.service.com.example;
+    match example.get_shape().await.unwrap() {
+        get_shape::OutputRefs::LexiconsComExampleShapeCircle(circle) => {
+            assert_eq!(circle.radius, 2)
+        }
+        other => panic!("expected a circle, got {other:?}"),
+    }
+
+    let square = shape::SquareData { side: 3 };
+    let input = Union::Refs(measure_shape::InputRefs::LexiconsComExampleShapeSquare(
+        Box::new(square.into()),
+    ));
+    let output = example.measure_shape(input).await.unwrap();
+    assert_eq!(output.area, 9);
+}
@@ -0,0 +1,74 @@
+use esquema_client::subscription::Message as _;
+use esquema_fixtures::lexicons::com::example::settings::{
+    RecordData, RecordLabelsItem, RecordVisibility,
+};
+use esquema_fixtures::lexicons::com::example::{subscribe_echoes, subscribe_ticks::Message};
+use serde_json::json;
+
+#[test]
+fn missing_defaults_and_consts_are_filled_in() {
+    let data: RecordData = serde_json::from_value(json!({ "name": "crab" })).unwrap();
+    assert_eq!(data, RecordData::new(String::from("crab")));
+    assert_eq!(
+        serde_json::to_value(&data).unwrap(),
+        json!({ "name": "crab", "lives": 3, "version": 2, "visibility": "public" })
+    );
+}
+
+#[test]
+fn consts_reject_other_values() {
+    let err =
+        serde_json::from_value::<RecordData>(json!({ "name": "crab", "version": 3 })).unwrap_err();
+    assert!(err.to_string().contains("`version` must be 2"), "{err}");
+}
+
+#[test]
+fn string_enums_round_trip() {
+    let data: RecordData = serde_json::from_value(json!({
+        "name": "crab",
+        "visibility": "followers-only",
+        "labels": ["spoiler", "gore"]
+    }))
+    .unwrap();
+    assert_eq!(data.visibility, RecordVisibility::FollowersOnly);
+    assert_eq!(
+        data.labels,
+        Some(vec![
+            RecordLabelsItem::Spoiler,
+            RecordLabelsItem::Other(String::from("gore"))
+        ])
+    );
+    let value = serde_json::to_value(&data).unwrap();
+    assert_eq!(value["visibility"], "followers-only");
+    assert_eq!(value["labels"], json!(["spoiler", "gore"]));
+
+    // `enum` is closed, unlike `knownValues`
+    assert!(
+        serde_json::from_value::<RecordData>(json!({ "name": "crab", "visibility": "private" }))
+            .is_err()
+    );
+}
+
+#[test]
+fn union_messages_decode_by_their_frame_type() {
+    let body = serde_ipld_dagcbor::to_vec(&json!({ "seq": 7 })).unwrap();
+    for t in ["#tick", "com.example.subscribeTicks#tick"] {
+        match Message::decode(Some(t), &body).unwrap() {
+            Message::Tick(tick) => assert_eq!(tick.seq, 7),
+            message => panic!("`{t}` decoded as {message:?}"),
+        }
+    }
+    assert!(matches!(
+        Message::decode(Some("#tock"), &body).unwrap(),
+        Message::Unknown { t, .. } if t == "#tock"
+    ));
+    assert!(Message::decode(None, &body).is_err());
+}
+
+#[test]
+fn object_messages_decode_whatever_the_frame_type() {
+    let body = serde_ipld_dagcbor::to_vec(&json!({ "text": "hello" })).unwrap();
+    let message = subscribe_echoes::Message::decode(None, &body).unwrap();
+    assert_eq!(message.0.text, "hello");
+    assert!(subscribe_echoes::Message::decode(None, &[]).is_err());
+}
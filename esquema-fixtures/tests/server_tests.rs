@@ -0,0 +1,105 @@
+use esquema_fixtures::lexicons::com::example::{create_thing, list_things};
+use esquema_fixtures::lexicons::server::{Handler, router};
+use esquema_server::Error;
+use esquema_server::axum::body::Body;
+use esquema_server::axum::http::{Request, StatusCode};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+struct Things;
+
+impl Handler for Things {
+    async fn list_things(
+        &self,
+        params: list_things::Parameters,
+    ) -> Result<list_things::Output, Error<list_things::Error>> {
+        let params = params.data;
+        let limit = params.limit.map_or(2, |limit| limit.get());
+        let mut things = (0..limit).map(|i| format!("thing{i}")).collect::<Vec<_>>();
+        if params.reverse == Some(true) {
+            things.reverse();
+        }
+        Ok(list_things::OutputData {
+            cursor: params.cursor,
+            things,
+        }
+        .into())
+    }
+
+    async fn create_thing(
+        &self,
+        input: create_thing::Input,
+    ) -> Result<create_thing::Output, Error<create_thing::Error>> {
+        if input.name == "taken" {
+            return Err(Error::Xrpc(create_thing::Error::ThingExists(None)));
+        }
+        let uri = format!("at://did:plc:fixture/com.example.thing/{}", input.name);
+        Ok(create_thing::OutputData {
+            uri: uri.parse().unwrap(),
+        }
+        .into())
+    }
+}
+
+async fn call(request: Request<Body>) -> (StatusCode, Value) {
+    let response = router(Things).oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = esquema_server::axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn list_things(query: &str) -> Request<Body> {
+    Request::get(format!("/xrpc/com.example.listThings?{query}"))
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn create_thing(input: Value) -> Request<Body> {
+    Request::post("/xrpc/com.example.createThing")
+        .header("content-type", "application/json")
+        .body(Body::from(input.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn integer_and_boolean_parameters_decode() {
+    assert_eq!(
+        call(list_things("limit=3&reverse=true&cursor=next")).await,
+        (
+            StatusCode::OK,
+            json!({ "cursor": "next", "things": ["thing2", "thing1", "thing0"] })
+        )
+    );
+    assert_eq!(
+        call(list_things("")).await,
+        (StatusCode::OK, json!({ "things": ["thing0", "thing1"] }))
+    );
+}
+
+#[tokio::test]
+async fn parameters_outside_their_type_are_invalid_requests() {
+    for query in ["limit=0", "limit=101", "limit=ten", "reverse=maybe"] {
+        let (status, body) = call(list_things(query)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{query}");
+        assert_eq!(body["error"], "InvalidRequest", "{query}");
+    }
+}
+
+#[tokio::test]
+async fn inputs_decode_and_lexicon_errors_are_sent() {
+    assert_eq!(
+        call(create_thing(json!({ "name": "crab" }))).await,
+        (
+            StatusCode::OK,
+            json!({ "uri": "at://did:plc:fixture/com.example.thing/crab" })
+        )
+    );
+    let (status, body) = call(create_thing(json!({ "name": "taken" }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "ThingExists");
+    let (status, body) = call(create_thing(json!({}))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "InvalidRequest");
+}
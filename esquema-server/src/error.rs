@@ -0,0 +1,57 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use serde_json::{Value, json};
+
+/// Why a method failed, sent as the standard `{error, message}` JSON body.
+#[derive(Debug)]
+pub enum Error<E> {
+    /// One of the method's lexicon errors, sent with status 400
+    Xrpc(E),
+    /// The parameters or input aren't valid for the method
+    InvalidRequest(String),
+    AuthRequired(String),
+    /// The handler doesn't implement the method
+    NotImplemented,
+    Internal(String),
+}
+
+impl<E> Error<E> {
+    fn status(&self) -> StatusCode {
+        match self {
+            Error::Xrpc(_) | Error::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            Error::AuthRequired(_) => StatusCode::UNAUTHORIZED,
+            Error::NotImplemented => StatusCode::NOT_IMPLEMENTED,
+            Error::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl<E: Serialize> IntoResponse for Error<E> {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let body = match self {
+            // the generated error enums serialize as `{"error": <name>, "message": <message>}`
+            Error::Xrpc(error) => serde_json::to_value(error).unwrap_or_else(
+                |err| json!({ "error": "InternalServerError", "message": err.to_string() }),
+            ),
+            Error::InvalidRequest(message) => {
+                json!({ "error": "InvalidRequest", "message": message })
+            }
+            Error::AuthRequired(message) => json!({ "error": "AuthRequired", "message": message }),
+            Error::NotImplemented => json!({ "error": "MethodNotImplemented" }),
+            Error::Internal(message) => {
+                json!({ "error": "InternalServerError", "message": message })
+            }
+        };
+        (status, axum::Json(without_null_message(body))).into_response()
+    }
+}
+
+/// Drops a `message` of `null`, lexicon errors without one serialize it that way.
+fn without_null_message(mut body: Value) -> Value {
+    if let Value::Object(map) = &mut body {
+        map.retain(|key, value| key != "message" || !value.is_null());
+    }
+    body
+}
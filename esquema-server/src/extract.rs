@@ -0,0 +1,54 @@
+use crate::error::Error;
+use axum::body::Bytes;
+use axum::extract::{FromRequest, FromRequestParts, Request};
+use axum::http::request::Parts;
+use axum::response::{IntoResponse, Response};
+use serde::de::DeserializeOwned;
+
+/// The parameters of a query string, with repeated keys for arrays like atrium sends them.
+///
+/// Rejects with an `InvalidRequest` XRPC error. Decode a generated `ParametersData` rather than
+/// its `Parameters` object, whose flattened fields would only see strings.
+#[derive(Debug, Clone)]
+pub struct Query<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for Query<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        serde_html_form::from_str(parts.uri.query().unwrap_or_default())
+            .map(Query)
+            .map_err(|err| invalid_request(format!("invalid parameters: {err}")))
+    }
+}
+
+/// A JSON input body.
+///
+/// Rejects with an `InvalidRequest` XRPC error.
+#[derive(Debug, Clone)]
+pub struct Json<T>(pub T);
+
+impl<T, S> FromRequest<S> for Json<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let body = Bytes::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        serde_json::from_slice(&body)
+            .map(Json)
+            .map_err(|err| invalid_request(format!("invalid input: {err}")))
+    }
+}
+
+fn invalid_request(message: String) -> Response {
+    Error::<()>::InvalidRequest(message).into_response()
+}
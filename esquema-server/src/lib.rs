@@ -0,0 +1,13 @@
+//! Runtime support for the XRPC servers esquema generates, on top of axum.
+
+mod error;
+mod extract;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod response;
+
+/// The axum the generated `router` is built with, so generated code doesn't need its own dependency on a matching
+/// version.
+pub use axum;
+pub use error::Error;
+pub use extract::{Json, Query};
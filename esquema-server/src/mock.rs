@@ -0,0 +1,224 @@
+//! A server for any set of lexicons without writing a handler, for testing the code calling them.
+//!
+//! Every query and procedure is answered: parameters and JSON inputs are validated against their
+//! lexicon, and the output is either one given with [`MockServer::output`] or the smallest value
+//! its schema allows. `com.atproto.repo.*` record methods are backed by an in-memory store, so
+//! records created through the server can be read and listed again.
+
+mod example;
+mod store;
+
+use crate::error::Error;
+use crate::response;
+use atrium_lex::LexiconDoc;
+use atrium_lex::lexicon::*;
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{Method, Uri};
+use axum::response::{IntoResponse, Response};
+use esquema_validator::{LexiconRegistry, ValidationError, Validator};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use store::Repos;
+
+/// Serves the queries and procedures of a set of lexicons, see the [module docs](self).
+pub struct MockServer {
+    validator: Validator,
+    outputs: HashMap<String, Value>,
+    repos: Mutex<Repos>,
+}
+
+impl MockServer {
+    pub fn new(docs: Vec<LexiconDoc>) -> Self {
+        Self::from_registry(LexiconRegistry::from_docs(docs))
+    }
+
+    pub fn from_registry(registry: LexiconRegistry) -> Self {
+        Self {
+            validator: Validator::from_registry(registry),
+            outputs: HashMap::new(),
+            repos: Mutex::new(Repos::default()),
+        }
+    }
+
+    /// Answers `nsid` with `output` instead of a value generated from its schema, once its
+    /// parameters or input are valid.
+    pub fn output(mut self, nsid: impl Into<String>, output: Value) -> Self {
+        self.outputs.insert(nsid.into(), output);
+        self
+    }
+
+    /// Serves every method at `/xrpc/<nsid>`.
+    pub fn router(self) -> axum::Router {
+        axum::Router::new()
+            .route("/xrpc/{nsid}", axum::routing::any(handle))
+            .with_state(Arc::new(self))
+    }
+
+    fn call(&self, nsid: &str, method: &Method, query: &str, body: &[u8]) -> Response {
+        let def = self
+            .validator
+            .registry()
+            .get(nsid)
+            .and_then(|doc| doc.defs.get("main"));
+        let (expected, output) = match def {
+            Some(LexUserType::XrpcQuery(query)) => (Method::GET, query.output.as_ref()),
+            Some(LexUserType::XrpcProcedure(procedure)) => {
+                (Method::POST, procedure.output.as_ref())
+            }
+            _ => match store::method(nsid) {
+                Some(expected) => (expected, None),
+                None => return Error::<()>::NotImplemented.into_response(),
+            },
+        };
+        if *method != expected {
+            return invalid_request(format!("`{nsid}` is served over {expected}"));
+        }
+        let checked = match def {
+            Some(LexUserType::XrpcQuery(lex_query)) => {
+                let parameters = if let Some(LexXrpcQueryParameter::Params(parameters)) =
+                    &lex_query.parameters
+                {
+                    Some(parameters)
+                } else {
+                    None
+                };
+                self.check_parameters(nsid, parameters, query)
+            }
+            Some(LexUserType::XrpcProcedure(procedure)) => {
+                let parameters = if let Some(LexXrpcProcedureParameter::Params(parameters)) =
+                    &procedure.parameters
+                {
+                    Some(parameters)
+                } else {
+                    None
+                };
+                self.check_parameters(nsid, parameters, query)
+                    .and_then(|()| match &procedure.input {
+                        Some(input) if input.encoding == "application/json" => {
+                            serde_json::from_slice::<Value>(body)
+                                .map_err(|err| {
+                                    Error::InvalidRequest(format!("invalid input: {err}"))
+                                })
+                                .and_then(|input| {
+                                    self.validator
+                                        .validate_input(nsid, &input)
+                                        .map_err(|err| invalid(&err))
+                                })
+                        }
+                        _ => Ok(()),
+                    })
+            }
+            _ => Ok(()),
+        };
+        if let Err(err) = checked {
+            return err.into_response();
+        }
+
+        if let Some(output) = self.outputs.get(nsid) {
+            return response::json::<_, ()>(Ok(output));
+        }
+        if store::method(nsid).is_some() {
+            let mut repos = self.repos.lock().unwrap_or_else(|err| err.into_inner());
+            return response::json(repos.call(nsid, query, body, &self.validator));
+        }
+        match output {
+            Some(LexXrpcBody {
+                schema: Some(schema),
+                ..
+            }) => {
+                response::json::<_, ()>(Ok(example::body(self.validator.registry(), nsid, schema)))
+            }
+            Some(output) => ([(CONTENT_TYPE, output.encoding.clone())], Vec::new()).into_response(),
+            None => response::empty::<()>(Ok(())),
+        }
+    }
+
+    fn check_parameters(
+        &self,
+        nsid: &str,
+        parameters: Option<&LexXrpcParameters>,
+        query: &str,
+    ) -> Result<(), Error<Value>> {
+        typed_parameters(parameters, query).and_then(|parameters| {
+            self.validator
+                .validate_parameters(nsid, &parameters)
+                .map_err(|err| invalid(&err))
+        })
+    }
+}
+
+async fn handle(
+    State(mock): State<Arc<MockServer>>,
+    Path(nsid): Path<String>,
+    method: Method,
+    uri: Uri,
+    body: Bytes,
+) -> Response {
+    mock.call(&nsid, &method, uri.query().unwrap_or_default(), &body)
+}
+
+/// The query string as the JSON object the parameters are validated as, parsing values as the
+/// types the lexicon gives them.
+///
+/// Values that don't parse stay strings, for validation to report them.
+fn typed_parameters(
+    parameters: Option<&LexXrpcParameters>,
+    query: &str,
+) -> Result<Value, Error<Value>> {
+    let pairs: Vec<(String, String)> = serde_html_form::from_str(query)
+        .map_err(|err| Error::InvalidRequest(format!("invalid parameters: {err}")))?;
+    let mut map = Map::new();
+    for (key, value) in pairs {
+        let property = parameters.and_then(|parameters| parameters.properties.get(&key));
+        let value = match property {
+            Some(LexXrpcParametersProperty::Array(array)) => {
+                let item = match &array.items {
+                    LexPrimitiveArrayItem::Boolean(_) => boolean(value),
+                    LexPrimitiveArrayItem::Integer(_) => integer(value),
+                    _ => Value::String(value),
+                };
+                if let Some(Value::Array(items)) = map.get_mut(&key) {
+                    items.push(item);
+                    continue;
+                }
+                Value::Array(vec![item])
+            }
+            Some(LexXrpcParametersProperty::Boolean(_)) => boolean(value),
+            Some(LexXrpcParametersProperty::Integer(_)) => integer(value),
+            _ => Value::String(value),
+        };
+        map.insert(key, value);
+    }
+    Ok(Value::Object(map))
+}
+
+fn boolean(value: String) -> Value {
+    match value.as_str() {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => Value::String(value),
+    }
+}
+
+fn integer(value: String) -> Value {
+    value
+        .parse::<i64>()
+        .map_or(Value::String(value), Value::from)
+}
+
+/// An `InvalidRequest` listing every violation.
+fn invalid(err: &ValidationError) -> Error<Value> {
+    let violations = err
+        .violations
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    Error::InvalidRequest(violations.join(", "))
+}
+
+fn invalid_request(message: String) -> Response {
+    Error::<()>::InvalidRequest(message).into_response()
+}
@@ -0,0 +1,191 @@
+//! The smallest values a lexicon schema allows, for outputs without a canned one.
+
+use super::store::cid;
+use atrium_lex::lexicon::*;
+use esquema_validator::{LexiconRegistry, qualify};
+use serde_json::{Map, Value, json};
+
+/// Deeper than any lexicon nests, only reached by a cycle of required refs.
+const MAX_DEPTH: usize = 32;
+
+/// A value for an XRPC body `schema` of `nsid`.
+pub(super) fn body(registry: &LexiconRegistry, nsid: &str, schema: &LexXrpcBodySchema) -> Value {
+    let example = Example { registry };
+    match schema {
+        LexXrpcBodySchema::Ref(r#ref) => example.r#ref(nsid, &r#ref.r#ref, 0),
+        LexXrpcBodySchema::Union(union) => example.union(nsid, union, 0),
+        LexXrpcBodySchema::Object(object) => example.object(nsid, object, 0),
+    }
+}
+
+struct Example<'a> {
+    registry: &'a LexiconRegistry,
+}
+
+impl Example<'_> {
+    fn user_type(&self, nsid: &str, def: &LexUserType, depth: usize) -> Value {
+        match def {
+            LexUserType::Record(record) => {
+                let LexRecordRecord::Object(object) = &record.record;
+                self.object(nsid, object, depth)
+            }
+            LexUserType::Object(object) => self.object(nsid, object, depth),
+            LexUserType::Array(array) => self.array(nsid, array, depth),
+            LexUserType::Blob(blob) => self.blob(blob),
+            LexUserType::Boolean(boolean) => self.boolean(boolean),
+            LexUserType::Integer(integer) => self.integer(integer),
+            LexUserType::String(string) => self.string(string),
+            LexUserType::Bytes(bytes) => self.bytes(bytes),
+            LexUserType::CidLink(_) => self.cid_link(),
+            LexUserType::Unknown(_) => json!({}),
+            // tokens and XRPC definitions do not describe data values
+            _ => Value::Null,
+        }
+    }
+
+    /// Only the required properties, optional ones are left out.
+    fn object(&self, nsid: &str, object: &LexObject, depth: usize) -> Value {
+        let mut map = Map::new();
+        for key in object.required.iter().flatten() {
+            if let Some(property) = object.properties.get(key) {
+                map.insert(key.clone(), self.property(nsid, property, depth));
+            }
+        }
+        Value::Object(map)
+    }
+
+    fn property(&self, nsid: &str, property: &LexObjectProperty, depth: usize) -> Value {
+        match property {
+            LexObjectProperty::Ref(r#ref) => self.r#ref(nsid, &r#ref.r#ref, depth),
+            LexObjectProperty::Union(union) => self.union(nsid, union, depth),
+            LexObjectProperty::Bytes(bytes) => self.bytes(bytes),
+            LexObjectProperty::CidLink(_) => self.cid_link(),
+            LexObjectProperty::Array(array) => self.array(nsid, array, depth),
+            LexObjectProperty::Blob(blob) => self.blob(blob),
+            LexObjectProperty::Boolean(boolean) => self.boolean(boolean),
+            LexObjectProperty::Integer(integer) => self.integer(integer),
+            LexObjectProperty::String(string) => self.string(string),
+            LexObjectProperty::Unknown(_) => json!({}),
+        }
+    }
+
+    fn array(&self, nsid: &str, array: &LexArray, depth: usize) -> Value {
+        let items = (0..array.min_length.unwrap_or_default())
+            .map(|_| match &array.items {
+                LexArrayItem::Boolean(boolean) => self.boolean(boolean),
+                LexArrayItem::Integer(integer) => self.integer(integer),
+                LexArrayItem::String(string) => self.string(string),
+                LexArrayItem::Unknown(_) => json!({}),
+                LexArrayItem::Bytes(bytes) => self.bytes(bytes),
+                LexArrayItem::CidLink(_) => self.cid_link(),
+                LexArrayItem::Blob(blob) => self.blob(blob),
+                LexArrayItem::Ref(r#ref) => self.r#ref(nsid, &r#ref.r#ref, depth),
+                LexArrayItem::Union(union) => self.union(nsid, union, depth),
+            })
+            .collect();
+        Value::Array(items)
+    }
+
+    fn r#ref(&self, nsid: &str, r#ref: &str, depth: usize) -> Value {
+        if depth >= MAX_DEPTH {
+            return Value::Null;
+        }
+        match self.registry.resolve(nsid, r#ref) {
+            Some(resolved) => self.user_type(resolved.nsid, resolved.def, depth + 1),
+            None => Value::Null,
+        }
+    }
+
+    /// The first member, with its `$type`.
+    fn union(&self, nsid: &str, union: &LexRefUnion, depth: usize) -> Value {
+        let Some(r#ref) = union.refs.first() else {
+            return json!({});
+        };
+        let mut value = self.r#ref(nsid, r#ref, depth);
+        if let Value::Object(map) = &mut value {
+            let r#type = qualify(nsid, r#ref);
+            let r#type = r#type.strip_suffix("#main").unwrap_or(&r#type);
+            map.insert(String::from("$type"), Value::from(r#type));
+        }
+        value
+    }
+
+    fn boolean(&self, boolean: &LexBoolean) -> Value {
+        Value::Bool(boolean.r#const.or(boolean.default).unwrap_or_default())
+    }
+
+    fn integer(&self, integer: &LexInteger) -> Value {
+        let value = integer
+            .r#const
+            .or(integer.default)
+            .or_else(|| integer.r#enum.as_ref()?.first().copied())
+            .unwrap_or_else(|| {
+                let closest_to_zero = integer.minimum.map_or(0, |min| min.max(0));
+                closest_to_zero.min(integer.maximum.unwrap_or(i64::MAX))
+            });
+        Value::from(value)
+    }
+
+    fn string(&self, string: &LexString) -> Value {
+        if let Some(value) = string
+            .r#const
+            .as_ref()
+            .or(string.default.as_ref())
+            .or_else(|| string.r#enum.as_ref()?.first())
+            .or_else(|| string.known_values.as_ref()?.first())
+        {
+            return Value::from(value.as_str());
+        }
+        if let Some(format) = &string.format {
+            return Value::from(formatted(format));
+        }
+        let length = string
+            .min_length
+            .unwrap_or_default()
+            .max(string.min_graphemes.unwrap_or_default());
+        Value::from("x".repeat(length))
+    }
+
+    fn bytes(&self, bytes: &LexBytes) -> Value {
+        // every 3 bytes take 4 base64 characters, `A` being a zero
+        let length = bytes.min_length.unwrap_or_default().div_ceil(3) * 4;
+        json!({ "$bytes": "A".repeat(length) })
+    }
+
+    fn cid_link(&self) -> Value {
+        json!({ "$link": cid(&Value::Null) })
+    }
+
+    fn blob(&self, blob: &LexBlob) -> Value {
+        let mime_type = blob
+            .accept
+            .as_ref()
+            .and_then(|accept| accept.first())
+            // a wildcard such as `image/*` isn't a mime type itself
+            .map(|accept| accept.replace('*', "mock"))
+            .unwrap_or_else(|| String::from("application/octet-stream"));
+        json!({
+            "$type": "blob",
+            "ref": self.cid_link(),
+            "mimeType": mime_type,
+            "size": 0
+        })
+    }
+}
+
+/// A fixed value in each string format.
+fn formatted(format: &LexStringFormat) -> String {
+    let value = match format {
+        LexStringFormat::AtIdentifier | LexStringFormat::Did => "did:plc:mockmockmockmockmockmock",
+        LexStringFormat::AtUri => "at://did:plc:mockmockmockmockmockmock/com.example.mock/self",
+        LexStringFormat::Cid => return cid(&Value::Null),
+        LexStringFormat::Datetime => "1970-01-01T00:00:00.000Z",
+        LexStringFormat::Handle => "mock.test",
+        LexStringFormat::Language => "en",
+        LexStringFormat::Nsid => "com.example.mock",
+        LexStringFormat::RecordKey => "self",
+        LexStringFormat::Tid => "2222222222222",
+        LexStringFormat::Uri => "https://example.com",
+    };
+    value.to_string()
+}
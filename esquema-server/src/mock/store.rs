@@ -0,0 +1,267 @@
+//! An in-memory `com.atproto.repo`, enough for records to round-trip.
+
+use crate::error::Error;
+use atrium_lex::lexicon::LexStringFormat;
+use axum::http::Method;
+use esquema_validator::{Validator, format};
+use ipld_core::cid::Cid;
+use ipld_core::cid::multihash::Multihash;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The HTTP method of a `com.atproto.repo` method the store answers.
+pub(super) fn method(nsid: &str) -> Option<Method> {
+    match nsid {
+        "com.atproto.repo.getRecord" | "com.atproto.repo.listRecords" => Some(Method::GET),
+        "com.atproto.repo.createRecord"
+        | "com.atproto.repo.putRecord"
+        | "com.atproto.repo.deleteRecord" => Some(Method::POST),
+        _ => None,
+    }
+}
+
+/// The CID of a raw block of `value`'s JSON, stable for the same content, but not the DAG-CBOR
+/// CID a PDS would give it.
+pub(super) fn cid(value: &Value) -> String {
+    let json = serde_json::to_vec(value).unwrap_or_default();
+    let digest = Multihash::<64>::wrap(0x12, &Sha256::digest(json))
+        .expect("a sha-256 digest fits in a multihash");
+    Cid::new_v1(0x55, digest).to_string()
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Repos {
+    /// `(repo, collection)` to the records by their key
+    collections: BTreeMap<(String, String), BTreeMap<String, Stored>>,
+    last_tid: u64,
+}
+
+#[derive(Debug)]
+struct Stored {
+    cid: String,
+    value: Value,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WriteInput {
+    repo: String,
+    collection: String,
+    rkey: Option<String>,
+    validate: Option<bool>,
+    record: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct GetParams {
+    repo: String,
+    collection: String,
+    rkey: String,
+}
+
+#[derive(Deserialize)]
+struct ListParams {
+    repo: String,
+    collection: String,
+    limit: Option<usize>,
+    cursor: Option<String>,
+    reverse: Option<bool>,
+}
+
+impl Repos {
+    /// Answers a `com.atproto.repo` method, see [`method`].
+    pub(super) fn call(
+        &mut self,
+        nsid: &str,
+        query: &str,
+        body: &[u8],
+        validator: &Validator,
+    ) -> Result<Value, Error<Value>> {
+        match nsid {
+            "com.atproto.repo.getRecord" => self.get(parameters(query)?),
+            "com.atproto.repo.listRecords" => self.list(parameters(query)?),
+            "com.atproto.repo.createRecord" => self.write(input(body)?, false, validator),
+            "com.atproto.repo.putRecord" => self.write(input(body)?, true, validator),
+            "com.atproto.repo.deleteRecord" => self.delete(input(body)?),
+            _ => Err(Error::NotImplemented),
+        }
+    }
+
+    fn write(
+        &mut self,
+        input: WriteInput,
+        replace: bool,
+        validator: &Validator,
+    ) -> Result<Value, Error<Value>> {
+        check(&LexStringFormat::AtIdentifier, "repo", &input.repo)?;
+        check(&LexStringFormat::Nsid, "collection", &input.collection)?;
+        let Some(Value::Object(mut record)) = input.record else {
+            return Err(Error::InvalidRequest(String::from(
+                "`record` must be an object",
+            )));
+        };
+        record
+            .entry("$type")
+            .or_insert_with(|| Value::from(input.collection.as_str()));
+        let record = Value::Object(record);
+
+        let known = validator.registry().contains(&input.collection);
+        if input.validate == Some(true) && !known {
+            return Err(Error::InvalidRequest(format!(
+                "no lexicon for `{}` to validate the record against",
+                input.collection
+            )));
+        }
+        let validation_status = if known && input.validate != Some(false) {
+            validator
+                .validate(&input.collection, &record)
+                .map_err(|err| super::invalid(&err))?;
+            "valid"
+        } else {
+            "unknown"
+        };
+
+        let rkey = match input.rkey {
+            Some(rkey) => {
+                check(&LexStringFormat::RecordKey, "rkey", &rkey)?;
+                rkey
+            }
+            None if replace => {
+                return Err(Error::InvalidRequest(String::from("`rkey` is required")));
+            }
+            None => self.next_tid(),
+        };
+        let records = self
+            .collections
+            .entry((input.repo.clone(), input.collection.clone()))
+            .or_default();
+        if !replace && records.contains_key(&rkey) {
+            return Err(Error::InvalidRequest(format!(
+                "record `{rkey}` already exists"
+            )));
+        }
+        let cid = cid(&record);
+        records.insert(
+            rkey.clone(),
+            Stored {
+                cid: cid.clone(),
+                value: record,
+            },
+        );
+        Ok(json!({
+            "uri": uri(&input.repo, &input.collection, &rkey),
+            "cid": cid,
+            "validationStatus": validation_status
+        }))
+    }
+
+    fn get(&self, params: GetParams) -> Result<Value, Error<Value>> {
+        let uri = uri(&params.repo, &params.collection, &params.rkey);
+        let stored = self
+            .collections
+            .get(&(params.repo, params.collection))
+            .and_then(|records| records.get(&params.rkey))
+            .ok_or_else(|| {
+                Error::Xrpc(json!({
+                    "error": "RecordNotFound",
+                    "message": format!("Could not locate record: {uri}")
+                }))
+            })?;
+        Ok(json!({
+            "uri": uri,
+            "cid": stored.cid,
+            "value": stored.value
+        }))
+    }
+
+    /// Newest first like a PDS, unless `reverse`, the cursor being the last key of a full page.
+    fn list(&self, params: ListParams) -> Result<Value, Error<Value>> {
+        let limit = params.limit.unwrap_or(50);
+        if !(1..=100).contains(&limit) {
+            return Err(Error::InvalidRequest(String::from(
+                "`limit` must be between 1 and 100",
+            )));
+        }
+        let empty = BTreeMap::new();
+        let records = self
+            .collections
+            .get(&(params.repo.clone(), params.collection.clone()))
+            .unwrap_or(&empty);
+        let cursor = params.cursor.as_deref();
+        let page: Vec<(&String, &Stored)> = if params.reverse.unwrap_or_default() {
+            records
+                .iter()
+                .filter(|(rkey, _)| cursor.is_none_or(|cursor| rkey.as_str() > cursor))
+                .take(limit)
+                .collect()
+        } else {
+            records
+                .iter()
+                .rev()
+                .filter(|(rkey, _)| cursor.is_none_or(|cursor| rkey.as_str() < cursor))
+                .take(limit)
+                .collect()
+        };
+        let cursor = (page.len() == limit).then(|| page[limit - 1].0.clone());
+        let records = page
+            .into_iter()
+            .map(|(rkey, stored)| {
+                json!({
+                    "uri": uri(&params.repo, &params.collection, rkey),
+                    "cid": stored.cid,
+                    "value": stored.value
+                })
+            })
+            .collect::<Vec<_>>();
+        Ok(match cursor {
+            Some(cursor) => json!({ "records": records, "cursor": cursor }),
+            None => json!({ "records": records }),
+        })
+    }
+
+    fn delete(&mut self, input: WriteInput) -> Result<Value, Error<Value>> {
+        let Some(rkey) = input.rkey else {
+            return Err(Error::InvalidRequest(String::from("`rkey` is required")));
+        };
+        if let Some(records) = self.collections.get_mut(&(input.repo, input.collection)) {
+            records.remove(&rkey);
+        }
+        Ok(json!({}))
+    }
+
+    /// A TID from the current time, after every one handed out before.
+    fn next_tid(&mut self) -> String {
+        const ALPHABET: &[u8] = b"234567abcdefghijklmnopqrstuvwxyz";
+        let micros = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_micros() as u64);
+        // the low 10 bits are the clock id, always 0 here
+        let tid = (micros << 10).max(self.last_tid + (1 << 10));
+        self.last_tid = tid;
+        (0..13)
+            .map(|i| ALPHABET[((tid >> (60 - 5 * i)) & 31) as usize] as char)
+            .collect()
+    }
+}
+
+fn uri(repo: &str, collection: &str, rkey: &str) -> String {
+    format!("at://{repo}/{collection}/{rkey}")
+}
+
+fn check(lex_format: &LexStringFormat, name: &str, value: &str) -> Result<(), Error<Value>> {
+    format::check(lex_format, value)
+        .map_err(|reason| Error::InvalidRequest(format!("`{name}` is {reason}")))
+}
+
+fn parameters<T: serde::de::DeserializeOwned>(query: &str) -> Result<T, Error<Value>> {
+    serde_html_form::from_str(query)
+        .map_err(|err| Error::InvalidRequest(format!("invalid parameters: {err}")))
+}
+
+fn input<T: serde::de::DeserializeOwned>(body: &[u8]) -> Result<T, Error<Value>> {
+    serde_json::from_slice(body)
+        .map_err(|err| Error::InvalidRequest(format!("invalid input: {err}")))
+}
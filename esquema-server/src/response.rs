@@ -0,0 +1,30 @@
+//! Responses for the results of generated handler methods.
+
+use crate::error::Error;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+
+/// A JSON output.
+pub fn json<O: Serialize, E: Serialize>(result: Result<O, Error<E>>) -> Response {
+    match result {
+        Ok(output) => axum::Json(output).into_response(),
+        Err(err) => err.into_response(),
+    }
+}
+
+/// An output of raw bytes, sent with the lexicon's `encoding`.
+pub fn bytes<E: Serialize>(encoding: &'static str, result: Result<Vec<u8>, Error<E>>) -> Response {
+    match result {
+        Ok(output) => ([(CONTENT_TYPE, encoding)], output).into_response(),
+        Err(err) => err.into_response(),
+    }
+}
+
+/// The empty response of a method without output.
+pub fn empty<E: Serialize>(result: Result<(), Error<E>>) -> Response {
+    match result {
+        Ok(()) => ().into_response(),
+        Err(err) => err.into_response(),
+    }
+}
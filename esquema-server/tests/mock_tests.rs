@@ -0,0 +1,261 @@
+use axum::Router;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use esquema_server::mock::MockServer;
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+fn lexicons() -> Vec<atrium_lex::LexiconDoc> {
+    [
+        json!({
+            "lexicon": 1,
+            "id": "com.example.thing",
+            "defs": {
+                "main": {
+                    "type": "record",
+                    "key": "tid",
+                    "record": {
+                        "type": "object",
+                        "required": ["name"],
+                        "properties": { "name": { "type": "string", "maxLength": 8 } }
+                    }
+                },
+                "view": {
+                    "type": "object",
+                    "required": ["name", "createdAt", "tags"],
+                    "properties": {
+                        "name": { "type": "string", "minLength": 3 },
+                        "createdAt": { "type": "string", "format": "datetime" },
+                        "tags": { "type": "array", "minLength": 1, "items": { "type": "string", "knownValues": ["crab"] } },
+                        "note": { "type": "string" }
+                    }
+                }
+            }
+        }),
+        json!({
+            "lexicon": 1,
+            "id": "com.example.getThing",
+            "defs": {
+                "main": {
+                    "type": "query",
+                    "parameters": {
+                        "type": "params",
+                        "required": ["limit"],
+                        "properties": { "limit": { "type": "integer", "minimum": 1, "maximum": 10 } }
+                    },
+                    "output": {
+                        "encoding": "application/json",
+                        "schema": { "type": "ref", "ref": "com.example.thing#view" }
+                    }
+                }
+            }
+        }),
+        json!({
+            "lexicon": 1,
+            "id": "com.example.putThing",
+            "defs": {
+                "main": {
+                    "type": "procedure",
+                    "parameters": {
+                        "type": "params",
+                        "properties": { "dryRun": { "type": "boolean" } }
+                    },
+                    "input": {
+                        "encoding": "application/json",
+                        "schema": {
+                            "type": "object",
+                            "required": ["name"],
+                            "properties": { "name": { "type": "string", "maxLength": 8 } }
+                        }
+                    }
+                }
+            }
+        }),
+    ]
+    .into_iter()
+    .map(|doc| serde_json::from_value(doc).unwrap())
+    .collect()
+}
+
+async fn call(router: &Router, request: Request<Body>) -> (StatusCode, Value) {
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn get(path: &str) -> Request<Body> {
+    Request::get(format!("/xrpc/{path}"))
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn post(nsid: &str, input: Value) -> Request<Body> {
+    Request::post(format!("/xrpc/{nsid}"))
+        .header("content-type", "application/json")
+        .body(Body::from(input.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn records_round_trip() {
+    let router = MockServer::new(lexicons()).router();
+    let repo = "did:plc:mockmockmockmockmockmock";
+    for name in ["first", "second"] {
+        let (status, created) = call(
+            &router,
+            post(
+                "com.atproto.repo.createRecord",
+                json!({ "repo": repo, "collection": "com.example.thing", "record": { "name": name } }),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{created}");
+        assert_eq!(created["validationStatus"], "valid");
+    }
+    let (status, _) = call(
+        &router,
+        post(
+            "com.atproto.repo.putRecord",
+            json!({ "repo": repo, "collection": "com.example.thing", "rkey": "self", "record": { "name": "third" } }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let list = format!("com.atproto.repo.listRecords?repo={repo}&collection=com.example.thing");
+    let (_, page) = call(&router, get(&format!("{list}&limit=2"))).await;
+    let names = |page: &Value| {
+        page["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|record| record["value"]["name"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+    // "self" sorts after the TIDs, so it is the newest
+    assert_eq!(names(&page), ["third", "second"]);
+    assert_eq!(page["records"][0]["value"]["$type"], "com.example.thing");
+    let cursor = page["cursor"].as_str().unwrap();
+    let (_, next) = call(&router, get(&format!("{list}&limit=2&cursor={cursor}"))).await;
+    assert_eq!(names(&next), ["first"]);
+    assert!(next.get("cursor").is_none());
+    let (_, reversed) = call(&router, get(&format!("{list}&reverse=true"))).await;
+    assert_eq!(names(&reversed), ["first", "second", "third"]);
+
+    let record =
+        format!("com.atproto.repo.getRecord?repo={repo}&collection=com.example.thing&rkey=self");
+    let (status, found) = call(&router, get(&record)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(found["value"]["name"], "third");
+    assert_eq!(found["uri"], format!("at://{repo}/com.example.thing/self"));
+
+    call(
+        &router,
+        post(
+            "com.atproto.repo.deleteRecord",
+            json!({ "repo": repo, "collection": "com.example.thing", "rkey": "self" }),
+        ),
+    )
+    .await;
+    let (status, missing) = call(&router, get(&record)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(missing["error"], "RecordNotFound");
+}
+
+#[tokio::test]
+async fn parameters_inputs_and_records_are_validated() {
+    let router = MockServer::new(lexicons()).router();
+
+    let (status, body) = call(&router, get("com.example.getThing?limit=11")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "InvalidRequest");
+    assert!(body["message"].as_str().unwrap().contains("/limit"));
+    let (status, _) = call(&router, get("com.example.getThing?limit=ten")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = call(
+        &router,
+        post("com.example.putThing", json!({ "name": "crab" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = call(
+        &router,
+        post(
+            "com.example.putThing",
+            json!({ "name": "a very long name" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"].as_str().unwrap().contains("/name"));
+    let (status, _) = call(
+        &router,
+        post(
+            "com.example.putThing?dryRun=true",
+            json!({ "name": "crab" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = call(
+        &router,
+        post(
+            "com.example.putThing?dryRun=maybe",
+            json!({ "name": "crab" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"].as_str().unwrap().contains("/dryRun"));
+
+    let (status, _) = call(
+        &router,
+        post(
+            "com.atproto.repo.createRecord",
+            json!({ "repo": "mock.test", "collection": "com.example.thing", "record": { "name": "a very long name" } }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn outputs_are_generated_from_the_schema_or_canned() {
+    let (status, output) = call(
+        &MockServer::new(lexicons()).router(),
+        get("com.example.getThing?limit=1"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        output,
+        json!({ "name": "xxx", "createdAt": "1970-01-01T00:00:00.000Z", "tags": ["crab"] })
+    );
+
+    let canned = json!({ "name": "canned", "createdAt": "2025-01-01T00:00:00.000Z", "tags": [] });
+    let router = MockServer::new(lexicons())
+        .output("com.example.getThing", canned.clone())
+        .router();
+    assert_eq!(
+        call(&router, get("com.example.getThing?limit=1")).await,
+        (StatusCode::OK, canned)
+    );
+}
+
+#[tokio::test]
+async fn unknown_methods_are_not_implemented() {
+    let router = MockServer::new(lexicons()).router();
+    assert_eq!(
+        call(&router, get("com.example.missing")).await,
+        (
+            StatusCode::NOT_IMPLEMENTED,
+            json!({ "error": "MethodNotImplemented" })
+        )
+    );
+    let (status, _) = call(&router, post("com.example.getThing", json!({}))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,108 @@
+use axum::Router;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::routing::{get, post};
+use esquema_server::{Error, Json, Query, response};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+/// What is generated for a lexicon with a `ThingNotFound` error.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "error", content = "message")]
+enum ThingError {
+    ThingNotFound(Option<String>),
+}
+
+#[derive(Deserialize)]
+struct Params {
+    #[serde(default)]
+    tags: Vec<String>,
+    #[allow(dead_code)]
+    limit: Option<u8>,
+}
+
+fn router() -> Router {
+    Router::new()
+        .route(
+            "/xrpc/com.example.getThing",
+            get(|Query(params): Query<Params>| async move {
+                response::json(if params.tags.is_empty() {
+                    Err(Error::Xrpc(ThingError::ThingNotFound(Some(String::from(
+                        "no tags",
+                    )))))
+                } else {
+                    Ok(json!({ "tags": params.tags }))
+                })
+            }),
+        )
+        .route(
+            "/xrpc/com.example.putThing",
+            post(|Json(_input): Json<Value>| async move {
+                response::empty::<ThingError>(Err(Error::NotImplemented))
+            }),
+        )
+}
+
+async fn call(request: Request<Body>) -> (StatusCode, Value) {
+    let response = router().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn get_thing(query: &str) -> Request<Body> {
+    Request::get(format!("/xrpc/com.example.getThing?{query}"))
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn put_thing(body: &str) -> Request<Body> {
+    Request::post("/xrpc/com.example.putThing")
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn query_strings_decode_repeated_keys_as_arrays() {
+    assert_eq!(
+        call(get_thing("tags=a&tags=b")).await,
+        (StatusCode::OK, json!({ "tags": ["a", "b"] }))
+    );
+}
+
+#[tokio::test]
+async fn lexicon_errors_are_sent_as_error_and_message() {
+    assert_eq!(
+        call(get_thing("")).await,
+        (
+            StatusCode::BAD_REQUEST,
+            json!({ "error": "ThingNotFound", "message": "no tags" })
+        )
+    );
+}
+
+#[tokio::test]
+async fn invalid_parameters_and_inputs_are_invalid_requests() {
+    let (status, body) = call(get_thing("limit=many")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "InvalidRequest");
+
+    let (status, body) = call(put_thing("{")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "InvalidRequest");
+}
+
+#[tokio::test]
+async fn unimplemented_methods_are_501() {
+    assert_eq!(
+        call(put_thing("{}")).await,
+        (
+            StatusCode::NOT_IMPLEMENTED,
+            json!({ "error": "MethodNotImplemented" })
+        )
+    );
+}
@@ -0,0 +1,57 @@
+//! Syntax checks for the lexicon string formats.
+
+use crate::types::{AtUri, Uri};
+use atrium_api::types::string::{
+    AtIdentifier, Cid, Datetime, Did, Handle, Language, Nsid, RecordKey, Tid,
+};
+use atrium_lex::lexicon::LexStringFormat;
+
+/// Checks that `value` is valid in the given lexicon string format.
+pub fn check(format: &LexStringFormat, value: &str) -> Result<(), &'static str> {
+    let valid = match format {
+        LexStringFormat::AtIdentifier => value.parse::<AtIdentifier>().is_ok(),
+        LexStringFormat::AtUri => return AtUri::new(value.to_string()).map(|_| ()),
+        LexStringFormat::Cid => value.parse::<Cid>().is_ok(),
+        LexStringFormat::Datetime => value.parse::<Datetime>().is_ok(),
+        LexStringFormat::Did => value.parse::<Did>().is_ok(),
+        LexStringFormat::Handle => value.parse::<Handle>().is_ok(),
+        LexStringFormat::Language => value.parse::<Language>().is_ok(),
+        LexStringFormat::Nsid => value.parse::<Nsid>().is_ok(),
+        LexStringFormat::RecordKey => value.parse::<RecordKey>().is_ok(),
+        LexStringFormat::Tid => value.parse::<Tid>().is_ok(),
+        LexStringFormat::Uri => return Uri::new(value.to_string()).map(|_| ()),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(match format {
+            LexStringFormat::AtIdentifier => "not a valid DID or handle",
+            LexStringFormat::Cid => "not a valid CID",
+            LexStringFormat::Datetime => "not a valid datetime",
+            LexStringFormat::Did => "not a valid DID",
+            LexStringFormat::Handle => "not a valid handle",
+            LexStringFormat::Language => "not a valid BCP-47 language tag",
+            LexStringFormat::Nsid => "not a valid NSID",
+            LexStringFormat::RecordKey => "not a valid record key",
+            LexStringFormat::Tid => "not a valid TID",
+            _ => "invalid format",
+        })
+    }
+}
+
+/// The name of a format as written in lexicon JSON.
+pub fn name(format: &LexStringFormat) -> &'static str {
+    match format {
+        LexStringFormat::AtIdentifier => "at-identifier",
+        LexStringFormat::AtUri => "at-uri",
+        LexStringFormat::Cid => "cid",
+        LexStringFormat::Datetime => "datetime",
+        LexStringFormat::Did => "did",
+        LexStringFormat::Handle => "handle",
+        LexStringFormat::Language => "language",
+        LexStringFormat::Nsid => "nsid",
+        LexStringFormat::RecordKey => "record-key",
+        LexStringFormat::Tid => "tid",
+        LexStringFormat::Uri => "uri",
+    }
+}
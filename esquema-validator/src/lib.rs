@@ -1,14 +1,306 @@
-pub fn add(left: u64, right: u64) -> u64 {
-    left + right
-}
+//! Validates ATProto data against its lexicon schema.
+
+pub mod format;
+pub mod record;
+mod registry;
+pub mod rules;
+pub mod types;
+mod validate;
+mod validator;
+mod violation;
+
+pub use record::RecordError;
+pub use registry::{
+    DanglingRef, LexiconRegistry, RegistryError, ResolvedDef, find_schemas, qualify,
+};
+pub use validate::{AsI64, Validate};
+pub use validator::{Validator, parameters_object};
+pub use violation::{Constraint, ValidationError, Violation, join_path};
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use atrium_lex::LexiconDoc;
+    use serde_json::json;
+
+    fn status_validator() -> Validator {
+        let doc: LexiconDoc = serde_json::from_value(json!({
+            "lexicon": 1,
+            "id": "xyz.statusphere.status",
+            "defs": {
+                "main": {
+                    "type": "record",
+                    "key": "tid",
+                    "record": {
+                        "type": "object",
+                        "required": ["status", "createdAt"],
+                        "properties": {
+                            "status": {
+                                "type": "string",
+                                "minLength": 1,
+                                "maxGraphemes": 1,
+                                "maxLength": 32
+                            },
+                            "createdAt": { "type": "string", "format": "datetime" }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        Validator::new(vec![doc])
+    }
+
+    #[test]
+    fn valid_record() {
+        let record = json!({ "status": "🦀", "createdAt": "2025-01-01T00:00:00Z" });
+        assert!(
+            status_validator()
+                .validate("xyz.statusphere.status", &record)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn counts_graphemes_not_chars() {
+        let validator = status_validator();
+        // a single grapheme made of five chars and 18 bytes
+        let family = json!({ "status": "👨‍👩‍👧", "createdAt": "2025-01-01T00:00:00Z" });
+        assert!(
+            validator
+                .validate("xyz.statusphere.status", &family)
+                .is_ok()
+        );
+
+        let two = json!({ "status": "🦀🦀", "createdAt": "2025-01-01T00:00:00Z" });
+        let err = validator
+            .validate("xyz.statusphere.status", &two)
+            .unwrap_err();
+        assert_eq!(err.violations[0].constraint, Constraint::MaxGraphemes);
+    }
+
+    #[test]
+    fn reports_every_violation() {
+        let record = json!({ "status": "x".repeat(33) });
+        let err = status_validator()
+            .validate("xyz.statusphere.status", &record)
+            .unwrap_err();
+        let found = err
+            .violations
+            .iter()
+            .map(|v| (v.path.as_str(), v.constraint))
+            .collect::<Vec<_>>();
+        assert!(found.contains(&("/createdAt", Constraint::Required)));
+        assert!(found.contains(&("/status", Constraint::MaxLength)));
+    }
+
+    #[test]
+    fn registry_reports_dangling_refs_and_cycles() {
+        let doc: LexiconDoc = serde_json::from_value(json!({
+            "lexicon": 1,
+            "id": "com.example.defs",
+            "defs": {
+                "a": {
+                    "type": "object",
+                    "required": ["b"],
+                    "properties": { "b": { "type": "ref", "ref": "#b" } }
+                },
+                "b": {
+                    "type": "object",
+                    "required": ["a", "missing"],
+                    "properties": {
+                        "a": { "type": "ref", "ref": "#a" },
+                        "missing": { "type": "ref", "ref": "com.example.other#thing" }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let registry = LexiconRegistry::from_docs(vec![doc]);
+        assert!(registry.resolve("com.example.defs", "#a").is_some());
+        let dangling = registry.dangling_refs();
+        assert_eq!(dangling.len(), 1);
+        assert_eq!(dangling[0].r#ref, "com.example.other#thing");
+        assert_eq!(registry.cycles().len(), 1);
+    }
+
+    #[test]
+    fn validates_xrpc_parameters_and_inputs() {
+        let docs = [
+            json!({
+                "lexicon": 1,
+                "id": "com.example.listThings",
+                "defs": {
+                    "main": {
+                        "type": "query",
+                        "parameters": {
+                            "type": "params",
+                            "required": ["tags"],
+                            "properties": {
+                                "limit": { "type": "integer", "minimum": 1, "maximum": 100 },
+                                "tags": { "type": "array", "items": { "type": "string" } }
+                            }
+                        }
+                    }
+                }
+            }),
+            json!({
+                "lexicon": 1,
+                "id": "com.example.putThing",
+                "defs": {
+                    "main": {
+                        "type": "procedure",
+                        "parameters": {
+                            "type": "params",
+                            "properties": { "dryRun": { "type": "boolean" } }
+                        },
+                        "input": {
+                            "encoding": "application/json",
+                            "schema": {
+                                "type": "object",
+                                "required": ["name"],
+                                "properties": { "name": { "type": "string", "maxLength": 4 } }
+                            }
+                        }
+                    }
+                }
+            }),
+        ];
+        let validator = Validator::new(
+            docs.into_iter()
+                .map(|doc| serde_json::from_value(doc).unwrap())
+                .collect(),
+        );
+
+        assert!(
+            validator
+                .validate_parameters(
+                    "com.example.listThings",
+                    &json!({ "tags": ["a"], "limit": 5 })
+                )
+                .is_ok()
+        );
+        let err = validator
+            .validate_parameters("com.example.listThings", &json!({ "limit": 500 }))
+            .unwrap_err();
+        let found = err
+            .violations
+            .iter()
+            .map(|v| (v.path.as_str(), v.constraint))
+            .collect::<Vec<_>>();
+        assert!(found.contains(&("/tags", Constraint::Required)));
+        assert!(found.contains(&("/limit", Constraint::Maximum)));
+
+        assert!(
+            validator
+                .validate_parameters("com.example.putThing", &json!({ "dryRun": true }))
+                .is_ok()
+        );
+        let err = validator
+            .validate_parameters("com.example.putThing", &json!({ "dryRun": "yes" }))
+            .unwrap_err();
+        assert_eq!(err.violations[0].path, "/dryRun");
+
+        assert!(
+            validator
+                .validate_input("com.example.putThing", &json!({ "name": "crab" }))
+                .is_ok()
+        );
+        let err = validator
+            .validate_input("com.example.putThing", &json!({ "name": "lobster" }))
+            .unwrap_err();
+        assert_eq!(err.violations[0].constraint, Constraint::MaxLength);
+        assert!(
+            validator
+                .validate_input("com.example.listThings", &json!({}))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn record_conversions_name_what_does_not_fit() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Status {
+            status: String,
+            created_at: atrium_api::types::string::Datetime,
+        }
+
+        let unknown = |value| serde_json::from_value::<atrium_api::types::Unknown>(value).unwrap();
+        let valid = unknown(json!({
+            "$type": "xyz.statusphere.status",
+            "status": "🦀",
+            "createdAt": "2025-01-01T00:00:00Z"
+        }));
+        let status: Status = record::from_unknown(None, &valid).unwrap();
+        assert_eq!(status.status, "🦀");
+        assert!(record::to_unknown(Some("xyz.statusphere.status"), &status).is_ok());
+
+        let invalid = unknown(json!({
+            "$type": "xyz.statusphere.status",
+            "status": "🦀",
+            "createdAt": "yesterday"
+        }));
+        let err = record::from_unknown::<Status>(None, &invalid).unwrap_err();
+        assert_eq!(err.nsid, "xyz.statusphere.status");
+        assert_eq!(err.path, "createdAt");
+        let err = record::from_unknown::<Status>(Some("com.example.status"), &invalid).unwrap_err();
+        assert_eq!(err.nsid, "com.example.status");
+        assert!(
+            err.to_string()
+                .starts_with("invalid `com.example.status` record at `createdAt`")
+        );
+    }
+
+    #[test]
+    fn record_conversions_keep_blob_links() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Avatar {
+            image: atrium_api::types::BlobRef,
+        }
+
+        let blob = json!({
+            "$type": "blob",
+            "ref": { "$link": "bafkreibme22gw2h7y2h7tg2fhqotaqjucnbc24deqo72b6mkl2egezxhvy" },
+            "mimeType": "image/png",
+            "size": 1024
+        });
+        let avatar = Avatar {
+            image: serde_json::from_value(blob.clone()).unwrap(),
+        };
+        // a record built locally, its blob ref holding a CID link
+        let unknown = record::to_unknown(Some("com.example.avatar"), &avatar).unwrap();
+        assert_eq!(
+            record::from_unknown::<Avatar>(Some("com.example.avatar"), &unknown).unwrap(),
+            avatar
+        );
+        // a record received as JSON, its blob ref holding a `$link` object
+        let received =
+            serde_json::from_value::<atrium_api::types::Unknown>(json!({ "image": blob })).unwrap();
+        assert_eq!(
+            record::from_unknown::<Avatar>(Some("com.example.avatar"), &received).unwrap(),
+            avatar
+        );
+    }
+
+    #[test]
+    fn parses_at_uri() {
+        let uri: types::AtUri = "at://did:plc:abc123/xyz.statusphere.status/3lbzw5ekxn22a"
+            .parse()
+            .unwrap();
+        assert_eq!(uri.authority().as_ref(), "did:plc:abc123");
+        assert_eq!(uri.collection().unwrap().as_str(), "xyz.statusphere.status");
+        assert_eq!(uri.rkey().unwrap().as_str(), "3lbzw5ekxn22a");
+        assert!("https://example.com".parse::<types::AtUri>().is_err());
+    }
 
     #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
+    fn bounded_signed_integers_reject_out_of_range_values() {
+        type Rating = types::BoundedI8<-5, 5>;
+        let rating: Rating = serde_json::from_value(json!(-5)).unwrap();
+        assert_eq!(rating.get(), -5);
+        assert_eq!(serde_json::to_value(rating).unwrap(), json!(-5));
+        assert!(serde_json::from_value::<Rating>(json!(6)).is_err());
+        assert!(Rating::new(-6).is_err());
     }
 }
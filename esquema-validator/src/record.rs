@@ -0,0 +1,90 @@
+//! Fallible conversions between generated record types and [`Unknown`], the type records are
+//! sent and received as.
+
+use atrium_api::types::Unknown;
+use ipld_core::ipld::Ipld;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::{self, Display};
+
+/// A record that doesn't fit its generated type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordError {
+    /// The record's collection, empty when neither given nor found in its `$type`.
+    pub nsid: String,
+    /// The serde path of the value that doesn't fit, e.g. `createdAt`, `.` for the record itself.
+    pub path: String,
+    pub reason: String,
+}
+
+impl Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nsid = if self.nsid.is_empty() {
+            "unknown"
+        } else {
+            &self.nsid
+        };
+        write!(
+            f,
+            "invalid `{nsid}` record at `{}`: {}",
+            self.path, self.reason
+        )
+    }
+}
+
+impl Error for RecordError {}
+
+/// Converts `value` into a generated record type, e.g. the `value` of a record from `list_records`.
+///
+/// `nsid` is the record's collection, `None` taking it from the record's `$type`. The value goes
+/// through its IPLD data model rather than JSON, so blob and CID links decode whichever way the
+/// record was received.
+pub fn from_unknown<T: DeserializeOwned>(
+    nsid: Option<&str>,
+    value: &Unknown,
+) -> Result<T, RecordError> {
+    let ipld = ipld_core::serde::to_ipld(value)
+        .map_err(|err| record_error(nsid.unwrap_or_default(), ".", err))?;
+    let nsid = nsid.map_or_else(|| record_type(&ipld), String::from);
+    serde_path_to_error::deserialize(ipld)
+        .map_err(|err| record_error(nsid, err.path().to_string(), err.into_inner()))
+}
+
+/// Converts a generated record type into an [`Unknown`], e.g. for `create_record`.
+///
+/// `nsid` is the record's collection, `None` taking it from the record's `$type`.
+pub fn to_unknown<T: Serialize>(nsid: Option<&str>, record: &T) -> Result<Unknown, RecordError> {
+    let ipld =
+        serde_path_to_error::serialize(record, ipld_core::serde::Serializer).map_err(|err| {
+            record_error(
+                nsid.unwrap_or_default(),
+                err.path().to_string(),
+                err.into_inner(),
+            )
+        })?;
+    let nsid = nsid.map_or_else(|| record_type(&ipld), String::from);
+    Unknown::deserialize(ipld).map_err(|err| record_error(nsid, ".", err))
+}
+
+fn record_type(ipld: &Ipld) -> String {
+    match ipld {
+        Ipld::Map(map) => match map.get("$type") {
+            Some(Ipld::String(r#type)) => r#type.clone(),
+            _ => String::new(),
+        },
+        _ => String::new(),
+    }
+}
+
+fn record_error(
+    nsid: impl Into<String>,
+    path: impl Into<String>,
+    reason: impl Display,
+) -> RecordError {
+    RecordError {
+        nsid: nsid.into(),
+        path: path.into(),
+        reason: reason.to_string(),
+    }
+}
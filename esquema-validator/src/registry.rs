@@ -0,0 +1,394 @@
+use atrium_api::types::Unknown;
+use atrium_lex::LexiconDoc;
+use atrium_lex::lexicon::*;
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fmt::{self, Display};
+use std::fs::{File, read_dir};
+use std::path::{Path, PathBuf};
+
+/// An index of lexicon definitions by fully qualified name (`nsid#def`).
+#[derive(Debug, Clone, Default)]
+pub struct LexiconRegistry {
+    docs: BTreeMap<String, LexiconDoc>,
+}
+
+/// A definition found by [`LexiconRegistry::resolve`].
+#[derive(Debug, Clone, Copy)]
+pub struct ResolvedDef<'a> {
+    pub nsid: &'a str,
+    pub name: &'a str,
+    pub def: &'a LexUserType,
+}
+
+/// A reference whose target is not in the registry.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DanglingRef {
+    /// The fully qualified name of the definition containing the reference.
+    pub from: String,
+    /// Where in that definition the reference sits, e.g. `record/properties/subject`.
+    pub path: String,
+    /// The fully qualified target of the reference.
+    pub r#ref: String,
+}
+
+#[derive(Debug)]
+pub enum RegistryError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Parse {
+        source: String,
+        error: serde_json::Error,
+    },
+    DanglingRef(DanglingRef),
+    /// A cycle of required references, which no finite value can satisfy.
+    Cycle(Vec<String>),
+}
+
+impl Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            RegistryError::Parse { source, error } => {
+                write!(f, "{source} is not a valid lexicon document: {error}")
+            }
+            RegistryError::DanglingRef(dangling) => write!(
+                f,
+                "`{}` references `{}` at {}, which could not be found",
+                dangling.from, dangling.r#ref, dangling.path
+            ),
+            RegistryError::Cycle(cycle) => {
+                write!(f, "required reference cycle: {}", cycle.join(" -> "))
+            }
+        }
+    }
+}
+
+impl Error for RegistryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RegistryError::Io { source, .. } => Some(source),
+            RegistryError::Parse { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl LexiconRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_docs(docs: Vec<LexiconDoc>) -> Self {
+        let mut registry = Self::new();
+        for doc in docs {
+            registry.insert(doc);
+        }
+        registry
+    }
+
+    /// Loads every `.json` file under `dir`, recursively.
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self, RegistryError> {
+        let paths = find_schemas(dir.as_ref()).map_err(|source| RegistryError::Io {
+            path: dir.as_ref().to_path_buf(),
+            source,
+        })?;
+        let mut registry = Self::new();
+        for path in paths {
+            let file = File::open(&path).map_err(|source| RegistryError::Io {
+                path: path.clone(),
+                source,
+            })?;
+            let doc = serde_json::from_reader(file).map_err(|error| RegistryError::Parse {
+                source: path.display().to_string(),
+                error,
+            })?;
+            registry.insert(doc);
+        }
+        Ok(registry)
+    }
+
+    /// Loads `com.atproto.lexicon.schema` record values, e.g. from `list_records`.
+    pub fn from_records<'a>(
+        records: impl IntoIterator<Item = &'a Unknown>,
+    ) -> Result<Self, RegistryError> {
+        let mut registry = Self::new();
+        for (i, record) in records.into_iter().enumerate() {
+            let doc = serde_json::to_value(record)
+                .and_then(serde_json::from_value)
+                .map_err(|error| RegistryError::Parse {
+                    source: format!("record {i}"),
+                    error,
+                })?;
+            registry.insert(doc);
+        }
+        Ok(registry)
+    }
+
+    /// Adds a document, returning the one it replaced with the same NSID.
+    pub fn insert(&mut self, doc: LexiconDoc) -> Option<LexiconDoc> {
+        self.docs.insert(doc.id.clone(), doc)
+    }
+
+    pub fn get(&self, nsid: &str) -> Option<&LexiconDoc> {
+        self.docs.get(nsid)
+    }
+
+    pub fn contains(&self, nsid: &str) -> bool {
+        self.docs.contains_key(nsid)
+    }
+
+    pub fn docs(&self) -> impl Iterator<Item = &LexiconDoc> {
+        self.docs.values()
+    }
+
+    pub fn into_docs(self) -> Vec<LexiconDoc> {
+        self.docs.into_values().collect()
+    }
+
+    /// Every definition, keyed by its fully qualified name.
+    pub fn defs(&self) -> impl Iterator<Item = (String, &LexUserType)> {
+        self.docs.values().flat_map(|doc| {
+            doc.defs
+                .iter()
+                .map(move |(name, def)| (format!("{}#{name}", doc.id), def))
+        })
+    }
+
+    /// Resolves a local (`#def`) or remote (`nsid`, `nsid#def`) reference made from `nsid`.
+    pub fn resolve<'a>(&'a self, nsid: &str, r#ref: &str) -> Option<ResolvedDef<'a>> {
+        let qualified = qualify(nsid, r#ref);
+        let (nsid, name) = qualified.split_once('#')?;
+        let doc = self.docs.get(nsid)?;
+        let (name, def) = doc.defs.get_key_value(name)?;
+        Some(ResolvedDef {
+            nsid: &doc.id,
+            name,
+            def,
+        })
+    }
+
+    pub fn dangling_refs(&self) -> Vec<DanglingRef> {
+        let mut dangling = Vec::new();
+        for doc in self.docs.values() {
+            for (name, def) in &doc.defs {
+                for found in refs_in_def(def) {
+                    let r#ref = qualify(&doc.id, &found.r#ref);
+                    if self.resolve(&doc.id, &r#ref).is_none() {
+                        dangling.push(DanglingRef {
+                            from: format!("{}#{name}", doc.id),
+                            path: found.path,
+                            r#ref,
+                        });
+                    }
+                }
+            }
+        }
+        dangling
+    }
+
+    /// Finds cycles of required, non-array references between object definitions.
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        let mut edges: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for doc in self.docs.values() {
+            for (name, def) in &doc.defs {
+                let targets = refs_in_def(def)
+                    .into_iter()
+                    .filter(|found| found.required)
+                    .map(|found| qualify(&doc.id, &found.r#ref))
+                    .filter(|r#ref| self.resolve(&doc.id, r#ref).is_some())
+                    .collect();
+                edges.insert(format!("{}#{name}", doc.id), targets);
+            }
+        }
+        let mut cycles = Vec::new();
+        let mut done = HashSet::new();
+        for start in edges.keys() {
+            let mut stack = Vec::new();
+            find_cycles(start, &edges, &mut stack, &mut done, &mut cycles);
+        }
+        cycles
+    }
+
+    /// Reports every dangling reference and required reference cycle.
+    pub fn check(&self) -> Result<(), Vec<RegistryError>> {
+        let errors = self
+            .dangling_refs()
+            .into_iter()
+            .map(RegistryError::DanglingRef)
+            .chain(self.cycles().into_iter().map(RegistryError::Cycle))
+            .collect::<Vec<_>>();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl FromIterator<LexiconDoc> for LexiconRegistry {
+    fn from_iter<T: IntoIterator<Item = LexiconDoc>>(iter: T) -> Self {
+        Self::from_docs(iter.into_iter().collect())
+    }
+}
+
+/// Expands a reference made from `nsid` to its fully qualified `nsid#def` form.
+pub fn qualify(nsid: &str, r#ref: &str) -> String {
+    match r#ref.split_once('#') {
+        Some(("", name)) => format!("{nsid}#{name}"),
+        Some(_) => r#ref.to_string(),
+        None => format!("{}#main", r#ref),
+    }
+}
+
+struct FoundRef {
+    path: String,
+    r#ref: String,
+    required: bool,
+}
+
+fn refs_in_def(def: &LexUserType) -> Vec<FoundRef> {
+    let mut found = Vec::new();
+    match def {
+        LexUserType::Record(record) => {
+            let LexRecordRecord::Object(object) = &record.record;
+            refs_in_object(object, "record", &mut found);
+        }
+        LexUserType::XrpcQuery(query) => {
+            if let Some(output) = &query.output {
+                refs_in_body(&output.schema, "output/schema", &mut found);
+            }
+        }
+        LexUserType::XrpcProcedure(procedure) => {
+            if let Some(input) = &procedure.input {
+                refs_in_body(&input.schema, "input/schema", &mut found);
+            }
+            if let Some(output) = &procedure.output {
+                refs_in_body(&output.schema, "output/schema", &mut found);
+            }
+        }
+        LexUserType::XrpcSubscription(subscription) => {
+            if let Some(schema) = subscription
+                .message
+                .as_ref()
+                .and_then(|m| m.schema.as_ref())
+            {
+                let path = "message/schema";
+                match schema {
+                    LexXrpcSubscriptionMessageSchema::Ref(r#ref) => {
+                        push_ref(&r#ref.r#ref, path, false, &mut found)
+                    }
+                    LexXrpcSubscriptionMessageSchema::Union(union) => {
+                        refs_in_union(union, path, &mut found)
+                    }
+                    LexXrpcSubscriptionMessageSchema::Object(object) => {
+                        refs_in_object(object, path, &mut found)
+                    }
+                }
+            }
+        }
+        LexUserType::Array(array) => refs_in_array(array, "items", &mut found),
+        LexUserType::Object(object) => refs_in_object(object, "", &mut found),
+        _ => {}
+    }
+    found
+}
+
+fn refs_in_body(schema: &Option<LexXrpcBodySchema>, path: &str, found: &mut Vec<FoundRef>) {
+    match schema {
+        Some(LexXrpcBodySchema::Ref(r#ref)) => push_ref(&r#ref.r#ref, path, false, found),
+        Some(LexXrpcBodySchema::Union(union)) => refs_in_union(union, path, found),
+        Some(LexXrpcBodySchema::Object(object)) => refs_in_object(object, path, found),
+        None => {}
+    }
+}
+
+fn refs_in_object(object: &LexObject, path: &str, found: &mut Vec<FoundRef>) {
+    let required = object.required.as_deref().unwrap_or_default();
+    let nullable = object.nullable.as_deref().unwrap_or_default();
+    for (key, property) in &object.properties {
+        let path = if path.is_empty() {
+            format!("properties/{key}")
+        } else {
+            format!("{path}/properties/{key}")
+        };
+        match property {
+            LexObjectProperty::Ref(r#ref) => {
+                let required = required.contains(key) && !nullable.contains(key);
+                push_ref(&r#ref.r#ref, &path, required, found);
+            }
+            LexObjectProperty::Union(union) => refs_in_union(union, &path, found),
+            LexObjectProperty::Array(array) => {
+                refs_in_array(array, &format!("{path}/items"), found)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn refs_in_array(array: &LexArray, path: &str, found: &mut Vec<FoundRef>) {
+    match &array.items {
+        LexArrayItem::Ref(r#ref) => push_ref(&r#ref.r#ref, path, false, found),
+        LexArrayItem::Union(union) => refs_in_union(union, path, found),
+        _ => {}
+    }
+}
+
+fn refs_in_union(union: &LexRefUnion, path: &str, found: &mut Vec<FoundRef>) {
+    for (i, r#ref) in union.refs.iter().enumerate() {
+        push_ref(r#ref, &format!("{path}/refs/{i}"), false, found);
+    }
+}
+
+fn push_ref(r#ref: &str, path: &str, required: bool, found: &mut Vec<FoundRef>) {
+    found.push(FoundRef {
+        path: path.to_string(),
+        r#ref: r#ref.to_string(),
+        required,
+    });
+}
+
+fn find_cycles(
+    node: &str,
+    edges: &BTreeMap<String, Vec<String>>,
+    stack: &mut Vec<String>,
+    done: &mut HashSet<String>,
+    cycles: &mut Vec<Vec<String>>,
+) {
+    if let Some(start) = stack.iter().position(|n| n == node) {
+        let mut cycle = stack[start..].to_vec();
+        cycle.push(node.to_string());
+        cycles.push(cycle);
+        return;
+    }
+    if done.contains(node) {
+        return;
+    }
+    stack.push(node.to_string());
+    for next in edges.get(node).into_iter().flatten() {
+        find_cycles(next, edges, stack, done, cycles);
+    }
+    stack.pop();
+    done.insert(node.to_string());
+}
+
+/// Every `.json` file under `path`, recursively, or `path` itself if it is one.
+pub fn find_schemas(path: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut results = Vec::new();
+    walk_schemas(path, &mut results)?;
+    Ok(results)
+}
+
+fn walk_schemas(path: &Path, results: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if path.is_dir() {
+        for entry in read_dir(path)? {
+            walk_schemas(&entry?.path(), results)?;
+        }
+    } else if path.extension().and_then(OsStr::to_str) == Some("json") {
+        results.push(path.to_path_buf());
+    }
+    Ok(())
+}
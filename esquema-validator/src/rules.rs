@@ -0,0 +1,173 @@
+//! Primitive checks shared by [`Validator`](crate::Validator) and generated code.
+
+use crate::format;
+use crate::violation::{Constraint, Violation};
+use atrium_api::types::{BlobRef, TypedBlobRef};
+use atrium_lex::lexicon::LexStringFormat;
+use std::fmt::Display;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Lexicon string lengths (`minLength`/`maxLength`) count UTF-8 bytes, so pass `str::len` here.
+pub fn min_length(path: &str, length: usize, min: usize, violations: &mut Vec<Violation>) {
+    if length < min {
+        violations.push(Violation::new(
+            path,
+            Constraint::MinLength,
+            format!("length {length} is shorter than the minimum of {min}"),
+        ));
+    }
+}
+
+pub fn max_length(path: &str, length: usize, max: usize, violations: &mut Vec<Violation>) {
+    if length > max {
+        violations.push(Violation::new(
+            path,
+            Constraint::MaxLength,
+            format!("length {length} is longer than the maximum of {max}"),
+        ));
+    }
+}
+
+/// Counts extended grapheme clusters, so `"🦀"` and `"👨‍👩‍👧"` are both one grapheme.
+pub fn min_graphemes(path: &str, value: &str, min: usize, violations: &mut Vec<Violation>) {
+    let count = value.graphemes(true).count();
+    if count < min {
+        violations.push(Violation::new(
+            path,
+            Constraint::MinGraphemes,
+            format!("{count} graphemes is fewer than the minimum of {min}"),
+        ));
+    }
+}
+
+/// Counts extended grapheme clusters, so `"🦀"` and `"👨‍👩‍👧"` are both one grapheme.
+pub fn max_graphemes(path: &str, value: &str, max: usize, violations: &mut Vec<Violation>) {
+    // stop counting early, values far over the limit can be long
+    let count = value.graphemes(true).take(max + 1).count();
+    if count > max {
+        violations.push(Violation::new(
+            path,
+            Constraint::MaxGraphemes,
+            format!("more than the maximum of {max} graphemes"),
+        ));
+    }
+}
+
+pub fn format(
+    path: &str,
+    value: &str,
+    string_format: &LexStringFormat,
+    violations: &mut Vec<Violation>,
+) {
+    if let Err(reason) = format::check(string_format, value) {
+        violations.push(Violation::new(
+            path,
+            Constraint::Format,
+            format!("`{value}` is {reason} ({})", format::name(string_format)),
+        ));
+    }
+}
+
+pub fn minimum(path: &str, value: i64, min: i64, violations: &mut Vec<Violation>) {
+    if value < min {
+        violations.push(Violation::new(
+            path,
+            Constraint::Minimum,
+            format!("{value} is less than the minimum of {min}"),
+        ));
+    }
+}
+
+pub fn maximum(path: &str, value: i64, max: i64, violations: &mut Vec<Violation>) {
+    if value > max {
+        violations.push(Violation::new(
+            path,
+            Constraint::Maximum,
+            format!("{value} is greater than the maximum of {max}"),
+        ));
+    }
+}
+
+pub fn constant<T>(path: &str, value: &T, expected: &T, violations: &mut Vec<Violation>)
+where
+    T: PartialEq + Display + ?Sized,
+{
+    if value != expected {
+        violations.push(Violation::new(
+            path,
+            Constraint::Const,
+            format!("expected the constant {expected}, found {value}"),
+        ));
+    }
+}
+
+pub fn one_of<T>(path: &str, value: &T, allowed: &[T], violations: &mut Vec<Violation>)
+where
+    T: PartialEq + Display,
+{
+    if !allowed.contains(value) {
+        violations.push(Violation::new(
+            path,
+            Constraint::Enum,
+            format!("{value} is not one of the allowed values"),
+        ));
+    }
+}
+
+pub fn accept<S: AsRef<str>>(
+    path: &str,
+    mime_type: &str,
+    accept: &[S],
+    violations: &mut Vec<Violation>,
+) {
+    if !accept
+        .iter()
+        .any(|pattern| mime_matches(pattern.as_ref(), mime_type))
+    {
+        violations.push(Violation::new(
+            path,
+            Constraint::Accept,
+            format!("mime type `{mime_type}` is not accepted"),
+        ));
+    }
+}
+
+pub fn max_size(path: &str, size: usize, max: usize, violations: &mut Vec<Violation>) {
+    if size > max {
+        violations.push(Violation::new(
+            path,
+            Constraint::MaxSize,
+            format!("blob size {size} exceeds the maximum of {max}"),
+        ));
+    }
+}
+
+/// Checks a blob's mime type and, for typed blobs, its size.
+pub fn blob<S: AsRef<str>>(
+    path: &str,
+    blob: &BlobRef,
+    accept: Option<&[S]>,
+    max_size: Option<usize>,
+    violations: &mut Vec<Violation>,
+) {
+    let (mime_type, size) = match blob {
+        BlobRef::Typed(TypedBlobRef::Blob(blob)) => (&blob.mime_type, Some(blob.size)),
+        BlobRef::Untyped(blob) => (&blob.mime_type, None),
+    };
+    if let Some(patterns) = accept {
+        self::accept(path, mime_type, patterns, violations);
+    }
+    if let (Some(max), Some(size)) = (max_size, size) {
+        self::max_size(path, size, max, violations);
+    }
+}
+
+fn mime_matches(pattern: &str, mime_type: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        Some("*") => true,
+        Some(prefix) => mime_type
+            .split_once('/')
+            .is_some_and(|(kind, _)| kind == prefix),
+        None => pattern == mime_type,
+    }
+}
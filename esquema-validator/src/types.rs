@@ -0,0 +1,12 @@
+//! Types for lexicon formats and integer ranges that atrium_api does not provide.
+
+mod at_uri;
+mod bounded;
+mod uri;
+
+pub use at_uri::AtUri;
+pub use bounded::{BoundedI8, BoundedI16, BoundedI32, BoundedI64};
+pub use uri::Uri;
+
+/// The maximum length in bytes of `uri` and `at-uri` strings.
+pub const MAX_URI_LENGTH: usize = 8 * 1024;
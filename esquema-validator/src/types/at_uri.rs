@@ -0,0 +1,127 @@
+use super::MAX_URI_LENGTH;
+use atrium_api::types::string::{AtIdentifier, Nsid, RecordKey};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::fmt::{self, Display};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// A string in the lexicon `at-uri` format: `at://authority[/collection[/rkey]][#fragment]`.
+#[derive(Debug, Clone)]
+pub struct AtUri {
+    uri: String,
+    authority: AtIdentifier,
+    collection: Option<Nsid>,
+    rkey: Option<RecordKey>,
+}
+
+impl AtUri {
+    pub fn new(uri: String) -> Result<Self, &'static str> {
+        if uri.len() > MAX_URI_LENGTH {
+            return Err("AT-URI is too long");
+        }
+        let rest = uri
+            .strip_prefix("at://")
+            .ok_or("AT-URI must start with `at://`")?;
+        let path = rest.split_once('#').map_or(rest, |(path, _)| path);
+        if path.contains('?') {
+            return Err("AT-URI must not have a query");
+        }
+        let mut segments = path.split('/');
+        let authority = segments
+            .next()
+            .and_then(|authority| authority.parse::<AtIdentifier>().ok())
+            .ok_or("AT-URI authority must be a DID or handle")?;
+        let collection = segments
+            .next()
+            .map(|collection| collection.parse::<Nsid>())
+            .transpose()
+            .map_err(|_| "AT-URI collection must be an NSID")?;
+        let rkey = segments
+            .next()
+            .map(|rkey| rkey.parse::<RecordKey>())
+            .transpose()
+            .map_err(|_| "AT-URI record key is invalid")?;
+        if segments.next().is_some() {
+            return Err("AT-URI has too many path segments");
+        }
+        Ok(Self {
+            uri,
+            authority,
+            collection,
+            rkey,
+        })
+    }
+
+    /// The repository DID or handle.
+    pub fn authority(&self) -> &AtIdentifier {
+        &self.authority
+    }
+
+    pub fn collection(&self) -> Option<&Nsid> {
+        self.collection.as_ref()
+    }
+
+    pub fn rkey(&self) -> Option<&RecordKey> {
+        self.rkey.as_ref()
+    }
+
+    pub fn fragment(&self) -> Option<&str> {
+        self.uri.split_once('#').map(|(_, fragment)| fragment)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.uri
+    }
+}
+
+impl PartialEq for AtUri {
+    fn eq(&self, other: &Self) -> bool {
+        self.uri == other.uri
+    }
+}
+
+impl Eq for AtUri {}
+
+impl Hash for AtUri {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.uri.hash(state);
+    }
+}
+
+impl FromStr for AtUri {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s.to_string())
+    }
+}
+
+impl AsRef<str> for AtUri {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Display for AtUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.uri)
+    }
+}
+
+impl From<AtUri> for String {
+    fn from(value: AtUri) -> Self {
+        value.uri
+    }
+}
+
+impl Serialize for AtUri {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.uri)
+    }
+}
+
+impl<'de> Deserialize<'de> for AtUri {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::new(String::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,66 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::fmt::{self, Display};
+
+/// Signed counterparts of atrium_api's `BoundedU*` types, for lexicon integers whose minimum is negative.
+macro_rules! bounded_signed {
+    ($($name:ident($prim:ty)),* $(,)?) => {
+        $(
+            #[doc = concat!("An `", stringify!($prim), "` within `MIN..=MAX`, which is checked when it is created or deserialized.")]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+            pub struct $name<const MIN: $prim, const MAX: $prim>($prim);
+
+            impl<const MIN: $prim, const MAX: $prim> $name<MIN, MAX> {
+                pub fn new(value: $prim) -> Result<Self, String> {
+                    if (MIN..=MAX).contains(&value) {
+                        Ok(Self(value))
+                    } else {
+                        Err(format!("value is not within {MIN}..={MAX}: {value}"))
+                    }
+                }
+
+                pub fn get(&self) -> $prim {
+                    self.0
+                }
+            }
+
+            impl<const MIN: $prim, const MAX: $prim> TryFrom<$prim> for $name<MIN, MAX> {
+                type Error = String;
+
+                fn try_from(value: $prim) -> Result<Self, Self::Error> {
+                    Self::new(value)
+                }
+            }
+
+            impl<const MIN: $prim, const MAX: $prim> From<$name<MIN, MAX>> for $prim {
+                fn from(value: $name<MIN, MAX>) -> Self {
+                    value.0
+                }
+            }
+
+            impl<const MIN: $prim, const MAX: $prim> Display for $name<MIN, MAX> {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    self.0.fmt(f)
+                }
+            }
+
+            impl<const MIN: $prim, const MAX: $prim> Serialize for $name<MIN, MAX> {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    self.0.serialize(serializer)
+                }
+            }
+
+            impl<'de, const MIN: $prim, const MAX: $prim> Deserialize<'de> for $name<MIN, MAX> {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    Self::new(<$prim>::deserialize(deserializer)?).map_err(de::Error::custom)
+                }
+            }
+        )*
+    };
+}
+
+bounded_signed!(
+    BoundedI8(i8),
+    BoundedI16(i16),
+    BoundedI32(i32),
+    BoundedI64(i64)
+);
@@ -0,0 +1,78 @@
+use super::MAX_URI_LENGTH;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// A string in the lexicon `uri` format: any URI with a scheme, e.g. `https://example.com`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Uri(String);
+
+impl Uri {
+    pub fn new(uri: String) -> Result<Self, &'static str> {
+        if uri.len() > MAX_URI_LENGTH {
+            return Err("URI is too long");
+        }
+        let Some((scheme, rest)) = uri.split_once(':') else {
+            return Err("URI must have a scheme");
+        };
+        let mut chars = scheme.chars();
+        if !chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+            || !chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        {
+            return Err("URI scheme is invalid");
+        }
+        if rest.is_empty() {
+            return Err("URI must have content after the scheme");
+        }
+        if uri.chars().any(char::is_whitespace) {
+            return Err("URI must not contain whitespace");
+        }
+        Ok(Self(uri))
+    }
+
+    pub fn scheme(&self) -> &str {
+        self.0.split_once(':').map_or("", |(scheme, _)| scheme)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Uri {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s.to_string())
+    }
+}
+
+impl AsRef<str> for Uri {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Display for Uri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<Uri> for String {
+    fn from(value: Uri) -> Self {
+        value.0
+    }
+}
+
+impl Serialize for Uri {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Uri {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::new(String::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,241 @@
+use crate::types::{AtUri, BoundedI8, BoundedI16, BoundedI32, BoundedI64, Uri};
+use crate::violation::{ValidationError, Violation, join_path};
+use atrium_api::types::string::{
+    AtIdentifier, Cid, Datetime, Did, Handle, Language, Nsid, RecordKey, Tid,
+};
+use atrium_api::types::{
+    BlobRef, BoundedU8, BoundedU16, BoundedU32, BoundedU64, CidLink, LimitedNonZeroU8,
+    LimitedNonZeroU16, LimitedNonZeroU32, LimitedNonZeroU64, LimitedU8, LimitedU16, LimitedU32,
+    LimitedU64, Object, Union, Unknown,
+};
+use std::num::{NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64};
+
+/// Checks a value against the constraints of the lexicon it was generated from.
+///
+/// esquema-codegen implements this for every generated object and union.
+pub trait Validate {
+    /// Appends every violation found in `self` to `violations`, prefixing paths with `path`.
+    fn validate_at(&self, path: &str, violations: &mut Vec<Violation>);
+
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut violations = Vec::new();
+        self.validate_at("", &mut violations);
+        ValidationError::check(violations)
+    }
+}
+
+impl<T: Validate> Validate for Object<T> {
+    fn validate_at(&self, path: &str, violations: &mut Vec<Violation>) {
+        self.data.validate_at(path, violations);
+    }
+}
+
+impl<T: Validate> Validate for Union<T> {
+    fn validate_at(&self, path: &str, violations: &mut Vec<Violation>) {
+        // members outside of an open union are not known, so there is nothing to check
+        if let Union::Refs(refs) = self {
+            refs.validate_at(path, violations);
+        }
+    }
+}
+
+impl<T: Validate + ?Sized> Validate for Box<T> {
+    fn validate_at(&self, path: &str, violations: &mut Vec<Violation>) {
+        self.as_ref().validate_at(path, violations);
+    }
+}
+
+impl<T: Validate> Validate for Option<T> {
+    fn validate_at(&self, path: &str, violations: &mut Vec<Violation>) {
+        if let Some(value) = self {
+            value.validate_at(path, violations);
+        }
+    }
+}
+
+impl<T: Validate> Validate for Vec<T> {
+    fn validate_at(&self, path: &str, violations: &mut Vec<Violation>) {
+        for (i, item) in self.iter().enumerate() {
+            item.validate_at(&join_path(path, i), violations);
+        }
+    }
+}
+
+/// Types whose constraints are already enforced by the type itself.
+macro_rules! validate_noop {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl Validate for $ty {
+                fn validate_at(&self, _path: &str, _violations: &mut Vec<Violation>) {}
+            }
+        )*
+    };
+}
+
+validate_noop!(
+    bool,
+    i8,
+    i16,
+    i32,
+    i64,
+    u8,
+    u16,
+    u32,
+    u64,
+    usize,
+    NonZeroU8,
+    NonZeroU16,
+    NonZeroU32,
+    NonZeroU64,
+    String,
+    AtIdentifier,
+    Cid,
+    Datetime,
+    Did,
+    Handle,
+    Language,
+    Nsid,
+    RecordKey,
+    Tid,
+    AtUri,
+    Uri,
+    BlobRef,
+    CidLink,
+    Unknown,
+);
+
+macro_rules! validate_noop_limited {
+    ($($ty:ident<$prim:ty>),* $(,)?) => {
+        $(
+            impl<const MAX: $prim> Validate for $ty<MAX> {
+                fn validate_at(&self, _path: &str, _violations: &mut Vec<Violation>) {}
+            }
+        )*
+    };
+}
+
+validate_noop_limited!(
+    LimitedU8<u8>,
+    LimitedU16<u16>,
+    LimitedU32<u32>,
+    LimitedU64<u64>,
+    LimitedNonZeroU8<u8>,
+    LimitedNonZeroU16<u16>,
+    LimitedNonZeroU32<u32>,
+    LimitedNonZeroU64<u64>,
+);
+
+macro_rules! validate_noop_bounded {
+    ($($ty:ident<$prim:ty>),* $(,)?) => {
+        $(
+            impl<const MIN: $prim, const MAX: $prim> Validate for $ty<MIN, MAX> {
+                fn validate_at(&self, _path: &str, _violations: &mut Vec<Violation>) {}
+            }
+        )*
+    };
+}
+
+validate_noop_bounded!(
+    BoundedU8<u8>,
+    BoundedU16<u16>,
+    BoundedU32<u32>,
+    BoundedU64<u64>,
+    BoundedI8<i8>,
+    BoundedI16<i16>,
+    BoundedI32<i32>,
+    BoundedI64<i64>,
+);
+
+/// Widens any integer type esquema-codegen emits so lexicon bounds can be checked uniformly.
+pub trait AsI64 {
+    fn as_i64(&self) -> i64;
+}
+
+macro_rules! as_i64_primitive {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl AsI64 for $ty {
+                fn as_i64(&self) -> i64 {
+                    i64::try_from(*self).unwrap_or(i64::MAX)
+                }
+            }
+        )*
+    };
+}
+
+as_i64_primitive!(i8, i16, i32, i64, u8, u16, u32, u64, usize);
+
+macro_rules! as_i64_non_zero {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl AsI64 for $ty {
+                fn as_i64(&self) -> i64 {
+                    self.get().as_i64()
+                }
+            }
+        )*
+    };
+}
+
+as_i64_non_zero!(NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64);
+
+macro_rules! as_i64_limited {
+    ($($ty:ident<$prim:ty>),* $(,)?) => {
+        $(
+            impl<const MAX: $prim> AsI64 for $ty<MAX> {
+                fn as_i64(&self) -> i64 {
+                    <$prim>::from(*self).as_i64()
+                }
+            }
+        )*
+    };
+}
+
+as_i64_limited!(
+    LimitedU8<u8>,
+    LimitedU16<u16>,
+    LimitedU32<u32>,
+    LimitedU64<u64>
+);
+
+macro_rules! as_i64_limited_non_zero {
+    ($($ty:ident<$prim:ty, $non_zero:ty>),* $(,)?) => {
+        $(
+            impl<const MAX: $prim> AsI64 for $ty<MAX> {
+                fn as_i64(&self) -> i64 {
+                    <$non_zero>::from(*self).as_i64()
+                }
+            }
+        )*
+    };
+}
+
+as_i64_limited_non_zero!(
+    LimitedNonZeroU8<u8, NonZeroU8>,
+    LimitedNonZeroU16<u16, NonZeroU16>,
+    LimitedNonZeroU32<u32, NonZeroU32>,
+    LimitedNonZeroU64<u64, NonZeroU64>,
+);
+
+macro_rules! as_i64_bounded {
+    ($($ty:ident<$prim:ty>),* $(,)?) => {
+        $(
+            impl<const MIN: $prim, const MAX: $prim> AsI64 for $ty<MIN, MAX> {
+                fn as_i64(&self) -> i64 {
+                    <$prim>::from(*self).as_i64()
+                }
+            }
+        )*
+    };
+}
+
+as_i64_bounded!(
+    BoundedU8<u8>,
+    BoundedU16<u16>,
+    BoundedU32<u32>,
+    BoundedU64<u64>,
+    BoundedI8<i8>,
+    BoundedI16<i16>,
+    BoundedI32<i32>,
+    BoundedI64<i64>,
+);
@@ -0,0 +1,451 @@
+use crate::registry::{LexiconRegistry, qualify};
+use crate::rules;
+use crate::violation::{Constraint, ValidationError, Violation, join_path};
+use atrium_api::types::Unknown;
+use atrium_lex::LexiconDoc;
+use atrium_lex::lexicon::*;
+use serde_json::Value;
+
+/// Validates JSON data against a set of lexicon documents.
+pub struct Validator {
+    registry: LexiconRegistry,
+    strict_known_values: bool,
+}
+
+impl Validator {
+    pub fn new(docs: Vec<LexiconDoc>) -> Self {
+        Self::from_registry(LexiconRegistry::from_docs(docs))
+    }
+
+    pub fn from_registry(registry: LexiconRegistry) -> Self {
+        Self {
+            registry,
+            strict_known_values: false,
+        }
+    }
+
+    pub fn registry(&self) -> &LexiconRegistry {
+        &self.registry
+    }
+
+    /// `knownValues` is an open set in the lexicon spec, so by default values outside of it are
+    /// accepted. Setting this reports them as violations instead.
+    pub fn strict_known_values(mut self, strict: bool) -> Self {
+        self.strict_known_values = strict;
+        self
+    }
+
+    /// Validates `value` against the `main` definition of `nsid`, usually a record.
+    pub fn validate(&self, nsid: &str, value: &Value) -> Result<(), ValidationError> {
+        self.validate_ref(nsid, value)
+    }
+
+    /// Validates an [`Unknown`], e.g. the `value` of a record returned by `list_records`.
+    pub fn validate_unknown(&self, nsid: &str, value: &Unknown) -> Result<(), ValidationError> {
+        let value = serde_json::to_value(value).map_err(|e| ValidationError {
+            violations: vec![Violation::new("", Constraint::Type, e.to_string())],
+        })?;
+        self.validate(nsid, &value)
+    }
+
+    /// Validates `value` against a fully qualified reference such as `nsid#def`.
+    pub fn validate_ref(&self, r#ref: &str, value: &Value) -> Result<(), ValidationError> {
+        let mut walker = Walker {
+            validator: self,
+            violations: Vec::new(),
+        };
+        walker.r#ref("", "", r#ref, value);
+        ValidationError::check(walker.violations)
+    }
+
+    /// Validates `value` against a single property schema, resolving refs in it from `nsid`, e.g. a
+    /// lexicon's own `default` for one of its properties.
+    pub fn validate_property(
+        &self,
+        nsid: &str,
+        property: &LexObjectProperty,
+        value: &Value,
+    ) -> Result<(), ValidationError> {
+        let mut walker = Walker {
+            validator: self,
+            violations: Vec::new(),
+        };
+        walker.property("", nsid, property, value);
+        ValidationError::check(walker.violations)
+    }
+
+    /// Validates the parameters of the query or procedure `nsid`, already typed as a JSON object,
+    /// e.g. `{"limit": 10}` rather than `{"limit": "10"}`.
+    pub fn validate_parameters(&self, nsid: &str, value: &Value) -> Result<(), ValidationError> {
+        let mut walker = Walker {
+            validator: self,
+            violations: Vec::new(),
+        };
+        match self.registry.get(nsid).and_then(|doc| doc.defs.get("main")) {
+            Some(LexUserType::XrpcQuery(query)) => {
+                if let Some(LexXrpcQueryParameter::Params(parameters)) = &query.parameters {
+                    walker.object("", nsid, &parameters_object(parameters), value);
+                }
+            }
+            Some(LexUserType::XrpcProcedure(procedure)) => {
+                if let Some(LexXrpcProcedureParameter::Params(parameters)) = &procedure.parameters {
+                    walker.object("", nsid, &parameters_object(parameters), value);
+                }
+            }
+            _ => walker.violation(
+                "",
+                Constraint::Ref,
+                format!("`{nsid}` is not a query or procedure"),
+            ),
+        }
+        ValidationError::check(walker.violations)
+    }
+
+    /// Validates the JSON input of the procedure `nsid`.
+    pub fn validate_input(&self, nsid: &str, value: &Value) -> Result<(), ValidationError> {
+        let mut walker = Walker {
+            validator: self,
+            violations: Vec::new(),
+        };
+        match self.registry.get(nsid).and_then(|doc| doc.defs.get("main")) {
+            Some(LexUserType::XrpcProcedure(procedure)) => {
+                match procedure
+                    .input
+                    .as_ref()
+                    .and_then(|input| input.schema.as_ref())
+                {
+                    Some(LexXrpcBodySchema::Ref(r#ref)) => {
+                        walker.r#ref("", nsid, &r#ref.r#ref, value)
+                    }
+                    Some(LexXrpcBodySchema::Union(union)) => walker.union("", nsid, union, value),
+                    Some(LexXrpcBodySchema::Object(object)) => {
+                        walker.object("", nsid, object, value)
+                    }
+                    None => {}
+                }
+            }
+            _ => walker.violation("", Constraint::Ref, format!("`{nsid}` is not a procedure")),
+        }
+        ValidationError::check(walker.violations)
+    }
+}
+
+/// Parameters are a subset of an object's properties, so they are checked, and generated, as one.
+pub fn parameters_object(parameters: &LexXrpcParameters) -> LexObject {
+    let properties = parameters
+        .properties
+        .iter()
+        .map(|(key, property)| {
+            let property = match property {
+                LexXrpcParametersProperty::Boolean(boolean) => {
+                    LexObjectProperty::Boolean(boolean.clone())
+                }
+                LexXrpcParametersProperty::Integer(integer) => {
+                    LexObjectProperty::Integer(integer.clone())
+                }
+                LexXrpcParametersProperty::String(string) => {
+                    LexObjectProperty::String(string.clone())
+                }
+                LexXrpcParametersProperty::Unknown(unknown) => {
+                    LexObjectProperty::Unknown(unknown.clone())
+                }
+                LexXrpcParametersProperty::Array(array) => LexObjectProperty::Array(LexArray {
+                    description: array.description.clone(),
+                    items: match &array.items {
+                        LexPrimitiveArrayItem::Boolean(b) => LexArrayItem::Boolean(b.clone()),
+                        LexPrimitiveArrayItem::Integer(i) => LexArrayItem::Integer(i.clone()),
+                        LexPrimitiveArrayItem::String(s) => LexArrayItem::String(s.clone()),
+                        LexPrimitiveArrayItem::Unknown(u) => LexArrayItem::Unknown(u.clone()),
+                    },
+                    min_length: array.min_length,
+                    max_length: array.max_length,
+                }),
+            };
+            (key.clone(), property)
+        })
+        .collect();
+    LexObject {
+        description: parameters.description.clone(),
+        required: parameters.required.clone(),
+        nullable: None,
+        properties,
+    }
+}
+
+struct Walker<'a> {
+    validator: &'a Validator,
+    violations: Vec<Violation>,
+}
+
+impl Walker<'_> {
+    fn violation(&mut self, path: &str, constraint: Constraint, message: impl Into<String>) {
+        self.violations
+            .push(Violation::new(path, constraint, message));
+    }
+
+    fn type_mismatch(&mut self, path: &str, expected: &str, value: &Value) {
+        self.violation(
+            path,
+            Constraint::Type,
+            format!("expected {expected}, found {}", json_type(value)),
+        );
+    }
+
+    fn user_type(&mut self, path: &str, nsid: &str, def: &LexUserType, value: &Value) {
+        match def {
+            LexUserType::Record(record) => {
+                let LexRecordRecord::Object(object) = &record.record;
+                self.object(path, nsid, object, value);
+            }
+            LexUserType::Object(object) => self.object(path, nsid, object, value),
+            LexUserType::Array(array) => self.array(path, nsid, array, value),
+            LexUserType::Blob(blob) => self.blob(path, blob, value),
+            LexUserType::Boolean(boolean) => self.boolean(path, boolean, value),
+            LexUserType::Integer(integer) => self.integer(path, integer, value),
+            LexUserType::String(string) => self.string(path, string, value),
+            LexUserType::Bytes(bytes) => self.bytes(path, bytes, value),
+            LexUserType::CidLink(_) => self.cid_link(path, value),
+            LexUserType::Unknown(_) => self.unknown(path, value),
+            // Tokens and XRPC definitions do not describe data values.
+            _ => {}
+        }
+    }
+
+    fn object(&mut self, path: &str, nsid: &str, object: &LexObject, value: &Value) {
+        let Value::Object(map) = value else {
+            return self.type_mismatch(path, "object", value);
+        };
+        let required = object.required.as_deref().unwrap_or_default();
+        let nullable = object.nullable.as_deref().unwrap_or_default();
+        for key in required {
+            if !map.contains_key(key) {
+                self.violation(
+                    &join_path(path, key),
+                    Constraint::Required,
+                    format!("required property `{key}` is missing"),
+                );
+            }
+        }
+        for (key, property) in &object.properties {
+            let Some(value) = map.get(key) else {
+                continue;
+            };
+            let path = join_path(path, key);
+            if value.is_null() {
+                if !nullable.contains(key) {
+                    self.violation(
+                        &path,
+                        Constraint::Nullable,
+                        format!("property `{key}` is not nullable"),
+                    );
+                }
+                continue;
+            }
+            self.property(&path, nsid, property, value);
+        }
+    }
+
+    fn property(&mut self, path: &str, nsid: &str, property: &LexObjectProperty, value: &Value) {
+        match property {
+            LexObjectProperty::Ref(r#ref) => self.r#ref(path, nsid, &r#ref.r#ref, value),
+            LexObjectProperty::Union(union) => self.union(path, nsid, union, value),
+            LexObjectProperty::Bytes(bytes) => self.bytes(path, bytes, value),
+            LexObjectProperty::CidLink(_) => self.cid_link(path, value),
+            LexObjectProperty::Array(array) => self.array(path, nsid, array, value),
+            LexObjectProperty::Blob(blob) => self.blob(path, blob, value),
+            LexObjectProperty::Boolean(boolean) => self.boolean(path, boolean, value),
+            LexObjectProperty::Integer(integer) => self.integer(path, integer, value),
+            LexObjectProperty::String(string) => self.string(path, string, value),
+            LexObjectProperty::Unknown(_) => self.unknown(path, value),
+        }
+    }
+
+    fn array(&mut self, path: &str, nsid: &str, array: &LexArray, value: &Value) {
+        let Value::Array(items) = value else {
+            return self.type_mismatch(path, "array", value);
+        };
+        if let Some(min) = array.min_length {
+            rules::min_length(path, items.len(), min, &mut self.violations);
+        }
+        if let Some(max) = array.max_length {
+            rules::max_length(path, items.len(), max, &mut self.violations);
+        }
+        for (i, item) in items.iter().enumerate() {
+            let path = join_path(path, i);
+            match &array.items {
+                LexArrayItem::Boolean(boolean) => self.boolean(&path, boolean, item),
+                LexArrayItem::Integer(integer) => self.integer(&path, integer, item),
+                LexArrayItem::String(string) => self.string(&path, string, item),
+                LexArrayItem::Unknown(_) => self.unknown(&path, item),
+                LexArrayItem::Bytes(bytes) => self.bytes(&path, bytes, item),
+                LexArrayItem::CidLink(_) => self.cid_link(&path, item),
+                LexArrayItem::Blob(blob) => self.blob(&path, blob, item),
+                LexArrayItem::Ref(r#ref) => self.r#ref(&path, nsid, &r#ref.r#ref, item),
+                LexArrayItem::Union(union) => self.union(&path, nsid, union, item),
+            }
+        }
+    }
+
+    fn r#ref(&mut self, path: &str, nsid: &str, r#ref: &str, value: &Value) {
+        let validator = self.validator;
+        match validator.registry.resolve(nsid, r#ref) {
+            Some(resolved) => self.user_type(path, resolved.nsid, resolved.def, value),
+            None => self.violation(
+                path,
+                Constraint::Ref,
+                format!("reference `{}` could not be resolved", r#ref),
+            ),
+        }
+    }
+
+    fn union(&mut self, path: &str, nsid: &str, union: &LexRefUnion, value: &Value) {
+        let Value::Object(map) = value else {
+            return self.type_mismatch(path, "object", value);
+        };
+        let Some(r#type) = map.get("$type").and_then(Value::as_str) else {
+            return self.violation(
+                path,
+                Constraint::Union,
+                "union members must have a `$type` property",
+            );
+        };
+        let r#type = qualify(nsid, r#type);
+        match union
+            .refs
+            .iter()
+            .find(|r#ref| qualify(nsid, r#ref) == r#type)
+        {
+            Some(r#ref) => self.r#ref(path, nsid, r#ref, value),
+            None if union.closed.unwrap_or_default() => self.violation(
+                path,
+                Constraint::Union,
+                format!("`{}` is not a member of this closed union", r#type),
+            ),
+            None => {}
+        }
+    }
+
+    fn string(&mut self, path: &str, string: &LexString, value: &Value) {
+        let Value::String(s) = value else {
+            return self.type_mismatch(path, "string", value);
+        };
+        if let Some(format) = &string.format {
+            rules::format(path, s, format, &mut self.violations);
+        }
+        if let Some(min) = string.min_length {
+            rules::min_length(path, s.len(), min, &mut self.violations);
+        }
+        if let Some(max) = string.max_length {
+            rules::max_length(path, s.len(), max, &mut self.violations);
+        }
+        if let Some(min) = string.min_graphemes {
+            rules::min_graphemes(path, s, min, &mut self.violations);
+        }
+        if let Some(max) = string.max_graphemes {
+            rules::max_graphemes(path, s, max, &mut self.violations);
+        }
+        if let Some(expected) = &string.r#const {
+            rules::constant(path, s, expected, &mut self.violations);
+        }
+        if let Some(allowed) = &string.r#enum {
+            rules::one_of(path, s, allowed, &mut self.violations);
+        }
+        if let Some(known_values) = &string.known_values {
+            if self.validator.strict_known_values && !known_values.contains(s) {
+                self.violation(
+                    path,
+                    Constraint::KnownValues,
+                    format!("`{s}` is not one of the known values"),
+                );
+            }
+        }
+    }
+
+    fn integer(&mut self, path: &str, integer: &LexInteger, value: &Value) {
+        let Some(n) = value.as_i64() else {
+            return self.type_mismatch(path, "integer", value);
+        };
+        if let Some(min) = integer.minimum {
+            rules::minimum(path, n, min, &mut self.violations);
+        }
+        if let Some(max) = integer.maximum {
+            rules::maximum(path, n, max, &mut self.violations);
+        }
+        if let Some(expected) = &integer.r#const {
+            rules::constant(path, &n, expected, &mut self.violations);
+        }
+        if let Some(allowed) = &integer.r#enum {
+            rules::one_of(path, &n, allowed, &mut self.violations);
+        }
+    }
+
+    fn boolean(&mut self, path: &str, boolean: &LexBoolean, value: &Value) {
+        let Some(b) = value.as_bool() else {
+            return self.type_mismatch(path, "boolean", value);
+        };
+        if let Some(expected) = &boolean.r#const {
+            rules::constant(path, &b, expected, &mut self.violations);
+        }
+    }
+
+    fn bytes(&mut self, path: &str, bytes: &LexBytes, value: &Value) {
+        let Some(encoded) = single_key(value, "$bytes").and_then(Value::as_str) else {
+            return self.type_mismatch(path, "bytes object", value);
+        };
+        // base64 without padding: every 4 characters carry 3 bytes
+        let length = encoded.trim_end_matches('=').len() * 3 / 4;
+        if let Some(min) = bytes.min_length {
+            rules::min_length(path, length, min, &mut self.violations);
+        }
+        if let Some(max) = bytes.max_length {
+            rules::max_length(path, length, max, &mut self.violations);
+        }
+    }
+
+    fn cid_link(&mut self, path: &str, value: &Value) {
+        if single_key(value, "$link").and_then(Value::as_str).is_none() {
+            self.type_mismatch(path, "cid-link object", value);
+        }
+    }
+
+    fn blob(&mut self, path: &str, blob: &LexBlob, value: &Value) {
+        let Value::Object(map) = value else {
+            return self.type_mismatch(path, "blob", value);
+        };
+        let Some(mime_type) = map.get("mimeType").and_then(Value::as_str) else {
+            return self.type_mismatch(path, "blob", value);
+        };
+        if let Some(accept) = &blob.accept {
+            rules::accept(path, mime_type, accept, &mut self.violations);
+        }
+        // legacy blobs (`{"cid": ..., "mimeType": ...}`) do not carry a size
+        if let (Some(max), Some(size)) = (blob.max_size, map.get("size").and_then(Value::as_u64)) {
+            rules::max_size(path, size as usize, max, &mut self.violations);
+        }
+    }
+
+    fn unknown(&mut self, path: &str, value: &Value) {
+        if !value.is_object() {
+            self.type_mismatch(path, "object", value);
+        }
+    }
+}
+
+fn single_key<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    match value {
+        Value::Object(map) if map.len() == 1 => map.get(key),
+        _ => None,
+    }
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
@@ -0,0 +1,118 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+
+/// The lexicon constraint a value failed to satisfy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Constraint {
+    Required,
+    Nullable,
+    Type,
+    MinLength,
+    MaxLength,
+    MinGraphemes,
+    MaxGraphemes,
+    Format,
+    Minimum,
+    Maximum,
+    Const,
+    Enum,
+    KnownValues,
+    Accept,
+    MaxSize,
+    Ref,
+    Union,
+}
+
+impl Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Constraint::Required => "required",
+            Constraint::Nullable => "nullable",
+            Constraint::Type => "type",
+            Constraint::MinLength => "minLength",
+            Constraint::MaxLength => "maxLength",
+            Constraint::MinGraphemes => "minGraphemes",
+            Constraint::MaxGraphemes => "maxGraphemes",
+            Constraint::Format => "format",
+            Constraint::Minimum => "minimum",
+            Constraint::Maximum => "maximum",
+            Constraint::Const => "const",
+            Constraint::Enum => "enum",
+            Constraint::KnownValues => "knownValues",
+            Constraint::Accept => "accept",
+            Constraint::MaxSize => "maxSize",
+            Constraint::Ref => "ref",
+            Constraint::Union => "union",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A single failed check, located by the JSON pointer of the offending value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub path: String,
+    pub constraint: Constraint,
+    pub message: String,
+}
+
+impl Violation {
+    pub fn new(
+        path: impl Into<String>,
+        constraint: Constraint,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            constraint,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "/"
+        } else {
+            &self.path
+        };
+        write!(f, "{path}: {} ({})", self.message, self.constraint)
+    }
+}
+
+/// Every violation found while validating a value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub violations: Vec<Violation>,
+}
+
+impl ValidationError {
+    /// Returns `Ok(())` when no violations were collected.
+    pub fn check(violations: Vec<Violation>) -> Result<(), Self> {
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(Self { violations })
+        }
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} lexicon violation(s)", self.violations.len())?;
+        for violation in &self.violations {
+            write!(f, "\n  {violation}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ValidationError {}
+
+/// Appends `segment` to a JSON pointer, escaping it per RFC 6901.
+pub fn join_path(path: &str, segment: impl Display) -> String {
+    let segment = segment.to_string().replace('~', "~0").replace('/', "~1");
+    format!("{path}/{segment}")
+}